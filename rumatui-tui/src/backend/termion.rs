@@ -12,6 +12,8 @@ where
     W: Write,
 {
     stdout: W,
+    /// Write the links of cells as OSC 8 hyperlinks.
+    hyperlinks: bool,
 }
 
 impl<W> TermionBackend<W>
@@ -19,7 +21,16 @@ where
     W: Write,
 {
     pub fn new(stdout: W) -> TermionBackend<W> {
-        TermionBackend { stdout }
+        TermionBackend {
+            stdout,
+            hyperlinks: false,
+        }
+    }
+
+    /// Turns on OSC 8 hyperlinks, only do this when the terminal supports them.
+    pub fn with_hyperlinks(mut self, hyperlinks: bool) -> TermionBackend<W> {
+        self.hyperlinks = hyperlinks;
+        self
     }
 }
 
@@ -78,6 +89,7 @@ where
 
         let mut string = String::with_capacity(content.size_hint().0 * 3);
         let mut style = style::Style::default();
+        let mut link: Option<&str> = None;
         let mut last_y = 0;
         let mut last_x = 0;
         let mut inst = 0;
@@ -111,9 +123,17 @@ where
                 style.bg = cell.style.bg;
                 inst += 1;
             }
+            if self.hyperlinks && cell.link.as_deref() != link {
+                link = cell.link.as_deref();
+                write!(string, "{}", Hyperlink(link)).unwrap();
+                inst += 1;
+            }
             string.push_str(&cell.symbol);
             inst += 1;
         }
+        if link.is_some() {
+            write!(string, "{}", Hyperlink(None)).unwrap();
+        }
         write!(
             self.stdout,
             "{}{}{}{}",
@@ -139,6 +159,9 @@ struct Fg(style::Color);
 
 struct Bg(style::Color);
 
+/// Starts an OSC 8 hyperlink to the URL, `None` ends the hyperlink.
+struct Hyperlink<'a>(Option<&'a str>);
+
 struct ModifierDiff {
     from: style::Modifier,
     to: style::Modifier,
@@ -197,6 +220,12 @@ impl fmt::Display for Bg {
     }
 }
 
+impl fmt::Display for Hyperlink<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1b]8;;{}\x1b\\", self.0.unwrap_or_default())
+    }
+}
+
 impl fmt::Display for ModifierDiff {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let remove = self.from - self.to;
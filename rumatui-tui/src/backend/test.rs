@@ -37,6 +37,7 @@ impl Backend for TestBackend {
             let cell = self.buffer.get_mut(x, y);
             cell.symbol = c.symbol.clone();
             cell.style = c.style;
+            cell.link = c.link.clone();
         }
         Ok(())
     }
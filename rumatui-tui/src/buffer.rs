@@ -13,6 +13,8 @@ use crate::style::{Color, Modifier, Style};
 pub struct Cell {
     pub symbol: String,
     pub style: Style,
+    /// The URL this cell is a hyperlink to.
+    pub link: Option<String>,
 }
 
 impl Cell {
@@ -48,10 +50,18 @@ impl Cell {
         self
     }
 
+    pub fn set_link(&mut self, link: Option<&str>) -> &mut Cell {
+        if self.link.as_deref() != link {
+            self.link = link.map(String::from);
+        }
+        self
+    }
+
     pub fn reset(&mut self) {
         self.symbol.clear();
         self.symbol.push(' ');
         self.style.reset();
+        self.link = None;
     }
 }
 
@@ -60,6 +70,7 @@ impl Default for Cell {
         Cell {
             symbol: " ".into(),
             style: Default::default(),
+            link: None,
         }
     }
 }
@@ -88,7 +99,9 @@ impl Default for Cell {
 ///         fg: Color::Red,
 ///         bg: Color::White,
 ///         modifier: Modifier::empty()
-///     }});
+///     },
+///     link: None,
+/// });
 /// buf.get_mut(5, 0).set_char('x');
 /// assert_eq!(buf.get(5, 0).symbol, "x");
 /// ```
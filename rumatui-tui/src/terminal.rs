@@ -40,6 +40,11 @@ where
         self.terminal.known_size
     }
 
+    /// The buffer widgets are drawn to, it holds what was rendered so far this frame.
+    pub fn buffer_mut(&mut self) -> &mut Buffer {
+        self.terminal.current_buffer_mut()
+    }
+
     /// Calls the draw method of a given widget on the current buffer
     pub fn render_widget<W>(&mut self, widget: W, area: Rect)
     where
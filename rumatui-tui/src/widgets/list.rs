@@ -220,7 +220,7 @@ where
                         style.unwrap_or(self.style),
                     );
                 }
-                Text::Styled(ref v, s) => {
+                Text::Styled(ref v, s) | Text::Link(ref v, s, _) => {
                     buf.set_stringn(x, y, v, list_area.width as usize, style.unwrap_or(s));
                 }
             };
@@ -50,6 +50,8 @@ bitflags! {
 pub enum Text<'b> {
     Raw(Cow<'b, str>),
     Styled(Cow<'b, str>, Style),
+    /// Styled text that is a hyperlink to the URL, the last field.
+    Link(Cow<'b, str>, Style, Cow<'b, str>),
 }
 
 impl<'b> Text<'b> {
@@ -60,6 +62,14 @@ impl<'b> Text<'b> {
     pub fn styled<D: Into<Cow<'b, str>>>(data: D, style: Style) -> Text<'b> {
         Text::Styled(data.into(), style)
     }
+
+    pub fn link<D, U>(data: D, style: Style, url: U) -> Text<'b>
+    where
+        D: Into<Cow<'b, str>>,
+        U: Into<Cow<'b, str>>,
+    {
+        Text::Link(data.into(), style, url.into())
+    }
 }
 
 /// Base requirements for a Widget
@@ -1,7 +1,6 @@
 use std::cell::Cell;
 use std::rc::Rc;
 
-use either::Either;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
@@ -159,15 +158,13 @@ where
         buf.set_background(text_area, self.style.bg);
 
         let style = self.style;
-        let mut styled = self.text.by_ref().flat_map(|t| match *t {
-            Text::Raw(ref d) => {
-                let data: &'t str = d; // coerce to &str
-                Either::Left(UnicodeSegmentation::graphemes(data, true).map(|g| Styled(g, style)))
-            }
-            Text::Styled(ref d, s) => {
-                let data: &'t str = d; // coerce to &str
-                Either::Right(UnicodeSegmentation::graphemes(data, true).map(move |g| Styled(g, s)))
-            }
+        let mut styled = self.text.by_ref().flat_map(|t| {
+            let (data, s, link): (&'t str, Style, Option<&'t str>) = match *t {
+                Text::Raw(ref d) => (&**d, style, None),
+                Text::Styled(ref d, s) => (&**d, s, None),
+                Text::Link(ref d, s, ref url) => (&**d, s, Some(&**url)),
+            };
+            UnicodeSegmentation::graphemes(data, true).map(move |g| Styled(g, s, link))
         });
 
         let line_composer: Box<dyn LineComposer> = if self.wrapping {
@@ -201,10 +198,11 @@ where
                 Some(ScrolledLine::Line(current_line, current_line_width)) => {
                     let mut x =
                         get_line_offset(current_line_width, text_area.width, self.alignment);
-                    for Styled(symbol, style) in current_line {
+                    for Styled(symbol, style, link) in current_line {
                         buf.get_mut(text_area.left() + x, text_area.top() + y)
                             .set_symbol(symbol)
-                            .set_style(style);
+                            .set_style(style)
+                            .set_link(link);
                         x += symbol.width() as u16;
                     }
                 }
@@ -3,8 +3,9 @@ use unicode_width::UnicodeWidthStr;
 
 const NBSP: &str = "\u{00a0}";
 
+/// A grapheme, its style and the URL it links to.
 #[derive(Copy, Clone, Debug)]
-pub struct Styled<'a>(pub &'a str, pub Style);
+pub struct Styled<'a>(pub &'a str, pub Style, pub Option<&'a str>);
 
 /// A state machine to pack styled symbols into lines.
 /// Cannot implement it as Iterator since it yields slices of the internal buffer (need streaming
@@ -55,14 +56,14 @@ impl<'a, 'b> LineComposer<'a> for WordWrapper<'a, 'b> {
         let mut current_line_width = self
             .current_line
             .iter()
-            .map(|Styled(c, _)| c.width() as u16)
+            .map(|Styled(c, _, _)| c.width() as u16)
             .sum();
 
         let mut symbols_to_last_word_end: usize = 0;
         let mut width_to_last_word_end: u16 = 0;
         let mut prev_whitespace = false;
         let mut symbols_exhausted = true;
-        for Styled(symbol, style) in &mut self.symbols {
+        for Styled(symbol, style, link) in &mut self.symbols {
             symbols_exhausted = false;
             let symbol_whitespace = symbol.chars().all(&char::is_whitespace);
 
@@ -89,7 +90,7 @@ impl<'a, 'b> LineComposer<'a> for WordWrapper<'a, 'b> {
                 width_to_last_word_end = current_line_width;
             }
 
-            self.current_line.push(Styled(symbol, style));
+            self.current_line.push(Styled(symbol, style, link));
             current_line_width += symbol.width() as u16;
 
             if current_line_width > self.max_line_width {
@@ -105,7 +106,7 @@ impl<'a, 'b> LineComposer<'a> for WordWrapper<'a, 'b> {
                     let remainder = &self.current_line[truncate_at..];
                     if let Some(remainder_nonwhite) = remainder
                         .iter()
-                        .position(|Styled(c, _)| !c.chars().all(&char::is_whitespace))
+                        .position(|Styled(c, _, _)| !c.chars().all(&char::is_whitespace))
                     {
                         self.next_line
                             .extend_from_slice(&remainder[remainder_nonwhite..]);
@@ -159,7 +160,7 @@ impl<'a, 'b> LineComposer<'a> for LineTruncator<'a, 'b> {
 
         let mut skip_rest = false;
         let mut symbols_exhausted = true;
-        for Styled(symbol, style) in &mut self.symbols {
+        for Styled(symbol, style, link) in &mut self.symbols {
             symbols_exhausted = false;
 
             // Ignore characters wider that the total max width.
@@ -179,11 +180,11 @@ impl<'a, 'b> LineComposer<'a> for LineTruncator<'a, 'b> {
             }
 
             current_line_width += symbol.width() as u16;
-            self.current_line.push(Styled(symbol, style));
+            self.current_line.push(Styled(symbol, style, link));
         }
 
         if skip_rest {
-            for Styled(symbol, _) in &mut self.symbols {
+            for Styled(symbol, _, _) in &mut self.symbols {
                 if symbol == "\n" {
                     break;
                 }
@@ -210,7 +211,7 @@ mod test {
 
     fn run_composer(which: Composer, text: &str, text_area_width: u16) -> (Vec<String>, Vec<u16>) {
         let style = Default::default();
-        let mut styled = UnicodeSegmentation::graphemes(text, true).map(|g| Styled(g, style));
+        let mut styled = UnicodeSegmentation::graphemes(text, true).map(|g| Styled(g, style, None));
         let mut composer: Box<dyn LineComposer> = match which {
             Composer::WordWrapper => Box::new(WordWrapper::new(&mut styled, text_area_width)),
             Composer::LineTruncator => Box::new(LineTruncator::new(&mut styled, text_area_width)),
@@ -220,7 +221,7 @@ mod test {
         while let Some((styled, width)) = composer.next_line() {
             let line = styled
                 .iter()
-                .map(|Styled(g, _style)| *g)
+                .map(|Styled(g, _style, _link)| *g)
                 .collect::<String>();
             assert!(width <= text_area_width);
             lines.push(line);
@@ -1,6 +1,7 @@
 use rumatui_tui::backend::TestBackend;
 use rumatui_tui::buffer::Buffer;
 use rumatui_tui::layout::Alignment;
+use rumatui_tui::style::Style;
 use rumatui_tui::widgets::{Block, Borders, Paragraph, Text};
 use rumatui_tui::Terminal;
 
@@ -137,3 +138,35 @@ fn paragraph_render_mixed_width() {
     ]);
     assert_eq!(&expected, terminal.backend().buffer());
 }
+
+#[test]
+fn paragraph_render_link() {
+    let backend = TestBackend::new(20, 3);
+    let mut terminal = Terminal::new(backend).unwrap();
+
+    terminal
+        .draw(|mut f| {
+            let size = f.size();
+            let text = [
+                Text::raw("see "),
+                Text::link("example", Style::default(), "https://example.org"),
+            ];
+            let paragraph =
+                Paragraph::new(text.iter()).block(Block::default().borders(Borders::ALL));
+            f.render_widget(paragraph, size);
+        })
+        .unwrap();
+
+    let buffer = terminal.backend().buffer();
+    assert_eq!(buffer.get(4, 1).link, None);
+    assert_eq!(buffer.get(5, 1).symbol, "e");
+    assert_eq!(
+        buffer.get(5, 1).link.as_deref(),
+        Some("https://example.org")
+    );
+    assert_eq!(
+        buffer.get(11, 1).link.as_deref(),
+        Some("https://example.org")
+    );
+    assert_eq!(buffer.get(12, 1).link, None);
+}
@@ -1,42 +1,160 @@
 use std::{
     collections::HashMap,
+    future::Future,
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use futures::future;
 use matrix_sdk::{
     api::r0::{
         account::register,
         directory::get_public_rooms_filtered::{self, RoomNetwork},
-        membership::{join_room_by_id, leave_room},
-        message::{create_message_event, get_message_events},
+        membership::{invite_user, join_room_by_id, leave_room},
+        message::get_message_events,
+        profile::set_display_name,
         // receipt::create_receipt,
         read_marker::set_read_marker,
         session::login,
         typing::create_typing_event,
     },
-    events::room::message::MessageEventContent,
+    events::{room::message::MessageEventContent, EventJson},
     identifiers::{EventId, RoomId, UserId},
-    Room,
+    Client, Room, SyncSettings,
 };
+use serde::{de::DeserializeOwned, Deserialize};
 use tokio::{
     runtime::Handle,
     sync::{
-        mpsc::{self, Sender},
-        RwLock,
+        mpsc::{self, error::SendError, Sender},
+        oneshot, watch, Mutex as AsyncMutex, RwLock,
     },
     task::JoinHandle,
+    time,
 };
 use uuid::Uuid;
 
 use crate::{
-    client::{event_stream::EventStream, MatrixClient},
+    client::{
+        connection::{self, Connection},
+        event_stream::EventStream,
+        outbox::{Failure, Outbox, OutgoingMessage, SendState, Unsent},
+        preview::UrlPreview,
+        room_link::RoomLink,
+        ruma_ext::{
+            account_data::{DirectEventContent, TagEventContent, Tags},
+            message::{redact_event, send_message_event},
+            state::send_state_event,
+        },
+        MatrixClient, SYNC_TIMEOUT,
+    },
     error::{Error, Result},
 };
 
+/// How long `MatrixEventHandle::shutdown` waits for each loop to finish.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+/// A loop that panics this many times in a row is not restarted again.
+const MAX_RESTARTS: u32 = 5;
+/// A loop that ran this long before panicking is counted as having recovered.
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// The state of the sync loop, broadcast from the UI to the sync task.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SyncLoop {
+    /// Waiting for the user to log in.
+    Waiting,
+    Running,
+    Quitting,
+}
+
+/// Identifies a `UserRequest` so it can be cancelled with `UserRequest::Cancel`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RequestId(u64);
+
+/// Sends `UserRequest`s to the request loop, each request is given a `RequestId`.
+pub struct RequestSender {
+    send: Sender<(RequestId, UserRequest)>,
+    next_id: u64,
+}
+
+impl RequestSender {
+    pub async fn send(
+        &mut self,
+        request: UserRequest,
+    ) -> std::result::Result<RequestId, SendError<UserRequest>> {
+        self.next_id += 1;
+        let id = RequestId(self.next_id);
+        self.send
+            .send((id, request))
+            .await
+            .map(|()| id)
+            .map_err(|SendError((_, request))| SendError(request))
+    }
+}
+
+/// Requests in the same lane run one at a time in the order they were sent, requests
+/// in different lanes run concurrently.
+///
+/// Sending messages has its own loop, see `outbox_loop`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Lane {
+    /// Loading the history of a room.
+    History(RoomId),
+    /// Changes to and notices for a room.
+    Room(RoomId),
+    /// Searching the room directory.
+    Directory,
+    /// Fetching URL previews.
+    Media,
+    /// Everything concerning the user's account.
+    Account,
+}
+
+impl Lane {
+    fn of(request: &UserRequest) -> Self {
+        match request {
+            UserRequest::RoomMsgs(room_id) => Self::History(room_id.clone()),
+            UserRequest::AcceptInvite(room_id)
+            | UserRequest::DeclineInvite(room_id)
+            | UserRequest::LeaveRoom(room_id)
+            | UserRequest::InviteUser(room_id, _)
+            | UserRequest::SetTopic(room_id, _)
+            | UserRequest::EditMessage(room_id, ..)
+            | UserRequest::RedactMessage(room_id, _)
+            | UserRequest::Typing(room_id, _)
+            | UserRequest::ReadReceipt(room_id, _)
+            | UserRequest::TagRoom(room_id, _)
+            | UserRequest::UntagRoom(room_id, _) => Self::Room(room_id.clone()),
+            UserRequest::RoomSearch(..) => Self::Directory,
+            UserRequest::UrlPreview(_) => Self::Media,
+            _ => Self::Account,
+        }
+    }
+}
+
+/// A request waiting in or running on a `Lane`.
+struct Job {
+    id: RequestId,
+    request: UserRequest,
+    client: MatrixClient,
+    /// Completes when the request is cancelled.
+    cancelled: oneshot::Receiver<()>,
+}
+
+/// The worker task running the requests of a `Lane`.
+struct LaneWorker {
+    jobs: Sender<Job>,
+    /// The jobs sent to the worker that have not finished, a lane with none is dropped
+    /// so its worker stops instead of living as long as the session.
+    pending: Arc<AtomicUsize>,
+}
+
+/// Cancels each request that has not finished.
+type Cancels = Arc<Mutex<HashMap<RequestId, oneshot::Sender<()>>>>;
+
 /// Requests sent from the UI portion of the app.
 ///
 /// Each request is sent in response to some user input.
@@ -46,16 +164,35 @@ pub enum UserRequest {
     Login(String, String),
     Register(String, String),
     SendMessage(RoomId, MessageEventContent, Uuid),
+    /// Retry one unsent message or every unsent message of a room.
+    RetryMessages(Unsent),
+    /// Discard one unsent message or every unsent message of a room.
+    DiscardMessages(Unsent),
     RoomMsgs(RoomId),
     AcceptInvite(RoomId),
     DeclineInvite(RoomId),
-    JoinRoom(RoomId),
+    JoinRoom(RoomLink),
     LeaveRoom(RoomId),
+    InviteUser(RoomId, UserId),
+    SetDisplayName(String),
+    SetTopic(RoomId, String),
+    /// Replace the text of one of the user's messages.
+    EditMessage(RoomId, EventId, String),
+    RedactMessage(RoomId, EventId),
     Typing(RoomId, UserId),
     ReadReceipt(RoomId, EventId),
     RoomSearch(String, RoomNetwork, Option<String>),
+    DirectRooms,
+    StartDirectMessage(UserId),
+    RoomTags,
+    TagRoom(RoomId, String),
+    UntagRoom(RoomId, String),
+    /// Fetch the preview card of the URL.
+    UrlPreview(String),
     UiaaPing(String),
     UiaaDummy(String),
+    /// Cancel the request if it is waiting or running, its result is `RequestResult::Cancelled`.
+    Cancel(RequestId),
     Quit,
 }
 unsafe impl Send for UserRequest {}
@@ -70,15 +207,41 @@ pub enum RequestResult {
         )>,
     ),
     Register(Result<register::Response>),
-    SendMessage(Result<create_message_event::Response>),
+    /// The message with the given transaction id was sent or is waiting in the outbox.
+    SendMessage(RoomId, Uuid, SendState),
+    /// The messages left in the outbox the last time `rumatui` ran.
+    Unsent(Vec<OutgoingMessage>),
+    /// The room and `Uuid` of each discarded message.
+    DiscardMessages(Vec<(RoomId, Uuid)>),
     RoomMsgs(Result<(get_message_events::Response, Arc<RwLock<Room>>)>),
     AcceptInvite(Result<join_room_by_id::Response>),
     DeclineInvite(Result<leave_room::Response>, RoomId),
     LeaveRoom(Result<leave_room::Response>, RoomId),
     JoinRoom(Result<RoomId>),
+    InviteUser(Result<invite_user::Response>, UserId),
+    SetDisplayName(Result<set_display_name::Response>),
+    SetTopic(Result<send_state_event::Response>),
+    EditMessage(
+        Result<send_message_event::Response>,
+        RoomId,
+        EventId,
+        String,
+    ),
+    RedactMessage(Result<redact_event::Response>, RoomId, EventId),
     Typing(Result<create_typing_event::Response>),
     ReadReceipt(Result<set_read_marker::Response>),
     RoomSearch(Result<get_public_rooms_filtered::Response>),
+    DirectRooms(Result<DirectEventContent>),
+    StartDirectMessage(Result<(RoomId, DirectEventContent)>, UserId),
+    RoomTags(Result<(HashMap<RoomId, Tags>, Vec<(RoomId, Error)>)>),
+    TagRoom(Result<Tags>, RoomId),
+    UrlPreview(Result<Option<UrlPreview>>, String),
+    /// Sent after every sync attempt.
+    Connection(Connection),
+    /// The request was cancelled before it finished.
+    Cancelled(RequestId),
+    /// The named loop panicked and was restarted.
+    Restarted(&'static str),
     Error(Error),
 }
 
@@ -86,262 +249,866 @@ unsafe impl Send for RequestResult {}
 
 /// The main task event loop.
 ///
-/// `MatrixEventHandle` controls the `sync_forever` and user request loop.
+/// `MatrixEventHandle` controls the sync loop and user request loop.
 pub struct MatrixEventHandle {
     cli_jobs: JoinHandle<Result<()>>,
     sync_jobs: JoinHandle<Result<()>>,
-    start_sync: Arc<AtomicBool>,
-    quit_flag: Arc<AtomicBool>,
+    lifecycle: watch::Sender<SyncLoop>,
 }
 unsafe impl Send for MatrixEventHandle {}
 
 impl MatrixEventHandle {
     pub async fn new(
         stream: EventStream,
-        mut to_app: Sender<RequestResult>,
+        to_app: Sender<RequestResult>,
         exec_hndl: Handle,
         homeserver: &str,
-    ) -> (Self, Sender<UserRequest>) {
-        let (app_sender, mut recv) = mpsc::channel(1024);
+    ) -> (Self, RequestSender) {
+        let (app_sender, recv) = mpsc::channel(1024);
 
         let mut client = MatrixClient::new(homeserver).unwrap();
         client.inner.add_event_emitter(Box::new(stream)).await;
 
         let cli = client.inner.clone();
-        // when the ui loop logs in `start_sync` releases and starts `sync_forever`
-        let start_sync = Arc::from(AtomicBool::from(false));
-        let quit_flag = Arc::from(AtomicBool::from(false));
-
-        let is_sync = Arc::clone(&start_sync);
-        let quitting = Arc::clone(&quit_flag);
-        // this loop uses the above `AtomicBool` to signal shutdown.
-        let sync_jobs = exec_hndl.spawn(async move {
-            while !is_sync.load(Ordering::SeqCst) {
-                if quitting.load(Ordering::SeqCst) {
-                    return Ok(());
-                }
+        // when the ui loop logs in `start_sync` starts the sync loop
+        let (lifecycle, sync_lifecycle) = watch::channel(SyncLoop::Waiting);
+        // the outbox retries as soon as the sync loop reaches the server again
+        let (online, online_recv) = watch::channel(true);
+        let online = Arc::new(online);
+        let app = to_app.clone();
+        let sync_jobs = exec_hndl.spawn(supervise(
+            "sync",
+            move || {
+                sync_loop(
+                    cli.clone(),
+                    sync_lifecycle.clone(),
+                    Arc::clone(&online),
+                    app.clone(),
+                )
+            },
+            to_app.clone(),
+        ));
 
-                std::sync::atomic::spin_loop_hint();
-            }
+        // this loop is shutdown with a channel message
+        let state = Arc::new(AsyncMutex::new(RequestLoop {
+            client,
+            requests: recv,
+            lanes: HashMap::new(),
+            cancels: Cancels::default(),
+            user: None,
+            outbox_jobs: None,
+            online: online_recv,
+        }));
+        let app = to_app.clone();
+        let cli_jobs = exec_hndl.spawn(supervise(
+            "user request",
+            move || request_loop(Arc::clone(&state), app.clone()),
+            to_app,
+        ));
+
+        (
+            MatrixEventHandle {
+                cli_jobs,
+                sync_jobs,
+                lifecycle,
+            },
+            RequestSender {
+                send: app_sender,
+                next_id: 0,
+            },
+        )
+    }
+
+    /// This is called after login and initial sync to start the sync loop.
+    pub(crate) fn start_sync(&self) {
+        // the sync loop only ends after `quit_sync`
+        let _ = self.lifecycle.broadcast(SyncLoop::Running);
+    }
+
+    /// This is called when the user quits to stop the sync loop, a sync in progress
+    /// is cancelled.
+    pub(crate) fn quit_sync(&self) {
+        let _ = self.lifecycle.broadcast(SyncLoop::Quitting);
+    }
 
-            if quitting.load(Ordering::SeqCst) {
-                return Ok(());
+    /// Waits for the sync loop and the user request loop to finish.
+    ///
+    /// The user request loop must have been sent `UserRequest::Quit`, a request in
+    /// flight is given `SHUTDOWN_TIMEOUT` to finish before it is abandoned.
+    pub(crate) async fn shutdown(&mut self) {
+        for (name, task) in vec![
+            ("sync", &mut self.sync_jobs),
+            ("user request", &mut self.cli_jobs),
+        ] {
+            match time::timeout(SHUTDOWN_TIMEOUT, task).await {
+                Ok(Ok(Ok(()))) => {}
+                Ok(Ok(Err(e))) => tracing::error!("the {} loop failed {}", name, e),
+                Ok(Err(e)) => tracing::error!("the {} loop panicked {}", name, e),
+                Err(_) => tracing::warn!("the {} loop did not shutdown in time", name),
             }
-            let set = matrix_sdk::SyncSettings::default();
-            cli.sync_forever(set.clone(), |_| async {}).await;
-            Ok(())
-        });
+        }
+    }
+}
 
-        // this loop is shutdown with a channel message
-        let cli_jobs = exec_hndl.spawn(async move {
-            loop {
-                let input = recv.recv().await;
-                if input.is_none() {
-                    return Ok(());
+/// Runs the loop `start` returns on its own task and restarts it when it panics.
+///
+/// Each restart waits longer than the last, after `MAX_RESTARTS` panics in a row the
+/// UI is sent a fatal error. The loop finishing, with or without an error, ends the
+/// supervisor.
+async fn supervise<F, Fut>(
+    name: &'static str,
+    mut start: F,
+    mut to_app: Sender<RequestResult>,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let mut restarts = 0;
+    loop {
+        let started = Instant::now();
+        let panic = match tokio::spawn(start()).await {
+            Ok(res) => return res,
+            Err(panic) => panic,
+        };
+        tracing::error!("the {} loop panicked {}", name, panic);
+
+        if started.elapsed() > STABLE_RUN {
+            restarts = 0;
+        }
+        restarts += 1;
+        // when the UI is gone there is nobody to tell
+        if restarts > MAX_RESTARTS {
+            let err = Error::Rumatui("the client keeps crashing, a crash report is in ~/.rumatui");
+            let _ = to_app.send(RequestResult::Error(err)).await;
+            return Err(Error::Rumatui("a client loop keeps crashing"));
+        }
+        let _ = to_app.send(RequestResult::Restarted(name)).await;
+        time::delay_for(connection::retry_delay(restarts, None)).await;
+    }
+}
+
+/// What the user request loop keeps when it is restarted after a panic.
+struct RequestLoop {
+    client: MatrixClient,
+    requests: mpsc::Receiver<(RequestId, UserRequest)>,
+    lanes: HashMap<Lane, LaneWorker>,
+    cancels: Cancels,
+    /// The user that logged in, the outbox loop is started for this user.
+    user: Option<UserId>,
+    outbox_jobs: Option<Sender<UserRequest>>,
+    /// Is the sync loop reaching the homeserver, passed on to the outbox loop.
+    online: watch::Receiver<bool>,
+}
+
+/// Dispatches each `UserRequest` until `UserRequest::Quit` is sent.
+///
+/// The state is locked for as long as the loop runs, when the loop panics the lock is
+/// released so the restarted loop carries on with the logged in client and the
+/// requests still waiting in the channel.
+async fn request_loop(
+    state: Arc<AsyncMutex<RequestLoop>>,
+    mut to_app: Sender<RequestResult>,
+) -> Result<()> {
+    let mut state = state.lock().await;
+    let RequestLoop {
+        client,
+        requests,
+        lanes,
+        cancels,
+        user: logged_in,
+        outbox_jobs,
+        online,
+    } = &mut *state;
+    while let Some((id, request)) = requests.recv().await {
+        match request {
+            UserRequest::Quit => break,
+            UserRequest::Cancel(target) => {
+                // the request may have finished already
+                if let Some(cancel) = cancels.lock().unwrap().remove(&target) {
+                    let _ = cancel.send(());
+                }
+            }
+            // logging in and registering change the client so they finish before
+            // any other request is dispatched
+            UserRequest::Login(u, p) => {
+                let res = client.login(u, p).await;
+                let user = res.as_ref().ok().map(|(_, resp)| resp.user_id.clone());
+                if let Err(e) = to_app.send(RequestResult::Login(res)).await {
+                    tracing::error!("client event handler crashed {}", e);
+                    panic!("client event handler crashed {}", e)
                 }
 
-                match input.unwrap() {
-                    UserRequest::Quit => return Ok(()),
-                    UserRequest::Login(u, p) => {
-                        let res = client.login(u, p).await;
-                        if let Err(e) = to_app.send(RequestResult::Login(res)).await {
-                            tracing::error!("client event handler crashed {}", e);
-                            panic!("client event handler crashed {}", e)
+                if let Some(user) = user {
+                    *outbox_jobs = Some(spawn_outbox(client, &user, online, &to_app));
+                    *logged_in = Some(user);
+                }
+            }
+            UserRequest::Register(u, p) => {
+                let res = client.register_user(u, p).await;
+                if let Err(e) = to_app.send(RequestResult::Register(res)).await {
+                    tracing::error!("client event handler crashed {}", e);
+                    panic!("client event handler crashed {}", e)
+                } else {
+                    tracing::info!("start UIAA cycle");
+                }
+            }
+            UserRequest::UiaaPing(sess) => {
+                let res = client.send_uiaa_ping(sess).await;
+                if let Err(e) = to_app
+                    .send(RequestResult::Register(res.map(Into::into)))
+                    .await
+                {
+                    tracing::error!("client event handler crashed {}", e);
+                    panic!("client event handler crashed {}", e)
+                } else {
+                    tracing::info!("ping UIAA endpoint");
+                }
+            }
+            UserRequest::UiaaDummy(sess) => {
+                let res = client.send_uiaa_dummy(sess).await;
+                if let Err(e) = to_app
+                    .send(RequestResult::Register(res.map(Into::into)))
+                    .await
+                {
+                    tracing::error!("client event handler crashed {}", e);
+                    panic!("client event handler crashed {}", e)
+                } else {
+                    tracing::info!("sending the dummy UIAA request");
+                }
+            }
+            UserRequest::SendMessage(room, _, uuid) if outbox_jobs.is_none() => {
+                let state = SendState::Failed("not logged in".into());
+                if let Err(e) = to_app
+                    .send(RequestResult::SendMessage(room, uuid, state))
+                    .await
+                {
+                    tracing::error!("client event handler crashed {}", e);
+                    panic!("client event handler crashed {}", e)
+                }
+            }
+            // the outbox loop sends one message at a time so they stay in order
+            request @ UserRequest::SendMessage(..)
+            | request @ UserRequest::RetryMessages(_)
+            | request @ UserRequest::DiscardMessages(_) => {
+                if let (Some(outbox), Some(user)) = (outbox_jobs.as_mut(), logged_in.as_ref()) {
+                    if let Err(SendError(request)) = outbox.send(request).await {
+                        // the outbox is saved to disk so nothing is lost restarting it
+                        tracing::error!("the outbox loop stopped, restarting it");
+                        *outbox = spawn_outbox(client, user, online, &to_app);
+                        if outbox.send(request).await.is_err() {
+                            tracing::error!("the outbox loop stopped");
                         }
                     }
-                    UserRequest::Register(u, p) => {
-                        let res = client.register_user(u, p).await;
-                        if let Err(e) = to_app.send(RequestResult::Register(res)).await {
-                            tracing::error!("client event handler crashed {}", e);
-                            panic!("client event handler crashed {}", e)
-                        } else {
-                            tracing::info!("start UIAA cycle");
-                        }
+                }
+            }
+            request => {
+                let (cancel, cancelled) = oneshot::channel();
+                cancels.lock().unwrap().insert(id, cancel);
+                let lane = Lane::of(&request);
+                // only this loop sends jobs so an idle lane stays idle until it is dropped
+                lanes.retain(|_, worker| worker.pending.load(Ordering::SeqCst) > 0);
+                let worker = lanes
+                    .entry(lane)
+                    .or_insert_with(|| spawn_lane(cancels, &to_app));
+                let job = Job {
+                    id,
+                    request,
+                    client: client.clone(),
+                    cancelled,
+                };
+                worker.pending.fetch_add(1, Ordering::SeqCst);
+                if let Err(SendError(job)) = worker.jobs.send(job).await {
+                    // the worker panicked, the requests it had waiting are gone
+                    tracing::error!("a request lane stopped, restarting it");
+                    *worker = spawn_lane(cancels, &to_app);
+                    worker.pending.fetch_add(1, Ordering::SeqCst);
+                    if worker.jobs.send(job).await.is_err() {
+                        tracing::error!("a request lane stopped");
                     }
-                    UserRequest::UiaaPing(sess) => {
-                        let res = client.send_uiaa_ping(sess).await;
-                        if let Err(e) = to_app
-                            .send(RequestResult::Register(res.map(Into::into)))
-                            .await
-                        {
-                            tracing::error!("client event handler crashed {}", e);
-                            panic!("client event handler crashed {}", e)
-                        } else {
-                            tracing::info!("ping UIAA endpoint");
-                        }
+                }
+            }
+        }
+    }
+
+    // cancel everything still running, the lanes and the outbox loop stop once
+    // their channels are dropped
+    for (_, cancel) in cancels.lock().unwrap().drain() {
+        let _ = cancel.send(());
+    }
+    Ok(())
+}
+
+fn spawn_lane(cancels: &Cancels, to_app: &Sender<RequestResult>) -> LaneWorker {
+    let (send, jobs) = mpsc::channel(1024);
+    let pending = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(lane_worker(
+        jobs,
+        Arc::clone(&pending),
+        Arc::clone(cancels),
+        to_app.clone(),
+    ));
+    LaneWorker {
+        jobs: send,
+        pending,
+    }
+}
+
+fn spawn_outbox(
+    client: &MatrixClient,
+    user: &UserId,
+    online: &watch::Receiver<bool>,
+    to_app: &Sender<RequestResult>,
+) -> Sender<UserRequest> {
+    let (send, jobs) = mpsc::channel(1024);
+    tokio::spawn(outbox_loop(
+        client.clone(),
+        Outbox::load(user),
+        jobs,
+        online.clone(),
+        to_app.clone(),
+    ));
+    send
+}
+
+/// Syncs with the homeserver once `SyncLoop::Running` is broadcast until `SyncLoop::Quitting`.
+///
+/// Failed syncs are retried until the user quits so a homeserver restart or losing the
+/// network never stops the loop.
+async fn sync_loop(
+    cli: Client,
+    mut lifecycle: watch::Receiver<SyncLoop>,
+    online: Arc<watch::Sender<bool>>,
+    mut to_app: Sender<RequestResult>,
+) -> Result<()> {
+    loop {
+        match *lifecycle.borrow() {
+            SyncLoop::Waiting => {}
+            SyncLoop::Running => break,
+            SyncLoop::Quitting => return Ok(()),
+        }
+        // the sender is gone so nothing will start the loop
+        if lifecycle.recv().await.is_none() {
+            return Ok(());
+        }
+    }
+
+    let mut failures = 0;
+    let mut last_sync = None;
+    loop {
+        let mut settings = SyncSettings::default().timeout(SYNC_TIMEOUT);
+        if let Some(token) = cli.sync_token().await {
+            settings = settings.token(token);
+        }
+
+        let res = tokio::select! {
+            res = cli.sync(settings) => res.map_err(Error::from),
+            _ = quit_requested(&mut lifecycle) => return Ok(()),
+        };
+        let (status, retry_in) = match res {
+            Ok(response) => {
+                // account data only arrives when it changed, another client may have
+                // started or ended a direct chat
+                if let Some(direct) =
+                    account_data::<_, DirectEventContent>(&response.account_data.events, "m.direct")
+                {
+                    if let Err(e) = to_app.send(RequestResult::DirectRooms(Ok(direct))).await {
+                        tracing::error!("client event handler crashed {}", e);
+                        panic!("client event handler crashed {}", e)
                     }
-                    UserRequest::UiaaDummy(sess) => {
-                        let res = client.send_uiaa_dummy(sess).await;
-                        if let Err(e) = to_app
-                            .send(RequestResult::Register(res.map(Into::into)))
-                            .await
-                        {
+                }
+                // tags changed here or by another client
+                for (room_id, room) in &response.rooms.join {
+                    if let Some(content) =
+                        account_data::<_, TagEventContent>(&room.account_data.events, "m.tag")
+                    {
+                        let tags = RequestResult::TagRoom(Ok(content.tags), room_id.clone());
+                        if let Err(e) = to_app.send(tags).await {
                             tracing::error!("client event handler crashed {}", e);
                             panic!("client event handler crashed {}", e)
-                        } else {
-                            tracing::info!("sending the dummy UIAA request");
                         }
                     }
-                    UserRequest::SendMessage(room, msg, uuid) => {
-                        let res = client.send_message(&room, msg, uuid).await;
-                        if let Err(e) = to_app.send(RequestResult::SendMessage(res)).await {
-                            tracing::error!("client event handler crashed {}", e);
-                            panic!("client event handler crashed {}", e)
-                        }
+                }
+                failures = 0;
+                let now = Instant::now();
+                last_sync = Some(now);
+                (Connection::Online(now), None)
+            }
+            Err(err) => {
+                tracing::warn!("sync failed {}", err);
+                failures += 1;
+                let retry_in = connection::retry_delay(failures, err.retry_after());
+                let reason = err
+                    .to_string()
+                    .lines()
+                    .last()
+                    .unwrap_or_default()
+                    .to_string();
+                // retrying will not help until the user logs in again, the loop keeps
+                // going so syncing picks up with the new session
+                if failures == 1 && err.needs_relogin() {
+                    if let Err(e) = to_app.send(RequestResult::Error(err)).await {
+                        tracing::error!("client event handler crashed {}", e);
+                        panic!("client event handler crashed {}", e)
                     }
-                    UserRequest::RoomMsgs(room_id) => match client.get_messages(&room_id).await {
-                        Ok(res) => {
-                            if let Err(e) = to_app
-                                .send(RequestResult::RoomMsgs(Ok((
-                                    res,
-                                    Arc::clone(
-                                        client
-                                            .inner
-                                            .joined_rooms()
-                                            .read()
-                                            .await
-                                            .get(&room_id)
-                                            .unwrap(),
-                                    ),
-                                ))))
+                }
+                let status = Connection::Offline {
+                    reason,
+                    last_sync,
+                    retry_at: Instant::now() + retry_in,
+                };
+                (status, Some(retry_in))
+            }
+        };
+        // only fails when the outbox loop is not running, there is nothing to retry then
+        let _ = online.broadcast(status.is_online());
+        if let Err(e) = to_app.send(RequestResult::Connection(status)).await {
+            tracing::error!("client event handler crashed {}", e);
+            panic!("client event handler crashed {}", e)
+        }
+        if let Some(wait) = retry_in {
+            tokio::select! {
+                _ = time::delay_for(wait) => {}
+                _ = quit_requested(&mut lifecycle) => return Ok(()),
+            }
+        }
+    }
+}
+
+/// The content of the last account data event of `event_type` among `events`.
+fn account_data<T, C>(events: &[EventJson<T>], event_type: &str) -> Option<C>
+where
+    C: DeserializeOwned,
+{
+    #[derive(Deserialize)]
+    struct AccountDataEvent {
+        #[serde(rename = "type")]
+        event_type: String,
+        content: serde_json::Value,
+    }
+
+    let content = events
+        .iter()
+        .filter_map(|ev| serde_json::from_str::<AccountDataEvent>(ev.json().get()).ok())
+        .filter(|ev| ev.event_type == event_type)
+        .last()?
+        .content;
+    match serde_json::from_value(content) {
+        Ok(content) => Some(content),
+        Err(e) => {
+            tracing::warn!("invalid {} account data {}", event_type, e);
+            None
+        }
+    }
+}
+
+/// Completes once `SyncLoop::Quitting` is broadcast or the sender is dropped.
+async fn quit_requested(lifecycle: &mut watch::Receiver<SyncLoop>) {
+    while *lifecycle.borrow() != SyncLoop::Quitting {
+        if lifecycle.recv().await.is_none() {
+            return;
+        }
+    }
+}
+
+/// Runs the requests of one `Lane` in the order they were dispatched, it stops once the
+/// request loop drops the idle lane.
+async fn lane_worker(
+    mut jobs: mpsc::Receiver<Job>,
+    pending: Arc<AtomicUsize>,
+    cancels: Cancels,
+    mut to_app: Sender<RequestResult>,
+) {
+    while let Some(Job {
+        id,
+        request,
+        client,
+        cancelled,
+    }) = jobs.recv().await
+    {
+        let finished = tokio::select! {
+            _ = run_request(&client, request, &mut to_app) => true,
+            _ = cancelled => false,
+        };
+        cancels.lock().unwrap().remove(&id);
+        pending.fetch_sub(1, Ordering::SeqCst);
+        if !finished {
+            if let Err(e) = to_app.send(RequestResult::Cancelled(id)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+    }
+}
+
+/// Runs a request that is not logging in or sending messages, the results are sent to the UI.
+async fn run_request(
+    client: &MatrixClient,
+    request: UserRequest,
+    to_app: &mut Sender<RequestResult>,
+) {
+    match request {
+        UserRequest::RoomMsgs(room_id) => match client.get_messages(&room_id).await {
+            Ok(res) => {
+                if let Err(e) = to_app
+                    .send(RequestResult::RoomMsgs(Ok((
+                        res,
+                        Arc::clone(
+                            client
+                                .inner
+                                .joined_rooms()
+                                .read()
                                 .await
-                            {
-                                tracing::error!("client event handler crashed {}", e);
-                                panic!("client event handler crashed {}", e)
-                            } else {
-                                // store state after receiving past events incase a sync_forever call only found a few messages
-                                if client.store_room_state(&room_id).await.is_err() {
-                                    // TODO log that an error happened at some point
-                                }
-                            }
-                        }
-                        Err(get_msg_err) => {
-                            if let Err(e) = to_app.send(RequestResult::Error(get_msg_err)).await {
-                                tracing::error!("client event handler crashed {}", e);
-                                panic!("client event handler crashed {}", e)
-                            }
-                        }
-                    },
-                    UserRequest::RoomSearch(filter, network, tkn) => {
-                        match client.get_rooms_filtered(&filter, network, tkn).await {
-                            Ok(res) => {
-                                if let Err(e) =
-                                    to_app.send(RequestResult::RoomSearch(Ok(res))).await
-                                {
-                                    tracing::error!("client event handler crashed {}", e);
-                                    panic!("client event handler crashed {}", e)
-                                }
-                            }
-                            Err(err) => {
-                                if let Err(e) = to_app.send(RequestResult::Error(err)).await {
-                                    tracing::error!("client event handler crashed {}", e);
-                                    panic!("client event handler crashed {}", e)
-                                }
-                            }
-                        }
-                    }
-                    UserRequest::AcceptInvite(room_id) => {
-                        let res = client.join_room_by_id(&room_id).await;
-                        if let Err(e) = to_app.send(RequestResult::AcceptInvite(res)).await {
-                            tracing::error!("client event handler crashed {}", e);
-                            panic!("client event handler crashed {}", e)
-                        }
-                    }
-                    UserRequest::DeclineInvite(room_id) => {
-                        let res = client.leave_room(&room_id).await;
-                        if let Err(e) = to_app
-                            .send(RequestResult::DeclineInvite(res, room_id))
-                            .await
-                        {
-                            tracing::error!("client event handler crashed {}", e);
-                            panic!("client event handler crashed {}", e)
-                        }
+                                .get(&room_id)
+                                .unwrap(),
+                        ),
+                    ))))
+                    .await
+                {
+                    tracing::error!("client event handler crashed {}", e);
+                    panic!("client event handler crashed {}", e)
+                } else {
+                    // store state after receiving past events incase a sync_forever call only found a few messages
+                    if client.store_room_state(&room_id).await.is_err() {
+                        // TODO log that an error happened at some point
                     }
-                    UserRequest::LeaveRoom(room_id) => {
-                        let res = client.leave_room(&room_id).await;
-                        if let Err(e) = to_app
-                            .send(RequestResult::LeaveRoom(res, room_id.clone()))
-                            .await
-                        {
-                            tracing::error!("client event handler crashed {}", e);
-                            panic!("client event handler crashed {}", e)
-                        } else if let Err(error) = client.forget_room(&room_id).await {
-                            // forget room failed so send that to the UI
-                            if let Err(e) = to_app.send(RequestResult::Error(error)).await {
-                                tracing::error!("client event handler crashed {}", e);
-                                panic!("client event handler crashed {}", e)
-                            }
-                        }
+                }
+            }
+            Err(get_msg_err) => {
+                if let Err(e) = to_app.send(RequestResult::RoomMsgs(Err(get_msg_err))).await {
+                    tracing::error!("client event handler crashed {}", e);
+                    panic!("client event handler crashed {}", e)
+                }
+            }
+        },
+        UserRequest::RoomSearch(filter, network, tkn) => {
+            match client.get_rooms_filtered(&filter, network, tkn).await {
+                Ok(res) => {
+                    if let Err(e) = to_app.send(RequestResult::RoomSearch(Ok(res))).await {
+                        tracing::error!("client event handler crashed {}", e);
+                        panic!("client event handler crashed {}", e)
                     }
-                    UserRequest::JoinRoom(room_id) => {
-                        // TODO just send the result
-                        match client.join_room_by_id(&room_id).await {
-                            Ok(res) => {
-                                let room_id = &res.room_id;
-                                if let Err(e) = to_app
-                                    .send(RequestResult::JoinRoom(Ok(room_id.clone())))
-                                    .await
-                                {
-                                    tracing::error!("client event handler crashed {}", e);
-                                    panic!("client event handler crashed {}", e)
-                                }
-                            }
-                            Err(err) => {
-                                if let Err(e) = to_app.send(RequestResult::JoinRoom(Err(err))).await
-                                {
-                                    tracing::error!("client event handler crashed {}", e);
-                                    panic!("client event handler crashed {}", e)
-                                }
-                            }
-                        }
+                }
+                Err(err) => {
+                    if let Err(e) = to_app.send(RequestResult::RoomSearch(Err(err))).await {
+                        tracing::error!("client event handler crashed {}", e);
+                        panic!("client event handler crashed {}", e)
                     }
-                    UserRequest::ReadReceipt(room_id, event_id) => {
-                        let res = client
-                            .read_marker(&room_id, &event_id, Some(&event_id))
-                            .await;
-                        if let Err(e) = to_app.send(RequestResult::ReadReceipt(res)).await {
+                }
+            }
+        }
+        UserRequest::AcceptInvite(room_id) => {
+            let res = client.join_room_by_id(&room_id).await;
+            if let Err(e) = to_app.send(RequestResult::AcceptInvite(res)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::DeclineInvite(room_id) => {
+            let res = client.leave_room(&room_id).await;
+            if let Err(e) = to_app
+                .send(RequestResult::DeclineInvite(res, room_id))
+                .await
+            {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::LeaveRoom(room_id) => {
+            let res = client.leave_room(&room_id).await;
+            if let Err(e) = to_app
+                .send(RequestResult::LeaveRoom(res, room_id.clone()))
+                .await
+            {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            } else if let Err(error) = client.forget_room(&room_id).await {
+                // forget room failed so send that to the UI
+                if let Err(e) = to_app.send(RequestResult::Error(error)).await {
+                    tracing::error!("client event handler crashed {}", e);
+                    panic!("client event handler crashed {}", e)
+                }
+            }
+        }
+        UserRequest::JoinRoom(link) => {
+            let res = client.join_room(&link).await;
+            if let Err(e) = to_app.send(RequestResult::JoinRoom(res)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::InviteUser(room_id, user_id) => {
+            let res = client.invite_user(&room_id, &user_id).await;
+            if let Err(e) = to_app.send(RequestResult::InviteUser(res, user_id)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::SetDisplayName(name) => {
+            let res = client.set_display_name(name).await;
+            if let Err(e) = to_app.send(RequestResult::SetDisplayName(res)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::SetTopic(room_id, topic) => {
+            let res = client.set_topic(&room_id, topic).await;
+            if let Err(e) = to_app.send(RequestResult::SetTopic(res)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::EditMessage(room_id, event_id, body) => {
+            let res = client.edit_message(&room_id, &event_id, body.clone()).await;
+            if let Err(e) = to_app
+                .send(RequestResult::EditMessage(res, room_id, event_id, body))
+                .await
+            {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::RedactMessage(room_id, event_id) => {
+            let res = client.redact_message(&room_id, &event_id).await;
+            if let Err(e) = to_app
+                .send(RequestResult::RedactMessage(res, room_id, event_id))
+                .await
+            {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::DirectRooms => {
+            let res = client.direct_rooms().await;
+            if let Err(e) = to_app.send(RequestResult::DirectRooms(res)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::StartDirectMessage(user_id) => {
+            let res = client.start_direct_message(&user_id).await;
+            if let Err(e) = to_app
+                .send(RequestResult::StartDirectMessage(res, user_id))
+                .await
+            {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::UrlPreview(url) => {
+            let res = client.url_preview(&url).await;
+            if let Err(e) = to_app.send(RequestResult::UrlPreview(res, url)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::RoomTags => {
+            let res = client.all_room_tags().await;
+            if let Err(e) = to_app.send(RequestResult::RoomTags(res)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::TagRoom(room_id, tag) => {
+            let res = client.tag_room(&room_id, tag).await;
+            if let Err(e) = to_app.send(RequestResult::TagRoom(res, room_id)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::UntagRoom(room_id, tag) => {
+            let res = client.untag_room(&room_id, tag).await;
+            if let Err(e) = to_app.send(RequestResult::TagRoom(res, room_id)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::ReadReceipt(room_id, event_id) => {
+            let res = client
+                .read_marker(&room_id, &event_id, Some(&event_id))
+                .await;
+            if let Err(e) = to_app.send(RequestResult::ReadReceipt(res)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        UserRequest::Typing(room_id, user_id) => {
+            let res = client
+                .typing_notice(&room_id, &user_id, true, Some(Duration::from_millis(3000)))
+                .await;
+            if let Err(e) = to_app.send(RequestResult::Typing(res)).await {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
+        // handled by the request loop or the outbox loop
+        _ => {}
+    }
+}
+
+/// Sends the messages in the outbox, failed messages are retried when they are due.
+async fn outbox_loop(
+    client: MatrixClient,
+    mut outbox: Outbox,
+    mut jobs: mpsc::Receiver<UserRequest>,
+    mut online: watch::Receiver<bool>,
+    mut to_app: Sender<RequestResult>,
+) {
+    if let Some(err) = outbox.take_load_error() {
+        if let Err(e) = to_app.send(RequestResult::Error(err)).await {
+            tracing::error!("client event handler crashed {}", e);
+            panic!("client event handler crashed {}", e)
+        }
+    }
+    if !outbox.messages().is_empty() {
+        let unsent = outbox.messages().to_vec();
+        if let Err(e) = to_app.send(RequestResult::Unsent(unsent)).await {
+            tracing::error!("client event handler crashed {}", e);
+            panic!("client event handler crashed {}", e)
+        }
+        flush_outbox(&client, &mut outbox, *online.borrow(), &mut to_app).await;
+    }
+
+    let mut was_online = *online.borrow();
+    loop {
+        let retry_in = outbox
+            .next_retry()
+            .map(|at| at.saturating_duration_since(Instant::now()));
+        let due = async move {
+            match retry_in {
+                Some(wait) => time::delay_for(wait).await,
+                None => future::pending().await,
+            }
+        };
+        let wake = tokio::select! {
+            request = jobs.recv() => OutboxWake::Request(request),
+            _ = due => OutboxWake::Due,
+            Some(is_online) = online.recv() => OutboxWake::Online(is_online),
+        };
+        let request = match wake {
+            OutboxWake::Request(request) => request,
+            // a message in the outbox is due to be retried
+            OutboxWake::Due => {
+                flush_outbox(&client, &mut outbox, *online.borrow(), &mut to_app).await;
+                continue;
+            }
+            OutboxWake::Online(is_online) => {
+                let restored = is_online && !was_online;
+                was_online = is_online;
+                // messages waiting on their backoff are sent now instead of when it ends
+                if restored {
+                    outbox.connection_restored();
+                    flush_outbox(&client, &mut outbox, *online.borrow(), &mut to_app).await;
+                }
+                continue;
+            }
+        };
+
+        match request {
+            None => return,
+            Some(UserRequest::SendMessage(room, msg, uuid)) => {
+                // the message is still sent if saving fails, it is only lost on quit
+                if let Err(e) = outbox.push(OutgoingMessage::new(room, msg, uuid)) {
+                    tracing::warn!("failed to save the outbox {}", e);
+                }
+                flush_outbox(&client, &mut outbox, *online.borrow(), &mut to_app).await;
+            }
+            Some(UserRequest::RetryMessages(unsent)) => {
+                let retried = match outbox.retry(&unsent) {
+                    Ok(retried) => retried,
+                    Err(err) => {
+                        if let Err(e) = to_app.send(RequestResult::Error(err)).await {
                             tracing::error!("client event handler crashed {}", e);
                             panic!("client event handler crashed {}", e)
                         }
+                        continue;
                     }
-                    UserRequest::Typing(room_id, user_id) => {
-                        let res = client
-                            .typing_notice(
-                                &room_id,
-                                &user_id,
-                                true,
-                                Some(Duration::from_millis(3000)),
-                            )
-                            .await;
-                        if let Err(e) = to_app.send(RequestResult::Typing(res)).await {
-                            tracing::error!("client event handler crashed {}", e);
-                            panic!("client event handler crashed {}", e)
-                        }
+                };
+                for (room_id, uuid) in retried {
+                    if let Err(e) = to_app
+                        .send(RequestResult::SendMessage(
+                            room_id,
+                            uuid,
+                            SendState::Pending,
+                        ))
+                        .await
+                    {
+                        tracing::error!("client event handler crashed {}", e);
+                        panic!("client event handler crashed {}", e)
                     }
                 }
+                flush_outbox(&client, &mut outbox, *online.borrow(), &mut to_app).await;
             }
-        });
-
-        (
-            MatrixEventHandle {
-                cli_jobs,
-                sync_jobs,
-                start_sync,
-                quit_flag,
-            },
-            app_sender,
-        )
+            Some(UserRequest::DiscardMessages(unsent)) => {
+                let res = match outbox.discard(&unsent) {
+                    Ok(discarded) => RequestResult::DiscardMessages(discarded),
+                    Err(err) => RequestResult::Error(err),
+                };
+                if let Err(e) = to_app.send(res).await {
+                    tracing::error!("client event handler crashed {}", e);
+                    panic!("client event handler crashed {}", e)
+                }
+            }
+            Some(_) => {}
+        }
     }
+}
 
-    /// This is called after login and initial sync to start `AsyncClient::sync_forever` loop.
-    pub(crate) fn start_sync(&self) {
-        self.start_sync
-            .swap(true, std::sync::atomic::Ordering::SeqCst);
-    }
+/// Why the outbox loop woke up.
+enum OutboxWake {
+    Request(Option<UserRequest>),
+    /// A message is due to be retried.
+    Due,
+    /// The sync loop reached the server, or stopped reaching it.
+    Online(bool),
+}
 
-    /// This is called when the user quits to signal the `tokio::Runtime` to shutdown.
-    pub(crate) fn quit_sync(&self) {
-        self.quit_flag
-            .swap(true, std::sync::atomic::Ordering::SeqCst);
+/// Send every message in the outbox that is due, the oldest message of a room is
+/// always sent first.
+async fn flush_outbox(
+    client: &MatrixClient,
+    outbox: &mut Outbox,
+    online: bool,
+    to_app: &mut Sender<RequestResult>,
+) {
+    loop {
+        let ready = outbox.ready(Instant::now());
+        if ready.is_empty() {
+            return;
+        }
+
+        for msg in ready {
+            let state = match client
+                .send_message(&msg.room_id, msg.content, msg.uuid)
+                .await
+            {
+                Ok(res) => {
+                    if let Err(e) = outbox.remove(&msg.uuid) {
+                        tracing::warn!("failed to save the outbox {}", e);
+                    }
+                    // a retry got through so don't wait to retry the rest
+                    if msg.retries > 0 {
+                        outbox.connection_restored();
+                    }
+                    SendState::Sent(res.event_id)
+                }
+                Err(err) => {
+                    let reason = err
+                        .to_string()
+                        .lines()
+                        .last()
+                        .unwrap_or_default()
+                        .to_string();
+                    outbox.send_failed(&msg.uuid, reason, Failure::of(&err, online))
+                }
+            };
+            if let Err(e) = to_app
+                .send(RequestResult::SendMessage(msg.room_id, msg.uuid, state))
+                .await
+            {
+                tracing::error!("client event handler crashed {}", e);
+                panic!("client event handler crashed {}", e)
+            }
+        }
     }
 }
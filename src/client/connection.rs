@@ -0,0 +1,112 @@
+//! The health of the sync loop shown in the header.
+
+use std::time::{Duration, Instant};
+
+/// A failed sync is never retried later than this unless the server asks.
+const MAX_SYNC_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Is the sync loop reaching the homeserver.
+#[derive(Clone, Debug)]
+pub enum Connection {
+    /// The last sync succeeded at the given time.
+    Online(Instant),
+    /// The last sync failed and is retried at `retry_at`.
+    Offline {
+        reason: String,
+        last_sync: Option<Instant>,
+        retry_at: Instant,
+    },
+}
+
+impl Connection {
+    pub fn is_online(&self) -> bool {
+        matches!(self, Self::Online(_))
+    }
+
+    /// A short description of the connection as of `now`.
+    pub fn status(&self, now: Instant) -> String {
+        match self {
+            Self::Online(last_sync) => format!(
+                "online, synced {} ago",
+                short_duration(now.saturating_duration_since(*last_sync))
+            ),
+            Self::Offline {
+                reason,
+                last_sync,
+                retry_at,
+            } => {
+                let last = last_sync
+                    .map(|at| {
+                        format!(
+                            ", last synced {} ago",
+                            short_duration(now.saturating_duration_since(at))
+                        )
+                    })
+                    .unwrap_or_default();
+                format!(
+                    "offline: {}, retrying in {}{}",
+                    reason,
+                    short_duration(retry_at.saturating_duration_since(now)),
+                    last
+                )
+            }
+        }
+    }
+}
+
+/// How long to wait before retrying after `failures` failed syncs in a row.
+///
+/// The wait doubles with each failure up to `MAX_SYNC_BACKOFF`, when the server
+/// rate limits with a `retry_after` that is used instead.
+pub fn retry_delay(failures: u32, retry_after: Option<Duration>) -> Duration {
+    retry_after.unwrap_or_else(|| {
+        Duration::from_secs(2_u64.saturating_pow(failures.saturating_sub(1))).min(MAX_SYNC_BACKOFF)
+    })
+}
+
+fn short_duration(dur: Duration) -> String {
+    let secs = dur.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 60 * 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}h", secs / (60 * 60))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn retry_delay_backs_off() {
+        assert_eq!(retry_delay(1, None), Duration::from_secs(1));
+        assert_eq!(retry_delay(3, None), Duration::from_secs(4));
+        assert_eq!(retry_delay(30, None), MAX_SYNC_BACKOFF);
+        // the server knows best
+        assert_eq!(
+            retry_delay(30, Some(Duration::from_secs(120))),
+            Duration::from_secs(120)
+        );
+    }
+
+    #[test]
+    fn status() {
+        let now = Instant::now();
+        let online = Connection::Online(now - Duration::from_secs(5));
+        assert!(online.is_online());
+        assert_eq!(online.status(now), "online, synced 5s ago");
+
+        let offline = Connection::Offline {
+            reason: "connection refused".into(),
+            last_sync: Some(now - Duration::from_secs(125)),
+            retry_at: now + Duration::from_secs(8),
+        };
+        assert!(!offline.is_online());
+        assert_eq!(
+            offline.status(now),
+            "offline: connection refused, retrying in 8s, last synced 2m ago"
+        );
+    }
+}
@@ -14,9 +14,7 @@ use matrix_sdk::{
             canonical_alias::CanonicalAliasEventContent,
             join_rules::JoinRulesEventContent,
             member::{MemberEventContent, MembershipChange},
-            message::{
-                feedback::FeedbackEventContent, MessageEventContent, TextMessageEventContent,
-            },
+            message::{feedback::FeedbackEventContent, MessageEventContent},
             name::NameEventContent,
             power_levels::PowerLevelsEventContent,
             redaction::SyncRedactionEvent,
@@ -25,7 +23,7 @@ use matrix_sdk::{
         typing::TypingEventContent,
         BasicEvent, EphemeralRoomEvent, StrippedStateEvent, SyncMessageEvent, SyncStateEvent,
     },
-    identifiers::{EventId, RoomId, UserId},
+    identifiers::{EventId, RoomAliasId, RoomId, UserId},
     CustomOrRawEvent, EventEmitter, Room, SyncRoom,
 };
 
@@ -55,11 +53,13 @@ pub enum StateResult {
     Message(Message, RoomId),
     MessageEdit(String, RoomId, EventId),
     Name(String, RoomId),
+    CanonicalAlias(RoomAliasId, RoomId),
     FullyRead(EventId, RoomId),
     ReadReceipt(RoomId, BTreeMap<EventId, Receipts>),
     Reaction(EventId, EventId, RoomId, String),
     Redact(EventId, RoomId),
-    Typing(RoomId, String),
+    /// The `UserId` and name of each member typing in the room.
+    Typing(RoomId, Vec<(UserId, String)>),
     Err,
 }
 unsafe impl Send for StateResult {}
@@ -110,6 +110,28 @@ impl EventStream {
             panic!("{}", e)
         }
     }
+
+    async fn handle_canonical_alias(
+        &self,
+        room: SyncRoom,
+        event: &SyncStateEvent<CanonicalAliasEventContent>,
+    ) {
+        if let (SyncRoom::Joined(room), Some(alias)) = (room, &event.content.alias) {
+            if let Err(e) = self
+                .send
+                .lock()
+                .await
+                .send(StateResult::CanonicalAlias(
+                    alias.clone(),
+                    room.read().await.room_id.clone(),
+                ))
+                .await
+            {
+                tracing::error!("event stream channel closed {}", e);
+                panic!("{}", e)
+            }
+        }
+    }
 }
 #[allow(clippy::eval_order_dependence)]
 #[async_trait::async_trait]
@@ -143,9 +165,10 @@ impl EventEmitter for EventStream {
     /// Fires when `AsyncClient` receives a `RoomEvent::RoomCanonicalAlias` event.
     async fn on_room_canonical_alias(
         &self,
-        _: SyncRoom,
-        _: &SyncStateEvent<CanonicalAliasEventContent>,
+        room: SyncRoom,
+        event: &SyncStateEvent<CanonicalAliasEventContent>,
     ) {
+        self.handle_canonical_alias(room, event).await
     }
     /// Fires when `AsyncClient` receives a `RoomEvent::RoomAliases` event.
     async fn on_room_aliases(&self, _: SyncRoom, _: &SyncStateEvent<AliasesEventContent>) {}
@@ -163,58 +186,38 @@ impl EventEmitter for EventStream {
                 ..
             } = event;
 
-            let name = if let Some(mem) = room.read().await.joined_members.get(&sender) {
-                mem.name()
-            } else {
-                sender.localpart().into()
-            };
-            match content {
-                MessageEventContent::Text(TextMessageEventContent {
-                    body, formatted, ..
-                }) => {
-                    let msg = if formatted
-                        .as_ref()
-                        .map(|f| f.body.to_string())
-                        .unwrap_or(body.to_string())
-                        != *body
-                    {
-                        // This is extremely expensive
-                        // TODO cache these results somehow
-                        crate::widgets::utils::markdown_to_terminal(body).unwrap_or(body.clone())
-                    } else {
-                        body.clone()
-                    };
-                    let txn_id = unsigned
-                        .transaction_id
-                        .as_ref()
-                        .cloned()
-                        .unwrap_or_default();
+            let name = crate::widgets::utils::display_name(&*room.read().await, sender);
+            if let Some(msg) = crate::widgets::utils::message_text(content) {
+                let txn_id = unsigned
+                    .transaction_id
+                    .as_ref()
+                    .cloned()
+                    .unwrap_or_default();
 
-                    if let Err(e) = self
-                        .send
-                        .lock()
-                        .await
-                        .send(StateResult::Message(
-                            Message {
-                                name,
-                                user: sender.clone(),
-                                text: msg,
-                                event_id: event_id.clone(),
-                                timestamp: *origin_server_ts,
-                                uuid: Uuid::parse_str(&txn_id).unwrap_or(Uuid::new_v4()),
-                                read: false,
-                                reactions: vec![],
-                                sent_receipt: false,
-                            },
-                            room.read().await.room_id.clone(),
-                        ))
-                        .await
-                    {
-                        tracing::error!("event stream channel closed {}", e);
-                        panic!("{}", e)
-                    }
+                if let Err(e) = self
+                    .send
+                    .lock()
+                    .await
+                    .send(StateResult::Message(
+                        Message {
+                            name,
+                            user: sender.clone(),
+                            text: msg,
+                            event_id: event_id.clone(),
+                            timestamp: *origin_server_ts,
+                            uuid: Uuid::parse_str(&txn_id).unwrap_or(Uuid::new_v4()),
+                            read: false,
+                            reactions: vec![],
+                            sent_receipt: false,
+                            send_state: None,
+                        },
+                        room.read().await.room_id.clone(),
+                    ))
+                    .await
+                {
+                    tracing::error!("event stream channel closed {}", e);
+                    panic!("{}", e)
                 }
-                _ => {}
             }
         }
     }
@@ -257,9 +260,10 @@ impl EventEmitter for EventStream {
     /// Fires when `AsyncClient` receives a `StateEvent::RoomCanonicalAlias` event.
     async fn on_state_canonical_alias(
         &self,
-        _: SyncRoom,
-        _: &SyncStateEvent<CanonicalAliasEventContent>,
+        room: SyncRoom,
+        event: &SyncStateEvent<CanonicalAliasEventContent>,
     ) {
+        self.handle_canonical_alias(room, event).await
     }
     /// Fires when `AsyncClient` receives a `StateEvent::RoomAliases` event.
     async fn on_state_aliases(&self, _: SyncRoom, _: &SyncStateEvent<AliasesEventContent>) {}
@@ -384,8 +388,6 @@ impl EventEmitter for EventStream {
         }
     }
 
-    // TODO make the StateResult::Typing variants a list of typing users and make messages in app
-    // like every other StateResult. Use Room::compute_display_name or whatever when PR is done
     /// Fires when `AsyncClient` receives a `NonRoomEvent::Typing` event.
     async fn on_non_room_typing(
         &self,
@@ -393,29 +395,23 @@ impl EventEmitter for EventStream {
         event: &EphemeralRoomEvent<TypingEventContent>,
     ) {
         if let SyncRoom::Joined(room) = room {
-            let typing = room
-                .read()
-                .await
-                .joined_members
-                .iter()
-                .filter(|(id, _)| event.content.user_ids.contains(id))
-                .map(|(_, mem)| mem.name.to_string())
-                .collect::<Vec<String>>();
-            let room_id = room.read().await.room_id.clone();
-            let notice = if typing.is_empty() {
-                String::default()
-            } else {
-                format!(
-                    "{} {} typing...",
-                    typing.join(", "),
-                    if typing.len() > 1 { "are" } else { "is" }
-                )
+            let (room_id, typing) = {
+                let room = room.read().await;
+                let mut names = crate::widgets::utils::member_names(&room);
+                let typing = event
+                    .content
+                    .user_ids
+                    .iter()
+                    .filter(|id| room.joined_members.contains_key(*id))
+                    .filter_map(|id| names.remove_entry(id))
+                    .collect::<Vec<_>>();
+                (room.room_id.clone(), typing)
             };
             if let Err(e) = self
                 .send
                 .lock()
                 .await
-                .send(StateResult::Typing(room_id, notice))
+                .send(StateResult::Typing(room_id, typing))
                 .await
             {
                 tracing::error!("event stream channel closed {}", e);
@@ -1,35 +1,69 @@
-use std::{collections::HashMap, fmt, path::Path, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
+use futures::stream::{self, StreamExt};
 use matrix_sdk::{
     self,
-    api::r0::{
-        account::register::{self, RegistrationKind},
-        directory::get_public_rooms_filtered::{self, Filter, RoomNetwork},
-        membership::{forget_room, join_room_by_id, kick_user, leave_room},
-        message::{create_message_event, get_message_events},
-        read_marker::set_read_marker,
-        receipt::create_receipt,
-        session::login,
-        typing::create_typing_event,
+    api::{
+        error::ErrorKind,
+        r0::{
+            account::register::{self, RegistrationKind},
+            alias::get_alias,
+            directory::get_public_rooms_filtered::{self, Filter, RoomNetwork},
+            membership::{forget_room, invite_user, join_room_by_id, kick_user, leave_room},
+            message::{create_message_event, get_message_events},
+            profile::set_display_name,
+            read_marker::set_read_marker,
+            receipt::create_receipt,
+            room::{create_room::RoomPreset, Visibility},
+            session::login,
+            typing::create_typing_event,
+        },
+        Error as RumaApiError,
     },
     events::room::message::MessageEventContent,
-    identifiers::{EventId, RoomId, UserId},
-    Client, ClientConfig, JsonStore, RegistrationBuilder, Room, RoomListFilterBuilder,
-    SyncSettings,
+    identifiers::{EventId, RoomAliasId, RoomId, RoomIdOrAliasId, UserId},
+    Client, ClientConfig, Error as MatrixError, FromHttpResponseError, JsonStore,
+    RegistrationBuilder, Room, RoomBuilder, RoomListFilterBuilder, ServerError, SyncSettings,
 };
 use tokio::{fs as async_fs, sync::RwLock};
 use url::Url;
 use uuid::Uuid;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use preview::UrlPreview;
+use room_link::{RoomLink, RoomTarget};
 
-use ruma_ext::auth::{self, dummy, SessionObj};
+use ruma_ext::{
+    account_data::{
+        create_tag, delete_tag, get_global_account_data, get_tags, set_global_account_data,
+        DirectEventContent, TagInfo, Tags,
+    },
+    auth::{self, dummy, SessionObj},
+    media::get_media_preview,
+    message::{
+        redact_event, send_message_event, EditEventContent, ExtraMessageEventContent,
+        MessageReplacement, RelatesTo,
+    },
+    state::send_state_event,
+};
 
 pub mod client_loop;
+pub mod connection;
 pub mod event_stream;
+pub mod outbox;
+pub mod preview;
+pub mod room_link;
 pub mod ruma_ext;
 
-const SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+pub(crate) const SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+/// How many rooms' tags are fetched at once, more than this gets rate limited.
+const TAG_REQUESTS: usize = 4;
 
 #[cfg(target_os = "linux")]
 const RUMATUI_ID: &str = "rumatui command line client (LINUX)";
@@ -47,7 +81,8 @@ pub struct MatrixClient {
     user: Option<UserId>,
     settings: SyncSettings,
     next_batch: Option<String>,
-    last_scroll: HashMap<RoomId, String>,
+    /// Where loading the history of each room got to, shared by every clone.
+    last_scroll: Arc<Mutex<HashMap<RoomId, String>>>,
 }
 unsafe impl Send for MatrixClient {}
 
@@ -80,7 +115,7 @@ impl MatrixClient {
             user: None,
             settings: SyncSettings::default(),
             next_batch: None,
-            last_scroll: HashMap::new(),
+            last_scroll: Arc::default(),
         };
 
         Ok(client)
@@ -218,6 +253,78 @@ impl MatrixClient {
             .map_err(Into::into)
     }
 
+    /// Replaces the text of a message the user sent with `body`.
+    ///
+    /// # Arguments
+    ///
+    /// * id - The room the message is in.
+    /// * event_id - The message to edit.
+    /// * body - The new text of the message.
+    pub(crate) async fn edit_message(
+        &self,
+        id: &RoomId,
+        event_id: &EventId,
+        body: String,
+    ) -> Result<send_message_event::Response> {
+        let content = ExtraMessageEventContent::EditEvent(EditEventContent {
+            // clients that do not understand edits show this
+            body: format!("* {}", body),
+            new_content: MessageReplacement {
+                body,
+                format: None,
+                formatted_body: None,
+                msgtype: "m.text".to_string(),
+            },
+            relates_to: RelatesTo {
+                event_id: event_id.clone(),
+                rel_type: "m.replace".to_string(),
+            },
+        });
+        let request = send_message_event::Request {
+            room_id: id.clone(),
+            event_type: "m.room.message".to_string(),
+            txn_id: Uuid::new_v4().to_string(),
+            data: serde_json::to_value(content)?,
+        };
+
+        self.inner.send(request).await.map_err(Into::into)
+    }
+
+    /// Deletes the content of a message.
+    ///
+    /// # Arguments
+    ///
+    /// * id - The room the message is in.
+    /// * event_id - The message to redact.
+    pub(crate) async fn redact_message(
+        &self,
+        id: &RoomId,
+        event_id: &EventId,
+    ) -> Result<redact_event::Response> {
+        let request = redact_event::Request {
+            room_id: id.clone(),
+            event_id: event_id.clone(),
+            txn_id: Uuid::new_v4().to_string(),
+            reason: None,
+        };
+
+        self.inner.send(request).await.map_err(Into::into)
+    }
+
+    /// Fetches the preview of `url` from the homeserver, `None` when the page has
+    /// nothing to show.
+    pub(crate) async fn url_preview(&self, url: &str) -> Result<Option<UrlPreview>> {
+        let request = get_media_preview::Request {
+            url: url.to_string(),
+        };
+
+        self.inner
+            .send(request)
+            .await
+            .map(|res| UrlPreview::from_og(&res.data))
+            .map_err(Into::into)
+    }
+
     /// Gets the `RoomEvent`s backwards in time, when user scrolls up.
     ///
     /// This uses the current sync token to look backwards from that point.
@@ -226,11 +333,8 @@ impl MatrixClient {
     ///
     /// * id - A valid RoomId otherwise sending will fail.
     ///
-    pub(crate) async fn get_messages(
-        &mut self,
-        id: &RoomId,
-    ) -> Result<get_message_events::Response> {
-        let from = if let Some(scroll) = self.last_scroll.get(id) {
+    pub(crate) async fn get_messages(&self, id: &RoomId) -> Result<get_message_events::Response> {
+        let from = if let Some(scroll) = self.last_scroll.lock().unwrap().get(id) {
             scroll.clone()
         } else {
             self.next_batch.as_ref().unwrap().clone()
@@ -251,7 +355,10 @@ impl MatrixClient {
         match self.inner.room_messages(request).await {
             Ok(res) => {
                 if let Some(end) = &res.end {
-                    self.last_scroll.insert(id.clone(), end.clone());
+                    self.last_scroll
+                        .lock()
+                        .unwrap()
+                        .insert(id.clone(), end.clone());
                 }
                 Ok(res)
             }
@@ -260,7 +367,7 @@ impl MatrixClient {
     }
 
     pub(crate) async fn get_rooms_filtered(
-        &mut self,
+        &self,
         filter: &str,
         network: RoomNetwork,
         token: Option<String>,
@@ -302,6 +409,322 @@ impl MatrixClient {
             .map_err(Into::into)
     }
 
+    /// Resolves a room alias to the `RoomId` and the servers that know about the room
+    /// using the room directory.
+    ///
+    /// # Arguments
+    ///
+    /// * alias - The `RoomAliasId` to resolve.
+    pub(crate) async fn resolve_alias(&self, alias: &RoomAliasId) -> Result<(RoomId, Vec<String>)> {
+        let request = get_alias::Request {
+            room_alias: alias.clone(),
+        };
+
+        match self.inner.send(request).await {
+            Ok(res) => Ok((
+                res.room_id,
+                res.servers.into_iter().map(|s| s.to_string()).collect(),
+            )),
+            Err(err) => match Error::from(err) {
+                // keep the errcode and status but say which alias was not found
+                Error::Api(mut err) if matches!(err.kind, ErrorKind::NotFound) => {
+                    err.message = format!(
+                        "The room alias {} could not be found, check the alias and its server name",
+                        alias
+                    );
+                    Err(Error::Api(err))
+                }
+                err => Err(err),
+            },
+        }
+    }
+
+    /// Joins the room the link points to and returns the `RoomId` of the joined room.
+    ///
+    /// Aliases are resolved through the room directory first, the servers the directory
+    /// knows about are used to join through unless the link has `via` servers.
+    ///
+    /// # Arguments
+    ///
+    /// * link - The alias or room id and the servers to join through.
+    pub(crate) async fn join_room(&self, link: &RoomLink) -> Result<RoomId> {
+        let (room_id, via) = match &link.target {
+            RoomTarget::Id(room_id) => (room_id.clone(), link.via.clone()),
+            RoomTarget::Alias(alias) => {
+                let (room_id, servers) = self.resolve_alias(alias).await?;
+                if link.via.is_empty() {
+                    (room_id, servers)
+                } else {
+                    (room_id, link.via.clone())
+                }
+            }
+        };
+
+        self.inner
+            .join_room_by_id_or_alias(&RoomIdOrAliasId::from(room_id), &via)
+            .await
+            .map(|res| res.room_id)
+            .map_err(Into::into)
+    }
+
+    /// Invites the specified user to the room.
+    ///
+    /// # Arguments
+    ///
+    /// * room_id - The `RoomId` of the room to invite the user to.
+    ///
+    /// * user_id - The `UserId` of the user to invite.
+    pub(crate) async fn invite_user(
+        &self,
+        room_id: &RoomId,
+        user_id: &UserId,
+    ) -> Result<invite_user::Response> {
+        self.inner
+            .invite_user_by_id(room_id, user_id)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Sets the display name of the logged in user.
+    ///
+    /// # Arguments
+    ///
+    /// * name - The new display name.
+    pub(crate) async fn set_display_name(
+        &self,
+        name: String,
+    ) -> Result<set_display_name::Response> {
+        let user_id = self.user.clone().ok_or(Error::NeedAuth(
+            "A user must be logged in to set a display name".into(),
+        ))?;
+        let request = set_display_name::Request {
+            user_id,
+            displayname: Some(name),
+        };
+
+        self.inner.send(request).await.map_err(Into::into)
+    }
+
+    /// Sets the topic of the specified room.
+    ///
+    /// # Arguments
+    ///
+    /// * room_id - The `RoomId` of the room to set the topic of.
+    ///
+    /// * topic - The new topic.
+    pub(crate) async fn set_topic(
+        &self,
+        room_id: &RoomId,
+        topic: String,
+    ) -> Result<send_state_event::Response> {
+        let request = send_state_event::Request {
+            room_id: room_id.clone(),
+            event_type: "m.room.topic".to_string(),
+            data: serde_json::json!({ "topic": topic }),
+        };
+
+        self.inner.send(request).await.map_err(Into::into)
+    }
+
+    /// Fetches the `m.direct` account data, the map of users to direct message rooms.
+    ///
+    /// If the user has never had a direct message an empty map is returned.
+    pub(crate) async fn direct_rooms(&self) -> Result<DirectEventContent> {
+        let user_id = self.user.clone().ok_or(Error::NeedAuth(
+            "A user must be logged in to fetch direct message rooms".into(),
+        ))?;
+        let request = get_global_account_data::Request {
+            user_id,
+            event_type: "m.direct".to_string(),
+        };
+
+        match self.inner.send(request).await {
+            Ok(res) => serde_json::from_value(res.account_data).map_err(Into::into),
+            // the server has no `m.direct` event for this user yet
+            Err(MatrixError::RumaResponse(FromHttpResponseError::Http(ServerError::Known(
+                RumaApiError {
+                    kind: ErrorKind::NotFound,
+                    ..
+                },
+            )))) => Ok(DirectEventContent::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Replaces the `m.direct` account data with `direct`.
+    ///
+    /// # Arguments
+    ///
+    /// * direct - The complete map of users to direct message rooms.
+    pub(crate) async fn set_direct_rooms(&self, direct: &DirectEventContent) -> Result<()> {
+        let user_id = self.user.clone().ok_or(Error::NeedAuth(
+            "A user must be logged in to update direct message rooms".into(),
+        ))?;
+        let request = set_global_account_data::Request {
+            user_id,
+            event_type: "m.direct".to_string(),
+            data: serde_json::to_value(direct)?,
+        };
+
+        self.inner
+            .send(request)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    /// Starts a direct message with `user_id`.
+    ///
+    /// If a joined direct message room with the user already exists it is reused,
+    /// otherwise a new private room with `is_direct` set is created and `m.direct`
+    /// is updated to include it.
+    ///
+    /// Returns the `RoomId` of the direct message room and the updated `m.direct` map.
+    ///
+    /// # Arguments
+    ///
+    /// * user_id - The `UserId` of the user to start the direct message with.
+    pub(crate) async fn start_direct_message(
+        &self,
+        user_id: &UserId,
+    ) -> Result<(RoomId, DirectEventContent)> {
+        let mut direct = self.direct_rooms().await?;
+
+        let existing = {
+            let joined = self.inner.joined_rooms();
+            let rooms = joined.read().await;
+            direct
+                .get(user_id)
+                .and_then(|ids| ids.iter().find(|id| rooms.contains_key(id)).cloned())
+        };
+        if let Some(room_id) = existing {
+            return Ok((room_id, direct));
+        }
+
+        let mut room = RoomBuilder::new();
+        room.is_direct(true)
+            .invite(vec![user_id.clone()])
+            .preset(RoomPreset::TrustedPrivateChat)
+            .visibility(Visibility::Private);
+
+        let res = self.inner.create_room(room).await?;
+
+        direct
+            .entry(user_id.clone())
+            .or_default()
+            .push(res.room_id.clone());
+        self.set_direct_rooms(&direct).await?;
+
+        Ok((res.room_id, direct))
+    }
+
+    /// Fetches the `m.tag` account data of the specified room.
+    ///
+    /// # Arguments
+    ///
+    /// * room_id - The `RoomId` of the room to get the tags of.
+    pub(crate) async fn room_tags(&self, room_id: &RoomId) -> Result<Tags> {
+        let user_id = self.user.clone().ok_or(Error::NeedAuth(
+            "A user must be logged in to fetch room tags".into(),
+        ))?;
+        let request = get_tags::Request {
+            user_id,
+            room_id: room_id.clone(),
+        };
+
+        self.inner
+            .send(request)
+            .await
+            .map(|res| res.tags)
+            .map_err(Into::into)
+    }
+
+    /// Fetches the `m.tag` account data of every joined room.
+    ///
+    /// `TAG_REQUESTS` rooms are fetched at a time, a room that fails is returned with its
+    /// error so the tags of the other rooms are still used.
+    pub(crate) async fn all_room_tags(
+        &self,
+    ) -> Result<(HashMap<RoomId, Tags>, Vec<(RoomId, Error)>)> {
+        if self.user.is_none() {
+            return Err(Error::NeedAuth(
+                "A user must be logged in to fetch room tags".into(),
+            ));
+        }
+        let room_ids = self
+            .inner
+            .joined_rooms()
+            .read()
+            .await
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let results = stream::iter(room_ids)
+            .map(|room_id| async move {
+                let res = self.room_tags(&room_id).await;
+                (room_id, res)
+            })
+            .buffer_unordered(TAG_REQUESTS)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut tags = HashMap::new();
+        let mut failed = Vec::new();
+        for (room_id, res) in results {
+            match res {
+                Ok(room_tags) => {
+                    tags.insert(room_id, room_tags);
+                }
+                Err(e) => failed.push((room_id, e)),
+            }
+        }
+        Ok((tags, failed))
+    }
+
+    /// Adds `tag` to the specified room and returns the updated tags of the room.
+    ///
+    /// # Arguments
+    ///
+    /// * room_id - The `RoomId` of the room to tag.
+    ///
+    /// * tag - The tag, `m.favourite`, `m.lowpriority` or a user defined `u.*` tag.
+    pub(crate) async fn tag_room(&self, room_id: &RoomId, tag: String) -> Result<Tags> {
+        let user_id = self.user.clone().ok_or(Error::NeedAuth(
+            "A user must be logged in to tag rooms".into(),
+        ))?;
+        let request = create_tag::Request {
+            user_id,
+            room_id: room_id.clone(),
+            tag,
+            tag_info: TagInfo::default(),
+        };
+
+        self.inner.send(request).await?;
+        self.room_tags(room_id).await
+    }
+
+    /// Removes `tag` from the specified room and returns the updated tags of the room.
+    ///
+    /// # Arguments
+    ///
+    /// * room_id - The `RoomId` of the room to untag.
+    ///
+    /// * tag - The tag to remove.
+    pub(crate) async fn untag_room(&self, room_id: &RoomId, tag: String) -> Result<Tags> {
+        let user_id = self.user.clone().ok_or(Error::NeedAuth(
+            "A user must be logged in to untag rooms".into(),
+        ))?;
+        let request = delete_tag::Request {
+            user_id,
+            room_id: room_id.clone(),
+            tag,
+        };
+
+        self.inner.send(request).await?;
+        self.room_tags(room_id).await
+    }
+
     /// Forgets the specified room.
     ///
     /// # Arguments
@@ -0,0 +1,483 @@
+//! Messages that have not been accepted by the server yet.
+//!
+//! Every message is written to disk before it is sent so nothing is lost if sending
+//! fails or `rumatui` quits. Only the oldest message of each room is ever sent so
+//! the order within a room is kept, a failed message holds back the rest of its room.
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use matrix_sdk::{
+    events::room::message::MessageEventContent,
+    identifiers::{EventId, RoomId, UserId},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+/// After this many failed attempts a message is only retried when the user asks, failures
+/// while offline or that go away on their own are not counted.
+const MAX_ATTEMPTS: u32 = 8;
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// Where a message the user sent is on its way to the server.
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
+pub enum SendState {
+    /// Waiting in the outbox to be sent or retried.
+    Pending,
+    /// Retrying gave up, the message stays in the outbox until it is retried or discarded.
+    Failed(String),
+    /// The server accepted the message.
+    Sent(EventId),
+    /// Another member of the room has read the message.
+    Seen,
+}
+
+/// Which unsent messages `/retry` and `/discard` act on.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Unsent {
+    /// One message, by the transaction id it is sent with.
+    Message(Uuid),
+    /// Every unsent message of the room.
+    Room(RoomId),
+}
+
+impl Unsent {
+    fn matches(&self, msg: &OutgoingMessage) -> bool {
+        match self {
+            Unsent::Message(uuid) => &msg.uuid == uuid,
+            Unsent::Room(room) => &msg.room_id == room,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OutgoingMessage {
+    pub room_id: RoomId,
+    /// The transaction id the message is sent with, the echo of the message uses the same `Uuid`.
+    pub uuid: Uuid,
+    pub content: MessageEventContent,
+    /// The failed attempts that count toward `MAX_ATTEMPTS`.
+    #[serde(default)]
+    pub attempts: u32,
+    /// How many times in a row sending has failed, sets the backoff.
+    #[serde(default)]
+    pub retries: u32,
+    /// Automatic retrying gave up.
+    #[serde(default)]
+    pub failed: bool,
+    /// When to try sending again, `None` is as soon as possible.
+    #[serde(skip)]
+    retry_at: Option<Instant>,
+}
+
+impl OutgoingMessage {
+    pub fn new(room_id: RoomId, content: MessageEventContent, uuid: Uuid) -> Self {
+        Self {
+            room_id,
+            uuid,
+            content,
+            attempts: 0,
+            retries: 0,
+            failed: false,
+            retry_at: None,
+        }
+    }
+}
+
+/// How a message that failed to send is retried.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Failure {
+    /// The server refused the message, retrying will not help.
+    Permanent,
+    /// The server could not be reached or asked to wait, the message is retried until it
+    /// is sent, after `retry_after` when the server gave one.
+    Transient { retry_after: Option<Duration> },
+    /// Any other failure, retried up to `MAX_ATTEMPTS` times.
+    Counted,
+}
+
+impl Failure {
+    /// How to retry after `err`, `online` is whether the sync loop reaches the server.
+    pub fn of(err: &Error, online: bool) -> Self {
+        if err.is_permanent() {
+            Self::Permanent
+        } else if err.is_retryable() || !online {
+            Self::Transient {
+                retry_after: err.retry_after(),
+            }
+        } else {
+            Self::Counted
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Outbox {
+    path: PathBuf,
+    /// The unsent messages of every room, oldest first.
+    messages: Vec<OutgoingMessage>,
+    /// Why the outbox on disk could not be loaded, reported to the user once.
+    load_error: Option<Error>,
+}
+
+impl Outbox {
+    /// Load the outbox of `user`, a missing outbox is empty and an unreadable one is
+    /// moved aside so the messages in it are not overwritten.
+    pub fn load(user: &UserId) -> Self {
+        let mut path = crate::RUMATUI_DIR.as_ref().unwrap().to_path_buf();
+        path.push(format!(
+            "outbox-{}-{}.json",
+            user.localpart(),
+            user.server_name()
+        ));
+        Self::from_path(path)
+    }
+
+    fn from_path(path: PathBuf) -> Self {
+        let mut load_error = None;
+        let messages = match fs::read_to_string(&path) {
+            Err(_) => vec![],
+            Ok(json) => match serde_json::from_str(&json) {
+                Ok(msgs) => msgs,
+                Err(e) => {
+                    tracing::warn!("failed to read the outbox {}", e);
+                    let corrupt = path.with_extension("json.corrupt");
+                    load_error = Some(match fs::rename(&path, &corrupt) {
+                        Ok(()) => Error::Json(format!(
+                            "the unsent messages could not be read and were moved to {}\n{}",
+                            corrupt.display(),
+                            e
+                        )),
+                        Err(io) => Error::Io(format!(
+                            "the unsent messages could not be read or moved aside\n{}",
+                            io
+                        )),
+                    });
+                    vec![]
+                }
+            },
+        };
+        Self {
+            path,
+            messages,
+            load_error,
+        }
+    }
+
+    /// Why the outbox on disk could not be loaded, only returned the first time.
+    pub fn take_load_error(&mut self) -> Option<Error> {
+        self.load_error.take()
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string(&self.messages)?;
+        // a crash while writing leaves the previous outbox instead of a truncated one
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &self.path).map_err(Into::into)
+    }
+
+    /// All unsent messages, oldest first.
+    pub fn messages(&self) -> &[OutgoingMessage] {
+        &self.messages
+    }
+
+    pub fn push(&mut self, msg: OutgoingMessage) -> Result<()> {
+        self.messages.push(msg);
+        self.save()
+    }
+
+    /// Remove a message once the server has accepted it.
+    pub fn remove(&mut self, uuid: &Uuid) -> Result<Option<OutgoingMessage>> {
+        let msg = self
+            .messages
+            .iter()
+            .position(|m| &m.uuid == uuid)
+            .map(|idx| self.messages.remove(idx));
+        self.save()?;
+        Ok(msg)
+    }
+
+    /// The oldest message of each room if it is due to be sent.
+    pub fn ready(&self, now: Instant) -> Vec<OutgoingMessage> {
+        let mut ready: Vec<OutgoingMessage> = vec![];
+        let mut seen: Vec<&RoomId> = vec![];
+        for msg in &self.messages {
+            if seen.contains(&&msg.room_id) {
+                continue;
+            }
+            seen.push(&msg.room_id);
+            if !msg.failed && msg.retry_at.map_or(true, |at| at <= now) {
+                ready.push(msg.clone());
+            }
+        }
+        ready
+    }
+
+    /// The next time a message is due to be retried.
+    pub fn next_retry(&self) -> Option<Instant> {
+        self.messages
+            .iter()
+            .filter(|m| !m.failed)
+            .filter_map(|m| m.retry_at)
+            .min()
+    }
+
+    /// Record a failed attempt, the message is retried later unless the `failure` is
+    /// permanent or it has failed too many times.
+    pub fn send_failed(&mut self, uuid: &Uuid, reason: String, failure: Failure) -> SendState {
+        let state = match self.messages.iter_mut().find(|m| &m.uuid == uuid) {
+            Some(msg) => {
+                msg.retries += 1;
+                let retry_in = match failure {
+                    Failure::Permanent => None,
+                    Failure::Transient { retry_after } => {
+                        Some(retry_after.unwrap_or_else(|| backoff(msg.retries)))
+                    }
+                    Failure::Counted => {
+                        msg.attempts += 1;
+                        if msg.attempts < MAX_ATTEMPTS {
+                            Some(backoff(msg.retries))
+                        } else {
+                            None
+                        }
+                    }
+                };
+                match retry_in {
+                    Some(wait) => {
+                        msg.retry_at = Some(Instant::now() + wait);
+                        SendState::Pending
+                    }
+                    None => {
+                        msg.failed = true;
+                        SendState::Failed(reason)
+                    }
+                }
+            }
+            None => SendState::Failed(reason),
+        };
+        if let Err(e) = self.save() {
+            tracing::warn!("failed to save the outbox {}", e);
+        }
+        state
+    }
+
+    /// Something reached the server so waiting messages are retried now.
+    pub fn connection_restored(&mut self) {
+        for msg in self.messages.iter_mut() {
+            msg.retries = 0;
+            msg.retry_at = None;
+        }
+    }
+
+    /// Retry the `unsent` messages, returns the room and `Uuid` of each message.
+    pub fn retry(&mut self, unsent: &Unsent) -> Result<Vec<(RoomId, Uuid)>> {
+        let mut retried = vec![];
+        for msg in self.messages.iter_mut().filter(|m| unsent.matches(m)) {
+            msg.attempts = 0;
+            msg.retries = 0;
+            msg.failed = false;
+            msg.retry_at = None;
+            retried.push((msg.room_id.clone(), msg.uuid));
+        }
+        self.save()?;
+        Ok(retried)
+    }
+
+    /// Discard the `unsent` messages, returns the room and `Uuid` of each message.
+    pub fn discard(&mut self, unsent: &Unsent) -> Result<Vec<(RoomId, Uuid)>> {
+        let discarded = self
+            .messages
+            .iter()
+            .filter(|m| unsent.matches(m))
+            .map(|m| (m.room_id.clone(), m.uuid))
+            .collect();
+        self.messages.retain(|m| !unsent.matches(m));
+        self.save()?;
+        Ok(discarded)
+    }
+}
+
+/// 2, 4, 8... seconds up to `MAX_BACKOFF`.
+fn backoff(attempts: u32) -> Duration {
+    Duration::from_secs(2_u64.saturating_pow(attempts)).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use matrix_sdk::events::room::message::TextMessageEventContent;
+
+    use super::*;
+
+    fn message(room: &RoomId, text: &str) -> OutgoingMessage {
+        OutgoingMessage::new(
+            room.clone(),
+            MessageEventContent::Text(TextMessageEventContent::new_plain(text)),
+            Uuid::new_v4(),
+        )
+    }
+
+    fn outbox() -> Outbox {
+        let mut path = std::env::temp_dir();
+        path.push(format!("rumatui-outbox-{}.json", Uuid::new_v4()));
+        Outbox::from_path(path)
+    }
+
+    #[test]
+    fn keeps_room_order() {
+        let room = RoomId::try_from("!room:example.org").unwrap();
+        let other = RoomId::try_from("!other:example.org").unwrap();
+        let mut outbox = outbox();
+        let first = message(&room, "first");
+        let second = message(&room, "second");
+        let third = message(&other, "third");
+        outbox.push(first.clone()).unwrap();
+        outbox.push(second.clone()).unwrap();
+        outbox.push(third.clone()).unwrap();
+
+        let now = Instant::now();
+        let ready = outbox
+            .ready(now)
+            .into_iter()
+            .map(|m| m.uuid)
+            .collect::<Vec<_>>();
+        assert_eq!(ready, vec![first.uuid, third.uuid]);
+
+        // a failed message holds back the rest of the room
+        assert_eq!(
+            outbox.send_failed(&first.uuid, "offline".into(), Failure::Counted),
+            SendState::Pending
+        );
+        let ready = outbox
+            .ready(now)
+            .into_iter()
+            .map(|m| m.uuid)
+            .collect::<Vec<_>>();
+        assert_eq!(ready, vec![third.uuid]);
+        assert!(outbox.next_retry().unwrap() > now);
+
+        outbox.connection_restored();
+        assert_eq!(outbox.ready(Instant::now()).len(), 2);
+
+        outbox.remove(&first.uuid).unwrap();
+        let ready = outbox
+            .ready(now)
+            .into_iter()
+            .map(|m| m.uuid)
+            .collect::<Vec<_>>();
+        assert_eq!(ready, vec![second.uuid, third.uuid]);
+
+        // the outbox survives a restart
+        let loaded = Outbox::from_path(outbox.path.clone());
+        assert_eq!(loaded.messages().len(), 2);
+        let _ = fs::remove_file(&outbox.path);
+    }
+
+    #[test]
+    fn gives_up_and_retries() {
+        let room = RoomId::try_from("!room:example.org").unwrap();
+        let mut outbox = outbox();
+        let msg = message(&room, "hello");
+        outbox.push(msg.clone()).unwrap();
+
+        assert_eq!(
+            outbox.send_failed(&msg.uuid, "forbidden".into(), Failure::Permanent),
+            SendState::Failed("forbidden".into())
+        );
+        assert!(outbox.ready(Instant::now()).is_empty());
+        assert!(outbox.next_retry().is_none());
+
+        assert_eq!(
+            outbox.retry(&Unsent::Message(msg.uuid)).unwrap(),
+            vec![(room.clone(), msg.uuid)]
+        );
+        assert_eq!(outbox.ready(Instant::now()).len(), 1);
+
+        // only the chosen message is discarded
+        let other = message(&room, "again");
+        outbox.push(other.clone()).unwrap();
+        assert_eq!(
+            outbox.discard(&Unsent::Message(other.uuid)).unwrap(),
+            vec![(room.clone(), other.uuid)]
+        );
+        assert_eq!(outbox.messages().len(), 1);
+
+        assert_eq!(
+            outbox.discard(&Unsent::Room(room.clone())).unwrap(),
+            vec![(room, msg.uuid)]
+        );
+        assert!(outbox.messages().is_empty());
+        let _ = fs::remove_file(&outbox.path);
+    }
+
+    #[test]
+    fn keeps_retrying_while_offline() {
+        let room = RoomId::try_from("!room:example.org").unwrap();
+        let mut outbox = outbox();
+        let msg = message(&room, "hello");
+        outbox.push(msg.clone()).unwrap();
+
+        let offline = Failure::Transient { retry_after: None };
+        for _ in 0..MAX_ATTEMPTS * 2 {
+            assert_eq!(
+                outbox.send_failed(&msg.uuid, "offline".into(), offline),
+                SendState::Pending
+            );
+        }
+        // the backoff stops growing
+        let wait = outbox.next_retry().unwrap() - Instant::now();
+        assert!(wait <= MAX_BACKOFF && wait > MAX_BACKOFF / 2);
+        assert!(outbox.ready(Instant::now()).is_empty());
+
+        outbox.connection_restored();
+        assert_eq!(outbox.ready(Instant::now()).len(), 1);
+
+        // the server asked to wait
+        let limited = Failure::Transient {
+            retry_after: Some(Duration::from_secs(30)),
+        };
+        outbox.send_failed(&msg.uuid, "too many requests".into(), limited);
+        let wait = outbox.next_retry().unwrap() - Instant::now();
+        assert!(wait <= Duration::from_secs(30) && wait > Duration::from_secs(25));
+
+        // counted failures still give up
+        for _ in 0..MAX_ATTEMPTS - 1 {
+            outbox.send_failed(&msg.uuid, "bad request".into(), Failure::Counted);
+        }
+        assert_eq!(
+            outbox.send_failed(&msg.uuid, "bad request".into(), Failure::Counted),
+            SendState::Failed("bad request".into())
+        );
+        let _ = fs::remove_file(&outbox.path);
+    }
+
+    #[test]
+    fn moves_unreadable_outbox_aside() {
+        let path = outbox().path;
+        fs::write(&path, "not json").unwrap();
+
+        let mut outbox = Outbox::from_path(path.clone());
+        assert!(outbox.messages().is_empty());
+        assert!(outbox.take_load_error().is_some());
+        assert!(outbox.take_load_error().is_none());
+
+        let corrupt = path.with_extension("json.corrupt");
+        assert_eq!(fs::read_to_string(&corrupt).unwrap(), "not json");
+        assert!(!path.exists());
+        let _ = fs::remove_file(&corrupt);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(backoff(1), Duration::from_secs(2));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(20), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,151 @@
+//! URL previews, the title and description of the first link in a message.
+//!
+//! The homeserver fetches the page so previews are sent through `preview_url`, they can
+//! be turned off everywhere or per room in `~/.rumatui/previews.json` with `/previews`.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use matrix_sdk::identifiers::RoomId;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::error::Result;
+
+/// The longest description shown, longer ones are cut off.
+const MAX_DESCRIPTION: usize = 200;
+
+/// The card shown under a message with a link.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UrlPreview {
+    pub site: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+impl UrlPreview {
+    /// Reads the `og:` properties returned by `preview_url`, `None` if the page has
+    /// neither a title nor a description.
+    pub fn from_og(data: &JsonValue) -> Option<Self> {
+        let prop = |name: &str| {
+            data.get(name)
+                .and_then(JsonValue::as_str)
+                .map(|value| value.split_whitespace().collect::<Vec<_>>().join(" "))
+                .filter(|value| !value.is_empty())
+        };
+        let description = prop("og:description").map(|desc| {
+            if desc.chars().count() > MAX_DESCRIPTION {
+                format!(
+                    "{}...",
+                    desc.chars().take(MAX_DESCRIPTION).collect::<String>()
+                )
+            } else {
+                desc
+            }
+        });
+        let preview = Self {
+            site: prop("og:site_name"),
+            title: prop("og:title"),
+            description,
+        };
+        if preview.title.is_none() && preview.description.is_none() {
+            None
+        } else {
+            Some(preview)
+        }
+    }
+}
+
+/// Where URL previews are fetched, a room's setting wins over the global one.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PreviewSettings {
+    pub enabled: bool,
+    #[serde(default)]
+    pub rooms: HashMap<RoomId, bool>,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rooms: HashMap::new(),
+        }
+    }
+}
+
+impl PreviewSettings {
+    /// Loads the settings, a missing file means previews are on everywhere.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(Into::into)
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        tokio::fs::write(path, json).await.map_err(Into::into)
+    }
+
+    pub fn is_enabled(&self, room: &RoomId) -> bool {
+        self.rooms.get(room).copied().unwrap_or(self.enabled)
+    }
+
+    /// Turns previews on or off for `room`, or everywhere with no room.
+    pub fn set(&mut self, room: Option<&RoomId>, on: bool) {
+        match room {
+            Some(room) => {
+                self.rooms.insert(room.clone(), on);
+            }
+            None => self.enabled = on,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    #[test]
+    fn og_data() {
+        let data = serde_json::json!({
+            "og:title": "Matrix.org",
+            "og:description": "An open network\n  for secure, decentralized communication.",
+            "og:image": "mxc://matrix.org/abc",
+            "matrix:image:size": 102400,
+        });
+        assert_eq!(
+            UrlPreview::from_og(&data),
+            Some(UrlPreview {
+                site: None,
+                title: Some("Matrix.org".into()),
+                description: Some(
+                    "An open network for secure, decentralized communication.".into()
+                ),
+            })
+        );
+        assert_eq!(
+            UrlPreview::from_og(&serde_json::json!({ "og:title": " " })),
+            None
+        );
+    }
+
+    #[test]
+    fn room_overrides_global() {
+        let room = RoomId::try_from("!room:example.org").unwrap();
+        let other = RoomId::try_from("!other:example.org").unwrap();
+        let mut settings = PreviewSettings::default();
+        assert!(settings.is_enabled(&room));
+
+        settings.set(Some(&room), false);
+        assert!(!settings.is_enabled(&room));
+        assert!(settings.is_enabled(&other));
+
+        settings.set(None, false);
+        settings.set(Some(&room), true);
+        assert!(settings.is_enabled(&room));
+        assert!(!settings.is_enabled(&other));
+    }
+}
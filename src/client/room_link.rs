@@ -0,0 +1,199 @@
+//! Parsing of the many ways a room can be referred to.
+//!
+//! A room can be joined with an alias `#room:server`, a room id `!id:server`
+//! optionally followed by `?via=server`, a `https://matrix.to/#/` link or a
+//! `matrix:` URI.
+
+use std::{convert::TryFrom, fmt};
+
+use matrix_sdk::identifiers::{RoomAliasId, RoomId, RoomIdOrAliasId};
+use percent_encoding::percent_decode_str;
+use url::form_urlencoded;
+
+const MATRIX_TO: &[&str] = &["https://matrix.to/#/", "http://matrix.to/#/"];
+const MATRIX_URI: &str = "matrix:";
+
+/// The room a `RoomLink` points to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RoomTarget {
+    Id(RoomId),
+    Alias(RoomAliasId),
+}
+
+impl fmt::Display for RoomTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "{}", id),
+            Self::Alias(alias) => write!(f, "{}", alias),
+        }
+    }
+}
+
+impl From<RoomTarget> for RoomIdOrAliasId {
+    fn from(target: RoomTarget) -> Self {
+        match target {
+            RoomTarget::Id(id) => id.into(),
+            RoomTarget::Alias(alias) => alias.into(),
+        }
+    }
+}
+
+/// A room to join and the servers that can be used to join through.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoomLink {
+    pub target: RoomTarget,
+    /// The servers to try joining through, from the `via` query parameters.
+    pub via: Vec<String>,
+}
+
+impl From<RoomId> for RoomLink {
+    fn from(id: RoomId) -> Self {
+        Self {
+            target: RoomTarget::Id(id),
+            via: vec![],
+        }
+    }
+}
+
+impl fmt::Display for RoomLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.target)
+    }
+}
+
+impl RoomLink {
+    /// Parses an alias, room id, matrix.to link or `matrix:` URI.
+    ///
+    /// The error is a message meant to be shown to the user.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+
+        let (ident, query) = if let Some(link) = MATRIX_TO
+            .iter()
+            .find(|prefix| input.starts_with(*prefix))
+            .map(|prefix| &input[prefix.len()..])
+        {
+            let (path, query) = split_query(link);
+            // event permalinks are `room/event`, only the room is needed
+            let room = path.split('/').next().unwrap_or_default();
+            (decode(room)?, query)
+        } else if input.starts_with(MATRIX_URI) {
+            let (path, query) = split_query(&input[MATRIX_URI.len()..]);
+            let mut segments = path.split('/');
+            let kind = segments.next().unwrap_or_default();
+            let name = decode(segments.next().unwrap_or_default())?;
+            let ident = match kind {
+                "r" | "room" => format!("#{}", name),
+                "roomid" => format!("!{}", name),
+                "u" | "user" => {
+                    return Err(format!(
+                        "\"{}\" links to a user not a room, use /query to message them",
+                        input
+                    ))
+                }
+                _ => return Err(format!("\"{}\" is not a room link", input)),
+            };
+            (ident, query)
+        } else {
+            let (ident, query) = split_query(input);
+            (ident.to_string(), query)
+        };
+
+        let target = if ident.starts_with('#') {
+            RoomAliasId::try_from(ident.as_str())
+                .map(RoomTarget::Alias)
+                .map_err(|_| format!("\"{}\" is not a valid room alias", ident))?
+        } else if ident.starts_with('!') {
+            RoomId::try_from(ident.as_str())
+                .map(RoomTarget::Id)
+                .map_err(|_| format!("\"{}\" is not a valid room id", ident))?
+        } else {
+            return Err(format!(
+                "\"{}\" is not a room, use #alias:server, !id:server or a matrix.to link",
+                input
+            ));
+        };
+
+        let via = query
+            .map(|q| {
+                form_urlencoded::parse(q.as_bytes())
+                    .filter(|(key, _)| key == "via")
+                    .map(|(_, server)| server.into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self { target, via })
+    }
+}
+
+fn split_query(input: &str) -> (&str, Option<&str>) {
+    match input.find('?') {
+        Some(idx) => (&input[..idx], Some(&input[idx + 1..])),
+        None => (input, None),
+    }
+}
+
+fn decode(input: &str) -> Result<String, String> {
+    percent_decode_str(input)
+        .decode_utf8()
+        .map(|s| s.into_owned())
+        .map_err(|_| format!("\"{}\" is not valid utf8", input))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn alias(s: &str) -> RoomTarget {
+        RoomTarget::Alias(RoomAliasId::try_from(s).unwrap())
+    }
+
+    fn id(s: &str) -> RoomTarget {
+        RoomTarget::Id(RoomId::try_from(s).unwrap())
+    }
+
+    #[test]
+    fn parse_ids_and_aliases() {
+        let link = RoomLink::parse("#rumatui:matrix.org").unwrap();
+        assert_eq!(link.target, alias("#rumatui:matrix.org"));
+        assert!(link.via.is_empty());
+
+        let link = RoomLink::parse("!abc:example.org?via=matrix.org&via=example.org").unwrap();
+        assert_eq!(link.target, id("!abc:example.org"));
+        assert_eq!(link.via, vec!["matrix.org", "example.org"]);
+    }
+
+    #[test]
+    fn parse_matrix_to() {
+        let link = RoomLink::parse("https://matrix.to/#/%23rumatui%3Amatrix.org").unwrap();
+        assert_eq!(link.target, alias("#rumatui:matrix.org"));
+
+        let link = RoomLink::parse(
+            "https://matrix.to/#/!abc:example.org/$event:example.org?via=matrix.org",
+        )
+        .unwrap();
+        assert_eq!(link.target, id("!abc:example.org"));
+        assert_eq!(link.via, vec!["matrix.org"]);
+    }
+
+    #[test]
+    fn parse_matrix_uri() {
+        let link = RoomLink::parse("matrix:r/rumatui:matrix.org?action=join").unwrap();
+        assert_eq!(link.target, alias("#rumatui:matrix.org"));
+        assert!(link.via.is_empty());
+
+        let link = RoomLink::parse("matrix:roomid/abc:example.org?via=matrix.org").unwrap();
+        assert_eq!(link.target, id("!abc:example.org"));
+        assert_eq!(link.via, vec!["matrix.org"]);
+
+        assert!(RoomLink::parse("matrix:u/devin:matrix.org").is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(RoomLink::parse("rumatui").is_err());
+        assert!(RoomLink::parse("#no-server").is_err());
+        assert!(RoomLink::parse("https://matrix.to/#/@devin:matrix.org").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use matrix_sdk::identifiers::{RoomId, UserId};
+use serde::{Deserialize, Serialize};
+
+/// The content of the `m.direct` global account data event.
+///
+/// Maps the user a direct chat is held with to the rooms used for those chats.
+pub type DirectEventContent = BTreeMap<UserId, Vec<RoomId>>;
+
+/// The tags of a room from the `m.tag` room account data event.
+///
+/// Maps the tag name, `m.favourite`, `m.lowpriority` or `u.*`, to the tag's info.
+pub type Tags = BTreeMap<String, TagInfo>;
+
+/// The content of the `m.tag` room account data event.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TagEventContent {
+    pub tags: Tags,
+}
+
+/// Information about a tag.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct TagInfo {
+    /// Value to use for lexicographically ordering rooms with this tag.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<f64>,
+}
+
+pub mod get_global_account_data {
+    use matrix_sdk::{api::Error, identifiers::UserId};
+    use serde_json::Value as JsonValue;
+
+    ruma_api::ruma_api! {
+        metadata: {
+            description: "Gets some account_data for the user.",
+            method: GET,
+            name: "get_global_account_data",
+            path: "/_matrix/client/r0/user/:user_id/account_data/:event_type",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request: {
+            /// User ID of user for whom to retrieve data.
+            #[ruma_api(path)]
+            pub user_id: UserId,
+
+            /// Type of data to retrieve.
+            #[ruma_api(path)]
+            pub event_type: String,
+        }
+
+        response: {
+            /// The account data content, the shape depends on the `event_type`.
+            #[ruma_api(body)]
+            pub account_data: JsonValue,
+        }
+
+        error: Error
+    }
+}
+
+pub mod set_global_account_data {
+    use matrix_sdk::{api::Error, identifiers::UserId};
+    use serde_json::Value as JsonValue;
+
+    ruma_api::ruma_api! {
+        metadata: {
+            description: "Sets global account data.",
+            method: PUT,
+            name: "set_global_account_data",
+            path: "/_matrix/client/r0/user/:user_id/account_data/:event_type",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request: {
+            /// The ID of the user to set account_data for.
+            #[ruma_api(path)]
+            pub user_id: UserId,
+
+            /// The event type of the account_data to set.
+            #[ruma_api(path)]
+            pub event_type: String,
+
+            /// Arbitrary JSON to store as config data.
+            #[ruma_api(body)]
+            pub data: JsonValue,
+        }
+
+        response: {}
+
+        error: Error
+    }
+}
+
+pub mod get_tags {
+    use matrix_sdk::{
+        api::Error,
+        identifiers::{RoomId, UserId},
+    };
+
+    use super::Tags;
+
+    ruma_api::ruma_api! {
+        metadata: {
+            description: "Get the tags associated with a room.",
+            method: GET,
+            name: "get_tags",
+            path: "/_matrix/client/r0/user/:user_id/rooms/:room_id/tags",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request: {
+            /// The user whose tags will be retrieved.
+            #[ruma_api(path)]
+            pub user_id: UserId,
+
+            /// The room from which tags will be retrieved.
+            #[ruma_api(path)]
+            pub room_id: RoomId,
+        }
+
+        response: {
+            /// The user's tags for the room.
+            pub tags: Tags,
+        }
+
+        error: Error
+    }
+}
+
+pub mod create_tag {
+    use matrix_sdk::{
+        api::Error,
+        identifiers::{RoomId, UserId},
+    };
+
+    use super::TagInfo;
+
+    ruma_api::ruma_api! {
+        metadata: {
+            description: "Add a new tag to a room.",
+            method: PUT,
+            name: "create_tag",
+            path: "/_matrix/client/r0/user/:user_id/rooms/:room_id/tags/:tag",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request: {
+            /// The ID of the user creating the tag.
+            #[ruma_api(path)]
+            pub user_id: UserId,
+
+            /// The room to tag.
+            #[ruma_api(path)]
+            pub room_id: RoomId,
+
+            /// The name of the tag to create.
+            #[ruma_api(path)]
+            pub tag: String,
+
+            /// Info about the tag.
+            #[ruma_api(body)]
+            pub tag_info: TagInfo,
+        }
+
+        response: {}
+
+        error: Error
+    }
+}
+
+pub mod delete_tag {
+    use matrix_sdk::{
+        api::Error,
+        identifiers::{RoomId, UserId},
+    };
+
+    ruma_api::ruma_api! {
+        metadata: {
+            description: "Remove a tag from a room.",
+            method: DELETE,
+            name: "delete_tag",
+            path: "/_matrix/client/r0/user/:user_id/rooms/:room_id/tags/:tag",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request: {
+            /// The user whose tag will be deleted.
+            #[ruma_api(path)]
+            pub user_id: UserId,
+
+            /// The tagged room.
+            #[ruma_api(path)]
+            pub room_id: RoomId,
+
+            /// The name of the tag to delete.
+            #[ruma_api(path)]
+            pub tag: String,
+        }
+
+        response: {}
+
+        error: Error
+    }
+}
@@ -0,0 +1,29 @@
+pub mod get_media_preview {
+    use matrix_sdk::api::Error;
+    use serde_json::Value as JsonValue;
+
+    ruma_api::ruma_api! {
+        metadata: {
+            description: "Get the OpenGraph data of a URL, fetched by the homeserver.",
+            method: GET,
+            name: "get_media_preview",
+            path: "/_matrix/media/r0/preview_url",
+            rate_limited: true,
+            requires_authentication: true,
+        }
+
+        request: {
+            /// The URL to preview.
+            #[ruma_api(query)]
+            pub url: String,
+        }
+
+        response: {
+            /// The `og:` properties of the page, any of them may be missing.
+            #[ruma_api(body)]
+            pub data: JsonValue,
+        }
+
+        error: Error
+    }
+}
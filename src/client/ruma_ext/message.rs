@@ -47,3 +47,90 @@ pub enum ExtraMessageEventContent {
     #[serde(rename = "m.text")]
     EditEvent(EditEventContent),
 }
+
+pub mod send_message_event {
+    use matrix_sdk::{
+        api::Error,
+        identifiers::{EventId, RoomId},
+    };
+    use serde_json::Value as JsonValue;
+
+    ruma_api::ruma_api! {
+        metadata: {
+            description: "Send a message event with content ruma can not represent to a room.",
+            method: PUT,
+            name: "send_message_event",
+            path: "/_matrix/client/r0/rooms/:room_id/send/:event_type/:txn_id",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request: {
+            /// The room to send the event to.
+            #[ruma_api(path)]
+            pub room_id: RoomId,
+
+            /// The type of event to send.
+            #[ruma_api(path)]
+            pub event_type: String,
+
+            /// The transaction ID for this event.
+            #[ruma_api(path)]
+            pub txn_id: String,
+
+            /// The event's content.
+            #[ruma_api(body)]
+            pub data: JsonValue,
+        }
+
+        response: {
+            /// A unique identifier for the event.
+            pub event_id: EventId,
+        }
+
+        error: Error
+    }
+}
+
+pub mod redact_event {
+    use matrix_sdk::{
+        api::Error,
+        identifiers::{EventId, RoomId},
+    };
+
+    ruma_api::ruma_api! {
+        metadata: {
+            description: "Redact an event, stripping all information not critical to the event graph.",
+            method: PUT,
+            name: "redact_event",
+            path: "/_matrix/client/r0/rooms/:room_id/redact/:event_id/:txn_id",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request: {
+            /// The room the event is in.
+            #[ruma_api(path)]
+            pub room_id: RoomId,
+
+            /// The event to redact.
+            #[ruma_api(path)]
+            pub event_id: EventId,
+
+            /// The transaction ID for this redaction.
+            #[ruma_api(path)]
+            pub txn_id: String,
+
+            /// Why the event was redacted.
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub reason: Option<String>,
+        }
+
+        response: {
+            /// The `EventId` of the redaction event.
+            pub event_id: EventId,
+        }
+
+        error: Error
+    }
+}
@@ -5,9 +5,12 @@ use serde_json::Value as JsonValue;
 
 use matrix_sdk::identifiers::{EventId, RoomId, UserId};
 
+pub mod account_data;
 pub mod auth;
+pub mod media;
 pub mod message;
 pub mod reaction;
+pub mod state;
 
 pub use message::ExtraMessageEventContent;
 pub use reaction::ExtraReactionEventContent;
@@ -0,0 +1,39 @@
+pub mod send_state_event {
+    use matrix_sdk::{
+        api::Error,
+        identifiers::{EventId, RoomId},
+    };
+    use serde_json::Value as JsonValue;
+
+    ruma_api::ruma_api! {
+        metadata: {
+            description: "Send a state event with an empty state key to a room.",
+            method: PUT,
+            name: "send_state_event",
+            path: "/_matrix/client/r0/rooms/:room_id/state/:event_type",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request: {
+            /// The room to set the state in.
+            #[ruma_api(path)]
+            pub room_id: RoomId,
+
+            /// The type of state event to send.
+            #[ruma_api(path)]
+            pub event_type: String,
+
+            /// The event's content.
+            #[ruma_api(body)]
+            pub data: JsonValue,
+        }
+
+        response: {
+            /// A unique identifier for the event.
+            pub event_id: EventId,
+        }
+
+        error: Error
+    }
+}
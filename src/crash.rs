@@ -0,0 +1,100 @@
+//! Restores the terminal and writes a crash report when `rumatui` panics.
+//!
+//! A panic on the UI thread leaves `rumatui`, the terminal is given back before the
+//! panic message is printed. A panic in one of the client tasks is only reported, the
+//! supervisor in `client::client_loop` restarts the task.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    panic::{self, PanicInfo},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+};
+
+use chrono::Local;
+
+use crate::{DISABLE_MOUSE, DISABLE_PASTE, VERSION};
+
+lazy_static::lazy_static! {
+    /// The terminal settings from before raw mode was turned on.
+    static ref TERMINAL: Mutex<Option<libc::termios>> = Mutex::new(None);
+}
+
+/// Remembers the terminal settings so the panic hook can restore them, this must be
+/// called before raw mode is turned on.
+pub fn save_terminal() {
+    let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+    // termion puts stdout in raw mode so that is the one to restore
+    if unsafe { libc::tcgetattr(libc::STDOUT_FILENO, &mut termios) } == 0 {
+        *TERMINAL.lock().unwrap() = Some(termios);
+    }
+}
+
+/// Installs the panic hook, crash reports are written to `dir`.
+pub fn install_panic_hook(dir: PathBuf) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = write_report(&dir, info);
+        if thread::current().name() == Some("main") {
+            restore_terminal();
+            default_hook(info);
+            match report {
+                Ok(path) => eprintln!("a crash report was written to {}", path.display()),
+                Err(e) => eprintln!("writing the crash report failed {}", e),
+            }
+        } else {
+            tracing::error!("a client task panicked {}", info);
+        }
+    }));
+}
+
+/// Turns off raw mode, mouse capture and bracketed paste and shows the cursor.
+fn restore_terminal() {
+    // the lock may be held by the panicking thread
+    if let Ok(terminal) = TERMINAL.try_lock() {
+        if let Some(termios) = terminal.as_ref() {
+            unsafe { libc::tcsetattr(libc::STDOUT_FILENO, libc::TCSANOW, termios) };
+        }
+    }
+    let mut stdout = io::stdout();
+    let _ = write!(
+        stdout,
+        "{}{}{}\n\r",
+        DISABLE_MOUSE,
+        DISABLE_PASTE,
+        termion::cursor::Show
+    );
+    let _ = stdout.flush();
+}
+
+fn write_report(dir: &Path, info: &PanicInfo) -> io::Result<PathBuf> {
+    let now = Local::now();
+    let path = dir.join(format!("crash-{}.txt", now.format("%Y-%m-%d-%H%M%S")));
+    fs::write(&path, report(info, &now.to_rfc2822()))?;
+    Ok(path)
+}
+
+fn report(info: &PanicInfo, time: &str) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(|s| s.as_str()))
+        .unwrap_or("unknown panic payload");
+
+    let mut report = String::new();
+    let _ = writeln!(report, "rumatui {} crashed at {}", VERSION, time);
+    let _ = writeln!(
+        report,
+        "thread: {}",
+        thread::current().name().unwrap_or("unnamed")
+    );
+    if let Some(location) = info.location() {
+        let _ = writeln!(report, "location: {}", location);
+    }
+    let _ = writeln!(report, "message: {}", message);
+    report
+}
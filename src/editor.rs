@@ -0,0 +1,42 @@
+use std::{
+    env, fs,
+    io::{self, ErrorKind},
+    process::Command,
+};
+
+use uuid::Uuid;
+
+/// Opens `draft` in the user's `$VISUAL` or `$EDITOR` and returns the edited text.
+///
+/// This blocks until the editor exits, the terminal must not be in raw mode.
+pub fn edit(draft: &str) -> io::Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    let mut path = env::temp_dir();
+    path.push(format!("rumatui-{}.md", Uuid::new_v4()));
+    fs::write(&path, draft)?;
+
+    // the editor may have arguments, `code --wait` for example
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or("vi");
+    let edited = Command::new(program)
+        .args(args)
+        .arg(&path)
+        .status()
+        .and_then(|status| {
+            if status.success() {
+                fs::read_to_string(&path)
+            } else {
+                Err(io::Error::new(
+                    ErrorKind::Other,
+                    format!("{} exited with {}", editor, status),
+                ))
+            }
+        });
+    let _ = fs::remove_file(&path);
+
+    // most editors end the file with a new line
+    edited.map(|text| text.trim_end_matches('\n').to_string())
+}
@@ -1,6 +1,6 @@
 //! Error conditions.
 
-use std::{fmt, io};
+use std::{fmt, io, time::Duration};
 
 use matrix_sdk::{
     api::{error::ErrorKind, Error as RumaApiError},
@@ -23,14 +23,67 @@ const AUTH_MSG: &str = r#"You tried to reach an endpoint that requires authentic
 
 This is most likely a bug in `rumatui` or one of it's dependencies."#;
 
-const LOGIN_MSG: &str = r#"The user name or password entered did not match any know user.
+/// A Matrix error the homeserver responded with.
+#[derive(Clone, Debug)]
+pub struct ApiError {
+    /// The `errcode` of the error, `M_FORBIDDEN` for example.
+    pub kind: ErrorKind,
+    /// The `error` the server sent, meant for the user.
+    pub message: String,
+    /// The HTTP status code of the response.
+    pub status: u16,
+}
+
+impl ApiError {
+    /// The Matrix `errcode`, `M_LIMIT_EXCEEDED` for example.
+    pub fn errcode(&self) -> String {
+        self.kind.to_string()
+    }
+
+    /// How long the server asked to wait before trying again.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match &self.kind {
+            ErrorKind::LimitExceeded { retry_after_ms } => *retry_after_ms,
+            _ => None,
+        }
+    }
+
+    /// What went wrong in plain language.
+    fn explain(&self) -> &'static str {
+        match &self.kind {
+            ErrorKind::Forbidden => "The server refused the request.",
+            ErrorKind::UnknownToken { .. } => {
+                "The session has expired or was logged out, log in again."
+            }
+            ErrorKind::MissingToken => "The request needs you to be logged in.",
+            ErrorKind::LimitExceeded { .. } => {
+                "The server is rate limiting requests, wait a moment and try again."
+            }
+            ErrorKind::NotFound => "The server could not find what was asked for.",
+            ErrorKind::UserInUse => "That user name is already taken.",
+            _ => "An error occurred with a response from the server.",
+        }
+    }
+}
 
-Make sure you are logging in on the correct server (rumatui defaults to 'http://matrix.org')."#;
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\n{}: {}",
+            self.explain(),
+            self.errcode(),
+            self.message
+        )
+    }
+}
 
 /// Internal representation of errors.
 #[derive(Debug)]
 pub enum Error {
     Encryption(String),
+    /// The homeserver responded with a Matrix error.
+    Api(ApiError),
     RumaResponse(String),
     RumaRequest(String),
     Json(String),
@@ -50,6 +103,7 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Encryption(msg) => write!(f, "{}", msg),
+            Self::Api(err) => write!(f, "{}", err),
             Self::RumaResponse(msg) => write!(
                 f,
                 "An error occurred with a response from the server.\n{}",
@@ -92,6 +146,68 @@ impl fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// The Matrix `errcode` if the server responded with an error.
+    pub fn errcode(&self) -> Option<String> {
+        match self {
+            Self::Api(err) => Some(err.errcode()),
+            _ => None,
+        }
+    }
+
+    /// The HTTP status code if the server responded with an error.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Api(err) => Some(err.status),
+            _ => None,
+        }
+    }
+
+    /// Sending the same request again later may succeed, the connection was lost,
+    /// the server was rate limiting or it had an internal error.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Api(err) => {
+                matches!(err.kind, ErrorKind::LimitExceeded { .. }) || err.status >= 500
+            }
+            Self::Unknown(_) | Self::Io(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The access token is missing or no longer valid, the user has to log in again.
+    pub fn needs_relogin(&self) -> bool {
+        match self {
+            Self::Api(err) => matches!(
+                err.kind,
+                ErrorKind::UnknownToken { .. } | ErrorKind::MissingToken
+            ),
+            Self::NeedAuth(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The server refused the request, retrying it will not help.
+    pub fn is_permanent(&self) -> bool {
+        self.needs_relogin()
+            || (matches!(self, Self::Api(_) | Self::RumaResponse(_)) && !self.is_retryable())
+    }
+
+    /// `rumatui` can not carry on, the client and UI can no longer talk to each other
+    /// or there is a bug.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::Channel(_) | Self::Rumatui(_))
+    }
+
+    /// How long the server asked to wait before the next request when rate limiting.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Api(err) => err.retry_after(),
+            _ => None,
+        }
+    }
+}
+
 /// This is the most important error conversion as most of the user facing errors are here.
 impl From<MatrixError> for Error {
     #[allow(clippy::useless_format)]
@@ -100,14 +216,17 @@ impl From<MatrixError> for Error {
             MatrixError::AuthenticationRequired => Error::NeedAuth(AUTH_MSG.to_string()),
             MatrixError::RumaResponse(http) => match http {
                 RumaResponseError::Http(server) => match server {
-                    // This should be the most common error kind and some should be recoverable.
-                    // TODO there are numerous ErrorKind's for `match kind { ... } deal with them
-                    // fix the LOGIN_MSG it is not always accurate
-                    ServerError::Known(RumaApiError { kind, message, .. }) => match kind {
-                        ErrorKind::Forbidden => Error::RumaResponse(LOGIN_MSG.to_string()),
-                        ErrorKind::UserInUse => Error::RumaResponse(format!("{}", message)),
-                        _ => Error::RumaResponse(format!("{}", message)),
-                    },
+                    // This should be the most common error kind, `Error::is_retryable` and
+                    // `Error::needs_relogin` say what can be done about it
+                    ServerError::Known(RumaApiError {
+                        kind,
+                        message,
+                        status_code,
+                    }) => Error::Api(ApiError {
+                        kind,
+                        message,
+                        status: status_code.as_u16(),
+                    }),
                     ServerError::Unknown(err) => Error::Unknown(format!("{}", err)),
                 },
                 RumaResponseError::Deserialization(err) => Error::SerDeError(format!("{}", err)),
@@ -154,7 +273,7 @@ impl From<IntoHttpError> for Error {
 impl From<SendError<UserRequest>> for Error {
     fn from(error: SendError<UserRequest>) -> Self {
         let text = format!("{}", error);
-        Self::RumaRequest(text)
+        Self::Channel(text)
     }
 }
 
@@ -0,0 +1,209 @@
+//! Vim style normal and insert modes configured by `~/.rumatui/keymap.json`.
+//!
+//! Without a keymap file there is no normal mode and every key is handled as before.
+//! A keymap file looks like
+//!
+//! ```json
+//! { "vim": true, "normal": { "j": "down", "k": "up", "dd": "delete" } }
+//! ```
+//!
+//! `normal` maps a key or a sequence of keys to an `Action`, when it is left out
+//! `default_normal` is used.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::error::Result;
+
+/// What a normal mode binding does.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Select the next message, room or member.
+    Down,
+    /// Select the previous message, room or member.
+    Up,
+    /// Select the first message, room or member.
+    Top,
+    /// Select the last room or member, in the timeline jump to the latest message.
+    Bottom,
+    /// Filter the room list, the same as Ctrl-k.
+    Search,
+    /// Switch to insert mode in the send box.
+    Insert,
+    /// Reply to the selected message.
+    Reply,
+    /// Edit the selected message.
+    Edit,
+    /// Delete the selected message if it is the user's, bound to `dd` so one stray key
+    /// does not delete it.
+    Delete,
+    /// Open the first link in the selected message.
+    OpenLink,
+    FocusLeft,
+    FocusRight,
+    FocusUp,
+    FocusDown,
+}
+
+/// The vim mode, insert mode is the only mode without a keymap.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    Normal,
+    Insert,
+}
+
+impl Mode {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Normal => "NORMAL",
+            Self::Insert => "INSERT",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Keymap {
+    /// Turns on normal mode.
+    #[serde(default)]
+    pub vim: bool,
+    #[serde(default = "default_normal")]
+    pub normal: HashMap<String, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            vim: false,
+            normal: default_normal(),
+        }
+    }
+}
+
+fn default_normal() -> HashMap<String, Action> {
+    vec![
+        ("j", Action::Down),
+        ("k", Action::Up),
+        ("gg", Action::Top),
+        ("G", Action::Bottom),
+        ("/", Action::Search),
+        ("i", Action::Insert),
+        ("r", Action::Reply),
+        ("e", Action::Edit),
+        ("dd", Action::Delete),
+        ("o", Action::OpenLink),
+        ("h", Action::FocusLeft),
+        ("l", Action::FocusRight),
+        ("K", Action::FocusUp),
+        ("J", Action::FocusDown),
+    ]
+    .into_iter()
+    .map(|(keys, action)| (keys.to_string(), action))
+    .collect()
+}
+
+impl Keymap {
+    /// Loads the keymap, a missing file means the default keymap with vim mode off.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(Into::into)
+    }
+
+    /// The normal mode state for this keymap, `None` when vim mode is off.
+    pub fn into_vim(self) -> Option<Vim> {
+        if self.vim {
+            Some(Vim {
+                keymap: self,
+                mode: Mode::Normal,
+                pending: String::new(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Vim {
+    keymap: Keymap,
+    pub mode: Mode,
+    /// The keys typed so far of a binding that is more than one key long.
+    pending: String,
+}
+
+impl Vim {
+    /// Feeds a key typed in normal mode, returns the action once a binding is complete.
+    pub fn key(&mut self, c: char) -> Option<Action> {
+        self.pending.push(c);
+        if let Some(action) = self.keymap.normal.get(&self.pending) {
+            self.pending.clear();
+            return Some(*action);
+        }
+        if self
+            .keymap
+            .normal
+            .keys()
+            .any(|keys| keys.starts_with(&self.pending))
+        {
+            return None;
+        }
+        // not a binding, start over from this key
+        self.pending.clear();
+        if self.keymap.normal.keys().any(|keys| keys.starts_with(c)) {
+            self.key(c)
+        } else {
+            None
+        }
+    }
+
+    /// The keys typed so far of an unfinished binding.
+    pub fn pending(&self) -> &str {
+        &self.pending
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.pending.clear();
+        self.mode = mode;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn key_sequences() {
+        let mut vim = Keymap {
+            vim: true,
+            ..Keymap::default()
+        }
+        .into_vim()
+        .unwrap();
+        assert_eq!(vim.mode, Mode::Normal);
+
+        assert_eq!(vim.key('j'), Some(Action::Down));
+        assert_eq!(vim.key('g'), None);
+        assert_eq!(vim.pending(), "g");
+        assert_eq!(vim.key('g'), Some(Action::Top));
+        assert_eq!(vim.pending(), "");
+
+        // a broken sequence starts over with the last key
+        assert_eq!(vim.key('g'), None);
+        assert_eq!(vim.key('k'), Some(Action::Up));
+        assert_eq!(vim.key('x'), None);
+        assert_eq!(vim.pending(), "");
+        assert_eq!(vim.key('d'), None);
+        assert_eq!(vim.key('d'), Some(Action::Delete));
+
+        assert!(Keymap::default().into_vim().is_none());
+        let keymap: Keymap =
+            serde_json::from_str(r#"{ "vim": true, "normal": { "x": "delete" } }"#).unwrap();
+        let mut vim = keymap.into_vim().unwrap();
+        assert_eq!(vim.key('x'), Some(Action::Delete));
+        assert_eq!(vim.key('j'), None);
+    }
+}
@@ -5,28 +5,53 @@
     clippy::single_match
 )]
 
-use std::{env, fs, io, path::Path, process, time::Duration};
+use std::{
+    env, fs,
+    io::{self, Stdout, Write},
+    path::Path,
+    process,
+    time::Duration,
+};
 
 use rumatui_tui::{backend::TermionBackend, Terminal};
 use termion::{
     event::{Event as TermEvent, Key, MouseButton, MouseEvent},
     input::MouseTerminal,
-    raw::IntoRawMode,
+    raw::{IntoRawMode, RawTerminal},
 };
 use tracing_subscriber::{self as tracer, EnvFilter};
 
 mod client;
 mod config;
+mod crash;
+mod editor;
 mod error;
+mod keymap;
 mod log;
 mod ui_loop;
 mod widgets;
 
+use client::preview::PreviewSettings;
 use ui_loop::{Config, Event, UiEventHandle};
-use widgets::{app::AppWidget, DrawWidget};
+use widgets::{
+    app::AppWidget,
+    colors::NickColors,
+    focus,
+    message::timeline::TimelineSettings,
+    text_input::Edit,
+    theme::{self, Themes},
+    DrawWidget,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// `termion::input::MouseTerminal` only writes these when it is created and dropped
+const ENABLE_MOUSE: &str = "\x1b[?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h";
+const DISABLE_MOUSE: &str = "\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l";
+// pasted text is wrapped in `\x1b[200~` and `\x1b[201~`, see `ui_loop::Event::Paste`
+const ENABLE_PASTE: &str = "\x1b[?2004h";
+const DISABLE_PASTE: &str = "\x1b[?2004l";
+
 lazy_static::lazy_static! {
     pub static ref RUMATUI_DIR: std::io::Result<std::path::PathBuf> = {
         let mut path = dirs::home_dir()
@@ -97,6 +122,7 @@ fn main() -> Result<(), failure::Error> {
     let path: &std::path::Path = RUMATUI_DIR
         .as_ref()
         .map_err(|e| failure::format_err!("home dir not found: {}", e))?;
+    crash::install_panic_hook(path.to_path_buf());
     let mut path = std::path::PathBuf::from(path);
     path.push("logs.json");
 
@@ -109,19 +135,57 @@ fn main() -> Result<(), failure::Error> {
     // .try_init()
     // .unwrap(); // they return `<dyn Error + Send + Sync + 'static>`
 
+    let keymap = keymap::Keymap::load(&path.with_file_name("keymap.json")).unwrap_or_else(|e| {
+        tracing::warn!("the keymap file could not be loaded {}", e);
+        keymap::Keymap::default()
+    });
+    let previews =
+        PreviewSettings::load(&path.with_file_name("previews.json")).unwrap_or_else(|e| {
+            tracing::warn!("the URL preview settings could not be loaded {}", e);
+            PreviewSettings::default()
+        });
+    let timeline =
+        TimelineSettings::load(&path.with_file_name("timeline.json")).unwrap_or_else(|e| {
+            tracing::warn!("the timeline settings could not be loaded {}", e);
+            TimelineSettings::default()
+        });
+    let nick_colors = NickColors::load(&path.with_file_name("colors.json")).unwrap_or_else(|e| {
+        tracing::warn!("the nick colors could not be loaded {}", e);
+        NickColors::default()
+    });
+    let truecolor = truecolor_supported();
+    let nick_colors = if truecolor {
+        nick_colors
+    } else {
+        nick_colors.without_truecolor()
+    };
+    let light = env::var("COLORFGBG").map_or(false, |colors| theme::is_light_background(&colors));
+    let mut themes = Themes::new(path.parent().unwrap_or(&path), truecolor, light);
+    if let Err(e) = themes.load_saved() {
+        tracing::warn!("the saved theme could not be loaded {}", e);
+    }
+
     let executor = runtime.handle().clone();
     runtime.block_on(async {
         let mut app = AppWidget::new(executor, &server).await;
+        app.vim = keymap.into_vim();
+        app.chat.set_preview_settings(previews);
+        app.chat.set_timeline_settings(timeline);
+        app.chat.set_nick_colors(nick_colors);
+        app.themes = themes;
         let events = UiEventHandle::with_config(Config {
             tick_rate: Duration::from_millis(60),
             exit_key: termion::event::Key::Ctrl('q'),
         });
-        let stdout = io::stdout().into_raw_mode()?;
-        let stdout = MouseTerminal::from(stdout);
-        let backend = TermionBackend::new(stdout);
+        // kept separate from the backend so raw mode can be suspended
+        crash::save_terminal();
+        let raw = io::stdout().into_raw_mode()?;
+        let stdout = MouseTerminal::from(io::stdout());
+        let backend = TermionBackend::new(stdout).with_hyperlinks(hyperlinks_supported());
         let mut terminal = Terminal::new(backend)?;
         terminal.clear()?;
         terminal.hide_cursor()?;
+        write!(terminal.backend_mut(), "{}", ENABLE_PASTE)?;
         loop {
             app.draw(&mut terminal)?;
 
@@ -134,22 +198,69 @@ fn main() -> Result<(), failure::Error> {
                     TermEvent::Key(key) => {
                         app.on_notifications().await;
 
-                        match key {
-                            Key::Ctrl(c) if c == 'c' => app.should_quit = true,
-                            Key::Ctrl(c) if c == 'q' => app.should_quit = true,
-                            Key::Ctrl(c) if c == 's' => app.on_send().await,
-                            Key::Ctrl(c) if c == 'd' => app.on_ctrl_d().await,
-                            Key::Ctrl(c) if c == 'k' => app.on_ctrl_k().await,
-                            Key::Up => app.on_up().await,
-                            Key::Down => app.on_down().await,
-                            Key::Left => app.on_left(),
-                            Key::Right => app.on_right(),
-                            Key::Backspace => app.on_backspace(),
-                            Key::Delete => app.on_delete().await,
-                            Key::Char(c) if c == '\t' => app.on_down().await,
-                            Key::Char(c) => app.on_key(c).await,
-                            Key::Esc => app.should_quit = true,
-                            _ => {}
+                        let normal_key = match key {
+                            Key::Char(c) if !c.is_control() && app.is_normal_mode() => Some(c),
+                            _ => None,
+                        };
+                        let quit_key = matches!(key, Key::Ctrl('c') | Key::Ctrl('q'));
+                        if app.is_settings_open() && !quit_key {
+                            app.on_settings_key(key).await
+                        } else if let Some(c) = normal_key {
+                            app.on_normal_key(c).await
+                        } else if let Some(to) = focus::movement(key) {
+                            app.on_move_focus(to).await
+                        } else {
+                            match key {
+                                Key::Ctrl(c) if c == 'c' => app.should_quit = true,
+                                Key::Ctrl(c) if c == 'q' => app.should_quit = true,
+                                Key::Ctrl(c) if c == 's' => app.on_send().await,
+                                Key::Ctrl(c) if c == 'd' => app.on_ctrl_d().await,
+                                Key::Ctrl(c) if c == 'k' => app.on_ctrl_k().await,
+                                Key::Ctrl(c) if c == 'n' => app.on_ctrl_n().await,
+                                Key::Ctrl(c) if c == 't' => app.on_ctrl_t().await,
+                                Key::Ctrl(c) if c == 'f' => app.on_ctrl_f().await,
+                                Key::Ctrl(c) if c == 'o' => app.on_ctrl_o().await,
+                                Key::Ctrl(c) if c == 'r' => app.on_ctrl_r().await,
+                                Key::Ctrl(c) if c == 'l' => app.on_ctrl_l().await,
+                                Key::Ctrl(c) if c == 'x' => {
+                                    if let Some(draft) = app.editor_draft() {
+                                        let edited =
+                                            run_suspended(&raw, &mut terminal, &events, || {
+                                                editor::edit(&draft)
+                                            })?;
+                                        app.on_editor_exit(edited).await;
+                                    }
+                                }
+                                Key::Ctrl(c) if c == 'a' => app.on_edit(Edit::Home).await,
+                                Key::Ctrl(c) if c == 'e' => app.on_edit(Edit::End).await,
+                                Key::Ctrl(c) if c == 'w' => {
+                                    app.on_edit(Edit::DeleteWordBefore).await
+                                }
+                                Key::Alt(c) if c == '\r' || c == '\n' => {
+                                    app.on_edit(Edit::Newline).await
+                                }
+                                Key::Alt(c) if c == '\x7f' => {
+                                    app.on_edit(Edit::DeleteWordBefore).await
+                                }
+                                Key::Alt(c) if c == 'd' => app.on_edit(Edit::DeleteWordAfter).await,
+                                Key::Alt(c) if c == 'b' => app.on_edit(Edit::WordLeft).await,
+                                Key::Alt(c) if c == 'f' => app.on_edit(Edit::WordRight).await,
+                                Key::Home => app.on_home().await,
+                                Key::End => app.on_end().await,
+                                Key::PageUp => app.on_page_up().await,
+                                Key::PageDown => app.on_page_down().await,
+                                Key::Up => app.on_up().await,
+                                Key::Down => app.on_down().await,
+                                Key::Left => app.on_left().await,
+                                Key::Right => app.on_right().await,
+                                Key::Backspace => app.on_backspace().await,
+                                Key::Delete => app.on_delete().await,
+                                Key::Char(c) if c == '\t' => app.on_tab().await,
+                                Key::BackTab => app.on_back_tab().await,
+                                Key::Char(c) => app.on_key(c).await,
+                                Key::Esc => app.on_esc().await,
+                                _ => {}
+                            }
                         }
                     }
                     TermEvent::Mouse(m) => {
@@ -167,14 +278,20 @@ fn main() -> Result<(), failure::Error> {
                             MouseEvent::Hold(_, _) => {}
                         }
                     }
-                    TermEvent::Unsupported(_) => {}
+                    TermEvent::Unsupported(bytes) => {
+                        if let Some(to) = focus::ctrl_arrow(&bytes) {
+                            app.on_move_focus(to).await
+                        }
+                    }
                 },
+                Event::Paste(text) => app.on_paste(text).await,
                 Event::Tick => {
                     app.on_tick(&events).await;
                 }
             }
 
             if app.should_quit {
+                write!(terminal.backend_mut(), "{}", DISABLE_PASTE)?;
                 terminal.clear()?;
                 app.on_quit().await;
                 break;
@@ -184,6 +301,50 @@ fn main() -> Result<(), failure::Error> {
     })
 }
 
+/// Links are written as OSC 8 hyperlinks unless `RUMATUI_HYPERLINKS=0` is set, terminals
+/// without hyperlinks ignore them except for the Linux console.
+fn hyperlinks_supported() -> bool {
+    let term = std::env::var("TERM").ok();
+    std::env::var("RUMATUI_HYPERLINKS").ok().as_deref() != Some("0")
+        && !matches!(term.as_deref(), None | Some("linux") | Some("dumb"))
+}
+
+/// `#rrggbb` colors are drawn as is when `COLORTERM` says the terminal supports them,
+/// otherwise they are turned into the closest of the 256 colors.
+fn truecolor_supported() -> bool {
+    matches!(
+        std::env::var("COLORTERM").ok().as_deref(),
+        Some("truecolor") | Some("24bit")
+    )
+}
+
+/// Hands the terminal to another program while `run` is called.
+///
+/// Input, mouse capture and raw mode are released and re-acquired after, the TUI is
+/// redrawn on the next draw.
+fn run_suspended<W: Write, T>(
+    raw: &RawTerminal<Stdout>,
+    terminal: &mut Terminal<TermionBackend<W>>,
+    events: &UiEventHandle,
+    run: impl FnOnce() -> T,
+) -> io::Result<T> {
+    events.pause();
+    terminal.clear()?;
+    terminal.show_cursor()?;
+    write!(terminal.backend_mut(), "{}{}", DISABLE_MOUSE, DISABLE_PASTE)?;
+    terminal.backend_mut().flush()?;
+    raw.suspend_raw_mode()?;
+
+    let res = run();
+
+    raw.activate_raw_mode()?;
+    write!(terminal.backend_mut(), "{}{}", ENABLE_MOUSE, ENABLE_PASTE)?;
+    terminal.hide_cursor()?;
+    terminal.clear()?;
+    events.resume();
+    Ok(res)
+}
+
 #[rustfmt::skip]
 #[allow(clippy::print_literal)]
 fn print_help() {
@@ -197,14 +358,37 @@ fn print_help() {
         "   -v, --verbose   Will create a log of the session at '~/.rumatui/logs.json'\n\n",
         "KEY-BINDINGS:",
 r#"
-    * Esc will exit `rumatui`
+    * Esc cancels loading previous messages or a room search, closes the error history, dismisses an error, closes a dialog or focuses the send box, otherwise it will exit `rumatui`
+    * Ctrl-r retries the request of the error shown in the header, Ctrl-l shows the history of errors
+    * Ctrl-arrows or Alt-h/j/k/l move focus between the room list, timeline, send box and member list, Tab/Shift-Tab cycle through them
+    * Up/down arrows select a room in the room list, a message in the timeline or a member in the member list
+    * PageUp/PageDown move the selected message a page, Home/End select the first loaded message or jump to the latest one
+    * Clicking a link in a message opens it in the browser, o opens the first link of the selected message
+    * The first link of a message gets a preview card fetched by the homeserver, `/previews off` turns previews off in the room and `/previews off all` everywhere
+    * Enter in the room list or timeline focuses the send box, Enter in the member list starts a direct message
+    * a/d accept or decline an invite while the room list has focus
     * Enter still works for all buttons except the decline/accept invite
-    * Ctrl-s sends a message
-    * Delete leaves and forgets the selected room
+    * Enter or Ctrl-s sends a message, Alt-Enter starts a new line in the send box
+    * Pasted text is inserted as is, new lines in a paste never send a message or submit a form
+    * Ctrl-x opens the send box in $VISUAL or $EDITOR, the edited text replaces the send box
+    * Delete leaves and forgets the selected room when the send box is empty
     * Left/right arrows, while at the login window, toggles login/register window
     * Left arrow, while at the main chat window, brings up the room search window
     * Enter, while in the room search window, starts the search
     * Ctrl-d, while a room is selected in the room search window, joins the room
+    * Ctrl-n opens the start direct message input, enter a user id and hit Enter
+    * Ctrl-f adds or removes the selected room from the favourites
+    * Ctrl-t opens the tag input, the entered tag is added to or removed from the selected room
+    * Ctrl-o collapses or expands the selected room list section
+    * Typing `/` in the send box starts a command, `/help` lists them and Tab completes them
+    * `/settings` opens the theme list, Up/Down (j/k) preview a theme, Enter keeps it and Esc goes back to the old theme
+    * Left/right arrows, Home/End (Ctrl-a/Ctrl-e) and Alt-b/Alt-f move the cursor in the send box
+    * Backspace/Delete remove a character, Ctrl-w/Alt-Backspace and Alt-d remove a word
+    * Up/down arrows move between lines in the send box, from an empty send box they go through the messages sent to the room
+    * Tab/Shift-Tab in the send box cycle through completions of `@user`, names, `#alias` and `:emoji:` before moving focus
+    * With `"vim": true` in '~/.rumatui/keymap.json' keys start in normal mode, shown in the header:
+      j/k move through messages, rooms or members, gg/G jump to the top or bottom, / searches rooms,
+      i enters the send box, r/e/dd reply to, edit or delete the selected message, o opens its first link and Esc goes back to normal mode
 "#,
     )
 }
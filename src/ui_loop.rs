@@ -1,13 +1,28 @@
-use std::{io, sync::mpsc, thread, time::Duration};
+use std::{
+    io::{self, Read},
+    os::unix::io::{AsRawFd, RawFd},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::Duration,
+};
 
 use termion::{
-    event::{Event as TermEvent, Key},
-    input::{MouseTerminal, TermRead},
+    event::{parse_event, Event as TermEvent, Key},
+    input::MouseTerminal,
     raw::IntoRawMode,
 };
 
+/// Sent by the terminal before and after pasted text when bracketed paste is enabled.
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
 pub enum Event<I> {
     Input(I),
+    /// Text pasted while bracketed paste is enabled, new lines are `\n`.
+    Paste(String),
     Tick,
 }
 
@@ -17,6 +32,10 @@ pub struct UiEventHandle {
     recv: mpsc::Receiver<Event<TermEvent>>,
     input_handle: thread::JoinHandle<()>,
     tick_handle: thread::JoinHandle<()>,
+    /// When true the input thread stops reading stdin so another program can.
+    paused: Arc<AtomicBool>,
+    /// The input thread sends on this once it has stopped reading stdin.
+    paused_ack: mpsc::Receiver<()>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -32,19 +51,62 @@ impl UiEventHandle {
         let stdout = io::stdout().into_raw_mode().unwrap();
         let _stdout = MouseTerminal::from(stdout);
 
+        let paused = Arc::new(AtomicBool::new(false));
+        let (ack_send, paused_ack) = mpsc::channel();
+
         let input_handle = {
             let send = send.clone();
+            let paused = Arc::clone(&paused);
             thread::spawn(move || {
-                let stdin = io::stdin();
-                for ev in stdin.events() {
-                    let ev = ev.unwrap();
-
-                    if let TermEvent::Key(Key::Char('q')) = ev {
-                        return;
+                // stdin keeps its own buffer that `poll` can not see, the tty is read
+                // without one so a ready check never misses pending input
+                let tty = termion::get_tty().unwrap();
+                let mut buf = [0; 1024];
+                let mut acked = false;
+                let mut paste: Option<String> = None;
+                loop {
+                    if paused.load(Ordering::SeqCst) {
+                        if !acked {
+                            acked = true;
+                            if ack_send.send(()).is_err() {
+                                return;
+                            }
+                        }
+                        thread::sleep(PAUSED_POLL);
+                        continue;
+                    }
+                    acked = false;
+                    // only read when there is input so a pause is never stuck behind a read
+                    if !input_ready(tty.as_raw_fd(), PAUSED_POLL) {
+                        continue;
                     }
 
-                    if send.send(Event::Input(ev)).is_err() {
-                        return;
+                    let read = match (&tty).read(&mut buf) {
+                        Ok(0) | Err(_) => return,
+                        Ok(read) => read,
+                    };
+                    // every byte read is parsed before waiting for more input, a sequence
+                    // cut off by the end of the buffer is finished from the tty
+                    let mut bytes = buf[..read].iter();
+                    while let Some(&byte) = bytes.next() {
+                        let ev = if byte == b'\x1b' && bytes.as_slice().is_empty() {
+                            TermEvent::Key(Key::Esc)
+                        } else {
+                            let mut rest = bytes.by_ref().map(|b| Ok(*b)).chain((&tty).bytes());
+                            match parse_event(byte, &mut rest) {
+                                Ok(ev) => ev,
+                                Err(_) => continue,
+                            }
+                        };
+
+                        let event = match collect_paste(ev, &mut paste) {
+                            Input::Event(event) => event,
+                            Input::Pasting => continue,
+                            Input::Quit => return,
+                        };
+                        if send.send(event).is_err() {
+                            return;
+                        }
                     }
                 }
             })
@@ -62,6 +124,8 @@ impl UiEventHandle {
             recv,
             input_handle,
             tick_handle,
+            paused,
+            paused_ack,
         }
     }
 
@@ -69,9 +133,62 @@ impl UiEventHandle {
         self.recv.recv()
     }
 
+    /// Stop reading input, this returns once the input thread is no longer reading stdin.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        let _ = self.paused_ack.recv();
+    }
+
+    /// Start reading input again after a `pause`.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
     #[allow(dead_code)]
     pub fn shutdown(self) {
         let _ = self.input_handle.join();
         let _ = self.tick_handle.join();
     }
 }
+
+/// How long the input thread waits before checking if it has been paused.
+const PAUSED_POLL: Duration = Duration::from_millis(50);
+
+/// What the input thread does with a terminal event.
+enum Input {
+    Event(Event<TermEvent>),
+    /// The event is part of a paste that has not ended yet.
+    Pasting,
+    Quit,
+}
+
+/// Collects everything between the paste start and end sequences into one `Event::Paste`.
+fn collect_paste(ev: TermEvent, paste: &mut Option<String>) -> Input {
+    match (ev, paste.as_mut()) {
+        (TermEvent::Unsupported(seq), None) if seq == PASTE_START => {
+            *paste = Some(String::new());
+            Input::Pasting
+        }
+        (TermEvent::Unsupported(seq), Some(_)) if seq == PASTE_END => {
+            Input::Event(Event::Paste(paste.take().unwrap_or_default()))
+        }
+        (TermEvent::Key(Key::Char(c)), Some(text)) => {
+            text.push(c);
+            Input::Pasting
+        }
+        (_, Some(_)) => Input::Pasting,
+        (TermEvent::Key(Key::Char('q')), None) => Input::Quit,
+        (ev, None) => Input::Event(Event::Input(ev)),
+    }
+}
+
+/// Waits up to `timeout` for `fd` to have input to read.
+fn input_ready(fd: RawFd, timeout: Duration) -> bool {
+    let mut fd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // safe as the pointer is to exactly one `pollfd` that lives for the whole call
+    unsafe { libc::poll(&mut fd, 1, timeout.as_millis() as libc::c_int) > 0 }
+}
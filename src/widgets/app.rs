@@ -1,4 +1,9 @@
-use std::{io, ops::Deref, sync::Arc, time::SystemTime};
+use std::{
+    convert::TryFrom,
+    io,
+    sync::Arc,
+    time::{Instant, SystemTime},
+};
 
 use matrix_sdk::{
     api::r0::{
@@ -9,7 +14,7 @@ use matrix_sdk::{
     events::{
         room::{
             member::MembershipChange,
-            message::{MessageEventContent, TextMessageEventContent},
+            message::{EmoteMessageEventContent, MessageEventContent},
         },
         AnySyncMessageEvent, AnySyncRoomEvent, SyncMessageEvent,
     },
@@ -19,11 +24,11 @@ use matrix_sdk::{
 use rumatui_tui::{
     backend::Backend,
     layout::{Alignment, Constraint, Layout},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     widgets::{Block, Borders, Paragraph, Text},
     Terminal,
 };
-use termion::event::MouseButton;
+use termion::event::{Key, MouseButton};
 use tokio::{
     fs as async_fs,
     runtime::Handle,
@@ -33,18 +38,27 @@ use uuid::Uuid;
 
 use crate::{
     client::{
-        client_loop::{MatrixEventHandle, RequestResult, UserRequest},
+        client_loop::{MatrixEventHandle, RequestId, RequestResult, RequestSender, UserRequest},
+        connection::Connection,
         event_stream::{EventStream, StateResult},
+        outbox::{SendState, Unsent},
     },
     error::Error,
+    keymap::{Action, Mode, Vim},
     ui_loop::{Event, UiEventHandle},
     widgets::{
         chat::ChatWidget,
+        command::{self, Command},
         error::ErrorWidget,
+        focus::{Focus, Move},
         login::{Login, LoginSelect, LoginWidget},
-        message::Message,
+        message::{Draft, Message, Notice},
         register::{Register, RegisterSelect, RegisterWidget},
-        rooms::Invite,
+        rooms::{Invite, RoomInputKind},
+        settings::SettingsWidget,
+        text_input::Edit,
+        theme::Themes,
+        toast::{ErrorHistoryWidget, Severity, Toasts},
         DrawWidget, RenderWidget,
     },
 };
@@ -64,8 +78,14 @@ pub struct AppWidget {
     pub should_quit: bool,
     /// Have we started the sync loop yet.
     pub sync_started: bool,
+    /// The result of the last sync, `None` until the first sync finishes.
+    pub connection: Option<Connection>,
     /// Have we started a scroll request.
     pub scrolling: bool,
+    /// The request loading previous messages of a room, cancelled with Esc.
+    history_request: Option<(RequestId, RoomId)>,
+    /// The request searching the room directory, cancelled with Esc.
+    search_request: Option<RequestId>,
     /// The client is sending a typing notice to the server.
     pub typing_notice: bool,
     /// The last interaction the user had with the app.
@@ -81,12 +101,21 @@ pub struct AppWidget {
     /// the event loop for MatrixClient tasks to run on.
     pub ev_loop: MatrixEventHandle,
     /// Send MatrixClient jobs to the event handler
-    pub send_jobs: mpsc::Sender<UserRequest>,
+    pub send_jobs: RequestSender,
     /// The result of any MatrixClient job.
     pub ev_msgs: mpsc::Receiver<RequestResult>,
     /// The result of any MatrixClient job.
     pub emitter_msgs: mpsc::Receiver<StateResult>,
+    /// A fatal error, shown in place of the UI.
     pub error: Option<Error>,
+    /// Errors that are not fatal and the history of every error.
+    toasts: Toasts,
+    /// Normal and insert mode, `None` unless turned on in the keymap file.
+    pub vim: Option<Vim>,
+    /// The theme every widget is drawn with.
+    pub themes: Themes,
+    /// The settings screen, shown in place of the main screen while open.
+    settings: Option<SettingsWidget>,
     registration: Option<String>,
 }
 
@@ -108,7 +137,10 @@ impl AppWidget {
             homeserver: homeserver.to_string(),
             should_quit: false,
             sync_started: false,
+            connection: None,
             scrolling: false,
+            history_request: None,
+            search_request: None,
             typing_notice: false,
             last_interaction: SystemTime::now(),
             login_w: LoginWidget::default(),
@@ -120,59 +152,79 @@ impl AppWidget {
             ev_msgs: recv,
             emitter_msgs,
             error: None,
+            toasts: Toasts::default(),
+            vim: None,
+            themes: Themes::default(),
+            settings: None,
             registration: None,
         }
     }
 
     pub async fn on_click(&mut self, btn: MouseButton, x: u16, y: u16) {
+        if self.login_w.logged_in && self.chat.is_main_screen() && btn == MouseButton::Left {
+            if let Some(url) = self.chat.link_at(x, y).map(String::from) {
+                self.open_link(&url);
+                return;
+            }
+        }
         if !self.login_w.logged_in && self.login_or_register == LoginOrRegister::Login {
             self.login_w.on_click(btn, x, y);
         }
+        if self.login_w.logged_in && self.chat.is_main_screen() {
+            self.chat.focus_on_click(x, y);
+        }
         if self.chat.msgs_on_click(btn, x, y) {
             self.on_send().await;
         }
-        if let Some(room_id) = self.chat.as_invite().map(|i| i.room_id.clone()) {
+        if self.chat.is_main_screen() && self.chat.room_list_on_click(btn, x, y) {
+            self.chat.reset_scroll()
+        }
+        if self.chat.as_invite().is_some() {
             match self.chat.room_on_click(btn, x, y) {
-                Invite::Accept => {
-                    if let Err(e) = self
-                        .send_jobs
-                        .send(UserRequest::AcceptInvite(room_id))
-                        .await
-                    {
-                        self.set_error(e.into())
-                    } else {
-                        self.chat.set_joining_room(true);
-                        self.chat.remove_invite();
-                    }
-                }
-                Invite::Decline => {
-                    if let Err(e) = self
-                        .send_jobs
-                        .send(UserRequest::DeclineInvite(room_id))
-                        .await
-                    {
-                        self.set_error(e.into())
-                    } else {
-                        self.chat.remove_invite();
-                    }
-                }
+                Invite::Accept => self.answer_invite(true).await,
+                Invite::Decline => self.answer_invite(false).await,
                 Invite::NoClick => {}
             }
         }
     }
 
+    fn open_link(&mut self, url: &str) {
+        if let Err(e) = webbrowser::open(url) {
+            self.set_error(e.into());
+        }
+    }
+
+    /// Opens the first link in the selected message.
+    fn open_selected_link(&mut self) {
+        if let Some(url) = self.chat.selected_link().map(String::from) {
+            self.open_link(&url);
+        }
+    }
+
+    /// Accepts or declines the invite shown in the room list.
+    async fn answer_invite(&mut self, accept: bool) {
+        if let Some(room_id) = self.chat.as_invite().map(|i| i.room_id.clone()) {
+            let request = if accept {
+                UserRequest::AcceptInvite(room_id)
+            } else {
+                UserRequest::DeclineInvite(room_id)
+            };
+            if let Err(e) = self.send_jobs.send(request).await {
+                self.set_error(e.into())
+            } else {
+                if accept {
+                    self.chat.set_joining_room(true);
+                }
+                self.chat.remove_invite();
+            }
+        }
+    }
+
     // TODO flatten out some
     pub async fn on_scroll_up(&mut self, x: u16, y: u16) {
         if self.chat.is_main_screen() {
             if self.chat.msgs_on_scroll_up(x, y) {
-                if !self.scrolling {
-                    self.scrolling = true;
-                    if let Some(room_id) = self.chat.to_current_room_id() {
-                        if let Err(e) = self.send_jobs.send(UserRequest::RoomMsgs(room_id)).await {
-                            self.set_error(e.into())
-                        }
-                    }
-                }
+                self.timeline_top_reached().await;
             } else if self.chat.room_on_scroll_up(x, y) {
                 self.chat.reset_scroll()
             } else if self.chat.room_search_scroll_up(x, y) {
@@ -188,12 +240,13 @@ impl AppWidget {
                 if self.chat.room_search_scroll_down(x, y) {
                     if let Some((filter, network, next_tkn)) = self.chat.room_search_next_request()
                     {
-                        if let Err(e) = self
+                        match self
                             .send_jobs
                             .send(UserRequest::RoomSearch(filter, network, Some(next_tkn)))
                             .await
                         {
-                            self.set_error(e.into())
+                            Ok(id) => self.search_request = Some(id),
+                            Err(e) => self.set_error(e.into()),
                         }
                     }
                 }
@@ -207,6 +260,16 @@ impl AppWidget {
         }
     }
 
+    /// Loads older messages once the top of the timeline is reached.
+    async fn timeline_top_reached(&mut self) {
+        if !self.scrolling {
+            self.scrolling = true;
+            if let Some(room_id) = self.chat.to_current_room_id() {
+                self.load_history(room_id).await;
+            }
+        }
+    }
+
     pub async fn on_up(&mut self) {
         if !self.login_w.logged_in {
             match self.login_or_register {
@@ -225,12 +288,28 @@ impl AppWidget {
                     }
                 }
             }
-        } else if self.chat.is_main_screen() {
+        } else if self.is_composing() {
+            // moves up a line or back through the history
+            self.edit_sending_text(Edit::Up).await;
+        } else {
+            self.select_previous().await
+        }
+    }
+
+    /// Moves up in the open dialog or the focused pane.
+    async fn select_previous(&mut self) {
+        if self.chat.is_main_screen() {
             if self.chat.is_room_search() {
                 self.chat.room_search_select_previous();
-            } else {
+            } else if self.chat.has_dialog() || self.chat.focus() == Focus::Rooms {
                 self.chat.room_select_previous();
                 self.chat.reset_scroll()
+            } else if self.chat.focus() == Focus::Timeline {
+                if self.chat.message_select_previous() {
+                    self.timeline_top_reached().await;
+                }
+            } else if self.chat.focus() == Focus::Members {
+                self.chat.member_select_previous()
             }
         }
     }
@@ -253,18 +332,242 @@ impl AppWidget {
                     }
                 }
             }
-        } else if self.chat.is_main_screen() {
+        } else if self.is_composing() {
+            // moves down a line or forward through the history
+            self.edit_sending_text(Edit::Down).await;
+        } else {
+            self.select_next()
+        }
+    }
+
+    /// Moves down in the open dialog or the focused pane.
+    fn select_next(&mut self) {
+        if self.chat.is_main_screen() {
             if self.chat.is_room_search() {
                 self.chat.room_search_select_next()
-            } else {
+            } else if self.chat.has_dialog() || self.chat.focus() == Focus::Rooms {
                 self.chat.room_select_next();
                 self.chat.reset_scroll()
+            } else if self.chat.focus() == Focus::Timeline {
+                self.chat.message_select_next()
+            } else if self.chat.focus() == Focus::Members {
+                self.chat.member_select_next()
             }
         }
     }
 
-    pub fn on_right(&mut self) {
-        if !self.login_w.logged_in {
+    /// Are typed keys normal mode bindings instead of text.
+    pub fn is_normal_mode(&self) -> bool {
+        self.vim
+            .as_ref()
+            .map_or(false, |vim| vim.mode == Mode::Normal)
+            && self.error.is_none()
+            && self.login_w.logged_in
+            && self.chat.is_main_screen()
+            && !self.chat.has_dialog()
+    }
+
+    pub fn is_settings_open(&self) -> bool {
+        self.settings.is_some() && self.error.is_none()
+    }
+
+    /// Moving through the themes previews them, Enter keeps the selected theme and Esc
+    /// puts back the theme in use before the settings screen was opened.
+    pub async fn on_settings_key(&mut self, key: Key) {
+        let settings = match self.settings.as_mut() {
+            Some(settings) => settings,
+            None => return,
+        };
+        match key {
+            Key::Up | Key::Char('k') => settings.select_previous(),
+            Key::Down | Key::Char('j') => settings.select_next(),
+            Key::Char('\n') => {
+                self.settings = None;
+                if let Err(e) = self.themes.save().await {
+                    self.set_error(e);
+                }
+                return;
+            }
+            Key::Esc => {
+                let original = settings.original().to_string();
+                self.settings = None;
+                if let Err(e) = self.themes.use_theme(&original) {
+                    self.set_warning(e);
+                }
+                return;
+            }
+            _ => return,
+        }
+        if let Some(name) = settings.selected().map(String::from) {
+            if let Err(e) = self.themes.use_theme(&name) {
+                self.set_warning(e);
+            }
+        }
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        if let Some(vim) = self.vim.as_mut() {
+            vim.set_mode(mode);
+        }
+        if mode == Mode::Insert {
+            self.chat.set_focus(Focus::Composer);
+        }
+    }
+
+    /// Runs the action of the normal mode binding `c` completes.
+    pub async fn on_normal_key(&mut self, c: char) {
+        let action = match self.vim.as_mut().and_then(|vim| vim.key(c)) {
+            Some(action) => action,
+            None => return,
+        };
+        let focus = self.chat.focus();
+        match action {
+            Action::Down | Action::Up | Action::Top | Action::Bottom => {
+                // the send box has no use for these, they move through the messages above it
+                if focus == Focus::Composer {
+                    self.chat.set_focus(Focus::Timeline);
+                }
+                match action {
+                    Action::Down => self.select_next(),
+                    Action::Up => self.select_previous().await,
+                    Action::Top => self.select_first(),
+                    _ => self.select_last(),
+                }
+            }
+            Action::Search => self.on_ctrl_k().await,
+            Action::OpenLink => self.open_selected_link(),
+            Action::Insert => self.set_mode(Mode::Insert),
+            Action::Reply => {
+                if let Some(msg) = self.chat.selected_message() {
+                    let draft = Draft::Reply(msg.event_id.clone(), msg.name.clone());
+                    self.chat.set_draft(draft);
+                    self.set_mode(Mode::Insert);
+                }
+            }
+            Action::Edit => {
+                let me = self.chat.to_current_user();
+                if let Some(msg) = self
+                    .chat
+                    .selected_message()
+                    .filter(|msg| Some(&msg.user) == me.as_ref())
+                {
+                    let (event_id, text) = (msg.event_id.clone(), msg.text.clone());
+                    self.chat.set_draft(Draft::Edit(event_id));
+                    self.chat.set_sending_text(text);
+                    self.set_mode(Mode::Insert);
+                }
+            }
+            Action::Delete => {
+                let me = self.chat.to_current_user();
+                let request = match self
+                    .chat
+                    .selected_message()
+                    .filter(|msg| Some(&msg.user) == me.as_ref())
+                {
+                    // an echo the server never accepted only has a placeholder event id
+                    Some(msg)
+                        if matches!(
+                            msg.send_state,
+                            Some(SendState::Pending) | Some(SendState::Failed(_))
+                        ) =>
+                    {
+                        Some(UserRequest::DiscardMessages(Unsent::Message(msg.uuid)))
+                    }
+                    Some(msg) => self
+                        .chat
+                        .to_current_room_id()
+                        .map(|room_id| UserRequest::RedactMessage(room_id, msg.event_id.clone())),
+                    None => None,
+                };
+                if let Some(request) = request {
+                    if let Err(e) = self.send_jobs.send(request).await {
+                        self.set_error(e.into());
+                    }
+                }
+            }
+            Action::FocusLeft => self.chat.move_focus(Move::Left),
+            Action::FocusRight => self.chat.move_focus(Move::Right),
+            Action::FocusUp => self.chat.move_focus(Move::Up),
+            Action::FocusDown => self.chat.move_focus(Move::Down),
+        }
+    }
+
+    /// Selects the first room or member, in the timeline the first loaded message.
+    fn select_first(&mut self) {
+        match self.chat.focus() {
+            Focus::Rooms => {
+                self.chat.room_select_first();
+                self.chat.reset_scroll();
+            }
+            Focus::Members => self.chat.member_select_first(),
+            Focus::Timeline | Focus::Composer => self.chat.message_select_first(),
+        }
+    }
+
+    /// Selects the last room or member, in the timeline jumps to the latest message.
+    fn select_last(&mut self) {
+        match self.chat.focus() {
+            Focus::Rooms => {
+                self.chat.room_select_last();
+                self.chat.reset_scroll();
+            }
+            Focus::Members => self.chat.member_select_last(),
+            Focus::Timeline | Focus::Composer => self.chat.clear_message_selection(),
+        }
+    }
+
+    /// Is a pane of the main screen handling the keyboard, rather than a dialog or the
+    /// login screen.
+    fn is_pane_focused(&self) -> bool {
+        self.login_w.logged_in && self.chat.is_main_screen() && !self.chat.has_dialog()
+    }
+
+    /// Moves the send box cursor to the start of the line or selects the first item of
+    /// the focused pane.
+    pub async fn on_home(&mut self) {
+        if self.is_composing() {
+            self.on_edit(Edit::Home).await;
+        } else if self.is_pane_focused() {
+            self.select_first();
+        }
+    }
+
+    /// Moves the send box cursor to the end of the line or selects the last item of the
+    /// focused pane, in the timeline that jumps to the latest message.
+    pub async fn on_end(&mut self) {
+        if self.is_composing() {
+            self.on_edit(Edit::End).await;
+        } else if self.is_pane_focused() {
+            self.select_last();
+        }
+    }
+
+    /// Moves the timeline selection up a page, from any pane.
+    pub async fn on_page_up(&mut self) {
+        if self.is_pane_focused() && self.chat.message_page_up() {
+            self.timeline_top_reached().await;
+        }
+    }
+
+    /// Moves the timeline selection down a page, from any pane.
+    pub async fn on_page_down(&mut self) {
+        if self.is_pane_focused() {
+            self.chat.message_page_down();
+        }
+    }
+
+    /// Moves focus to another pane, while a dialog is open focus stays put.
+    pub async fn on_move_focus(&mut self, to: Move) {
+        if self.is_pane_focused() {
+            self.chat.move_focus(to);
+        }
+    }
+
+    /// If there is text in the send box move the cursor right.
+    pub async fn on_right(&mut self) {
+        if self.has_sending_text() {
+            self.on_edit(Edit::Right).await;
+        } else if !self.login_w.logged_in {
             if self.login_or_register == LoginOrRegister::Login {
                 self.login_or_register = LoginOrRegister::Register;
             } else {
@@ -276,9 +579,11 @@ impl AppWidget {
     /// If not logged in toggle login and registration.
     ///
     /// If we are at the main screen (after login) go to the room search
-    /// window.
-    pub fn on_left(&mut self) {
-        if !self.login_w.logged_in {
+    /// window, unless there is text in the send box then the cursor moves left.
+    pub async fn on_left(&mut self) {
+        if self.has_sending_text() {
+            self.on_edit(Edit::Left).await;
+        } else if !self.login_w.logged_in {
             if self.login_or_register == LoginOrRegister::Login {
                 self.login_or_register = LoginOrRegister::Register;
             } else {
@@ -345,23 +650,41 @@ impl AppWidget {
                     }
                 }
             } else if self.chat.is_main_screen() {
-                if self.chat.is_room_search() {
+                if self.chat.room_input_kind().is_some() {
+                    if let Some((kind, text)) = self.chat.room_input_add_char(c) {
+                        self.submit_room_input(kind, text.trim()).await;
+                    }
+                } else if self.chat.is_room_search() {
                     if c == '\n' && self.chat.try_room_search() {
                         let filter = self.chat.search_term().to_string();
-                        if let Err(e) = self
+                        match self
                             .send_jobs
                             .send(UserRequest::RoomSearch(filter, RoomNetwork::Matrix, None))
                             .await
                         {
-                            self.set_error(Error::from(e));
-                        } else {
-                            self.chat.clear_room_search();
+                            Ok(id) => {
+                                self.search_request = Some(id);
+                                self.chat.clear_room_search();
+                            }
+                            Err(e) => self.set_error(Error::from(e)),
                         }
                         return;
                     }
                     self.chat.push_search_text(c)
+                } else if self.chat.is_quick_select() {
+                    self.chat.add_char(c);
+                } else if self.chat.focus() != Focus::Composer {
+                    self.on_pane_key(c).await
+                } else if c == '\n' {
+                    self.on_send().await
                 } else {
-                    if !self.chat.is_quick_select() {
+                    // commands are never sent as messages so don't send a typing notice
+                    let composing_command = self
+                        .chat
+                        .sending_text()
+                        .map(|text| command::is_command(&text) || (text.is_empty() && c == '/'))
+                        .unwrap_or(false);
+                    if !composing_command {
                         // send typing notice to the server
                         let room_id = self.chat.to_current_room_id();
                         if !self.typing_notice {
@@ -379,6 +702,7 @@ impl AppWidget {
                     }
 
                     self.chat.add_char(c);
+                    self.update_command_hint().await;
                 }
             }
         }
@@ -388,7 +712,38 @@ impl AppWidget {
         self.add_char(c).await;
     }
 
-    pub fn on_backspace(&mut self) {
+    /// A key typed while the room list, timeline or member list has focus.
+    async fn on_pane_key(&mut self, c: char) {
+        match (self.chat.focus(), c) {
+            (Focus::Rooms, 'a') => self.answer_invite(true).await,
+            (Focus::Rooms, 'd') => self.answer_invite(false).await,
+            (Focus::Rooms, '\n') | (Focus::Timeline, '\n') => self.chat.set_focus(Focus::Composer),
+            (Focus::Timeline, 'o') => self.open_selected_link(),
+            (Focus::Members, '\n') => {
+                if let Some(user_id) = self.chat.selected_member().cloned() {
+                    self.start_direct_message(user_id).await
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Pasted text goes into the send box as is, other inputs are a single line so
+    /// new lines are dropped and never submit.
+    pub async fn on_paste(&mut self, text: String) {
+        if self.is_composing() {
+            if self.error.is_none() {
+                self.chat.insert_sending_text(&text);
+                self.update_command_hint().await;
+            }
+        } else {
+            for c in text.chars().filter(|c| *c != '\n' && *c != '\r') {
+                self.add_char(c).await;
+            }
+        }
+    }
+
+    pub async fn on_backspace(&mut self) {
         if !self.login_w.logged_in {
             match self.login_or_register {
                 LoginOrRegister::Login => {
@@ -409,14 +764,104 @@ impl AppWidget {
         } else if self.chat.is_main_screen() {
             if self.chat.is_room_search() {
                 self.chat.pop_search_text()
-            } else {
+            } else if self.chat.has_dialog() || self.chat.focus() == Focus::Composer {
                 self.chat.remove_char();
+                self.update_command_hint().await;
+            }
+        }
+    }
+
+    /// Tab complete the word being typed, otherwise select the next item.
+    pub async fn on_tab(&mut self) {
+        if self.complete(false).await {
+            // the send box cycled to another candidate
+        } else if self.login_w.logged_in && self.chat.is_main_screen() && !self.chat.has_dialog() {
+            self.chat.move_focus(Move::Next)
+        } else {
+            self.on_down().await
+        }
+    }
+
+    /// Cycle backwards through the completions, otherwise select the previous item.
+    pub async fn on_back_tab(&mut self) {
+        if self.complete(true).await {
+            // the send box cycled to another candidate
+        } else if self.login_w.logged_in && self.chat.is_main_screen() && !self.chat.has_dialog() {
+            self.chat.move_focus(Move::Previous)
+        } else {
+            self.on_up().await
+        }
+    }
+
+    /// Is the send box the focused text input.
+    fn is_composing(&self) -> bool {
+        self.login_w.logged_in
+            && self.chat.is_main_screen()
+            && !self.chat.has_dialog()
+            && self.chat.focus() == Focus::Composer
+    }
+
+    fn has_sending_text(&self) -> bool {
+        self.is_composing()
+            && self
+                .chat
+                .sending_text()
+                .map_or(false, |text| !text.is_empty())
+    }
+
+    /// The text of the send box to open in an external editor, `None` if the
+    /// send box is not focused.
+    pub fn editor_draft(&self) -> Option<String> {
+        if self.is_composing() {
+            self.chat.sending_text()
+        } else {
+            None
+        }
+    }
+
+    /// Replace the send box with the text from the external editor.
+    pub async fn on_editor_exit(&mut self, edited: io::Result<String>) {
+        match edited {
+            Ok(text) => {
+                self.chat.set_sending_text(text);
+                self.update_command_hint().await;
             }
+            Err(e) => self.set_error(e.into()),
+        }
+    }
+
+    /// Move the cursor or edit the text of the send box.
+    pub async fn on_edit(&mut self, edit: Edit) {
+        self.edit_sending_text(edit).await;
+    }
+
+    /// Returns false if nothing changed.
+    async fn edit_sending_text(&mut self, edit: Edit) -> bool {
+        if self.is_composing() && self.chat.edit_sending_text(edit) {
+            self.update_command_hint().await;
+            true
+        } else {
+            false
+        }
+    }
+
+    async fn complete(&mut self, reverse: bool) -> bool {
+        if self.is_composing() && self.chat.complete(reverse).await {
+            self.update_command_hint().await;
+            true
+        } else {
+            false
         }
     }
 
+    /// Deletes the character after the cursor, with an empty send box leaves the room.
     pub async fn on_delete(&mut self) {
-        if self.chat.is_main_screen() {
+        if self.has_sending_text() {
+            self.on_edit(Edit::DeleteAfter).await;
+        } else if self.chat.is_main_screen()
+            && !self.chat.has_dialog()
+            && matches!(self.chat.focus(), Focus::Rooms | Focus::Composer)
+        {
             let id = self.chat.to_current_room_id();
             if let Some(room_id) = id {
                 if let Err(e) = self.send_jobs.send(UserRequest::LeaveRoom(room_id)).await {
@@ -429,43 +874,42 @@ impl AppWidget {
     }
 
     pub async fn on_send(&mut self) {
-        // unfortunately we have to do it this way or we have a mutable borrow in the scope of immutable
-        let res = if let Some(room_id) = self.chat.to_current_room_id() {
-            match self.chat.get_sending_message() {
-                Ok(msg) => {
-                    self.chat.set_sending_message(true);
-                    let uuid = Uuid::new_v4();
-                    let message = msg.clone();
-                    if let Err(e) = self
-                        .send_jobs
-                        .send(UserRequest::SendMessage(room_id.clone(), msg, uuid))
-                        .await
-                    {
-                        Err(e.into())
-                    } else {
-                        // find the room the message was just sent to
-                        let local_message = if let Some(room) = self.chat.rooms().get(&room_id) {
-                            let r = room.read().await;
-                            let matrix_sdk::Room { joined_members, .. } = r.deref();
-                            let name = if let Some(mem) =
-                                joined_members.get(self.chat.as_current_user().unwrap())
-                            {
-                                mem.name()
-                            } else {
-                                self.chat.as_current_user().unwrap().localpart().into()
-                            };
-                            Some(name)
-                        } else {
-                            None
-                        };
+        if let Some(text) = self.chat.sending_text() {
+            if text.trim().is_empty() {
+                return;
+            }
+            // a command is never sent as a message, if it is invalid the hint shows why
+            if command::is_command(&text) {
+                match command::parse(text.trim()) {
+                    Ok(cmd) => self.run_command(cmd).await,
+                    Err(err) => self.chat.set_command_hint(Some(err)),
+                }
+                return;
+            }
+        }
+        self.send_composer().await
+    }
 
-                        if let Some(name) = local_message {
-                            self.chat.echo_sent_msg(&room_id, name, uuid, message);
-                        }
-                        self.chat.clear_send_msg();
-                        Ok(())
-                    }
+    /// Sends the text of the send box as a message, or as the new version of the message
+    /// being edited.
+    async fn send_composer(&mut self) {
+        if let Some(Draft::Edit(event_id)) = self.chat.draft().cloned() {
+            if let (Some(room_id), Some(text)) =
+                (self.chat.to_current_room_id(), self.chat.sending_text())
+            {
+                let request = UserRequest::EditMessage(room_id, event_id, text);
+                if let Err(e) = self.send_jobs.send(request).await {
+                    self.set_error(e.into());
+                } else {
+                    self.chat.clear_send_msg();
                 }
+            }
+            return;
+        }
+        let members = self.chat.current_room_members().await;
+        let res = if let Some(room_id) = self.chat.to_current_room_id() {
+            match self.chat.get_sending_message(&members) {
+                Ok(msg) => self.send_content(room_id, msg).await,
                 Err(e) => Err(e),
             }
         } else {
@@ -476,12 +920,179 @@ impl AppWidget {
         }
     }
 
+    /// Sends `msg` to the room and echoes it locally until the server responds.
+    async fn send_content(
+        &mut self,
+        room_id: RoomId,
+        msg: MessageEventContent,
+    ) -> Result<(), Error> {
+        self.chat.set_sending_message(true);
+        let uuid = Uuid::new_v4();
+        let message = msg.clone();
+        if let Err(e) = self
+            .send_jobs
+            .send(UserRequest::SendMessage(room_id.clone(), msg, uuid))
+            .await
+        {
+            Err(e.into())
+        } else {
+            self.echo_message(&room_id, uuid, message).await;
+            self.chat.clear_send_msg();
+            Ok(())
+        }
+    }
+
+    /// Shows a message that is waiting in the outbox as pending until the server responds.
+    async fn echo_message(&mut self, room_id: &RoomId, uuid: Uuid, message: MessageEventContent) {
+        // find the room the message was just sent to
+        let local_message = if let Some(room) = self.chat.rooms().get(room_id) {
+            let r = room.read().await;
+            Some(crate::widgets::utils::display_name(
+                &r,
+                self.chat.as_current_user().unwrap(),
+            ))
+        } else {
+            None
+        };
+
+        if let Some(name) = local_message {
+            self.chat.echo_sent_msg(room_id, name, uuid, message);
+        }
+    }
+
+    /// Maps a slash command to the `UserRequest` it stands for and sends it.
+    async fn run_command(&mut self, cmd: Command) {
+        let room_id = self.chat.to_current_room_id();
+        // the command stays in the text box so it can be sent once a room is selected
+        if room_id.is_none() && cmd.needs_room() {
+            self.chat.add_notify("join or select a room first");
+            return;
+        }
+        let request = match cmd {
+            Command::Join(link) => {
+                self.chat.add_notify(&format!("joining {}", link));
+                Some(UserRequest::JoinRoom(link))
+            }
+            Command::Leave => room_id.map(UserRequest::LeaveRoom),
+            Command::Nick(name) => Some(UserRequest::SetDisplayName(name)),
+            Command::Topic(topic) => room_id.map(|id| UserRequest::SetTopic(id, topic)),
+            Command::Invite(user_id) => room_id.map(|id| UserRequest::InviteUser(id, user_id)),
+            Command::Query(user_id) => Some(UserRequest::StartDirectMessage(user_id)),
+            Command::Retry { all } | Command::Discard { all } => {
+                let unsent = if all {
+                    room_id.map(Unsent::Room)
+                } else {
+                    self.chat.unsent_message().map(Unsent::Message)
+                };
+                match unsent {
+                    Some(unsent) if matches!(cmd, Command::Retry { .. }) => {
+                        Some(UserRequest::RetryMessages(unsent))
+                    }
+                    Some(unsent) => Some(UserRequest::DiscardMessages(unsent)),
+                    None => {
+                        self.chat
+                            .add_notify("select an unsent message or add all for the whole room");
+                        return;
+                    }
+                }
+            }
+            Command::Previews { on, everywhere } => {
+                self.chat.set_previews_enabled(on, everywhere);
+                let path = crate::RUMATUI_DIR.as_ref().unwrap().join("previews.json");
+                match self.chat.preview_settings().save(&path).await {
+                    Err(e) => self.set_error(e),
+                    Ok(()) => self.chat.add_notify(&format!(
+                        "URL previews are {} {}",
+                        if on { "on" } else { "off" },
+                        if everywhere {
+                            "everywhere"
+                        } else {
+                            "in this room"
+                        }
+                    )),
+                }
+                None
+            }
+            Command::Settings => {
+                self.settings = Some(SettingsWidget::new(
+                    self.themes.names(),
+                    &self.themes.active.name,
+                ));
+                None
+            }
+            Command::Help => {
+                self.chat.add_notify(&command::help());
+                None
+            }
+            Command::Me(body) => {
+                if let Some(room_id) = room_id {
+                    let emote = MessageEventContent::Emote(EmoteMessageEventContent {
+                        body,
+                        formatted: None,
+                    });
+                    if let Err(e) = self.send_content(room_id, emote).await {
+                        self.set_error(e);
+                    }
+                }
+                return;
+            }
+            Command::Text(text) => {
+                self.chat.set_sending_text(text);
+                self.chat.set_command_hint(None);
+                self.send_composer().await;
+                return;
+            }
+        };
+
+        if let Some(request) = request {
+            let leaving = matches!(request, UserRequest::LeaveRoom(_));
+            let joining = matches!(request, UserRequest::StartDirectMessage(_));
+            if let Err(e) = self.send_jobs.send(request).await {
+                self.set_error(e.into());
+                return;
+            }
+            if leaving {
+                self.chat.set_leaving_room(true);
+            } else if joining {
+                self.chat.set_joining_room(true);
+            }
+        }
+        self.chat.clear_send_msg();
+    }
+
+    /// Refreshes the usage, completions or error shown while typing a slash command.
+    async fn update_command_hint(&mut self) {
+        let hint = match self.chat.sending_text() {
+            Some(text) if command::is_command(&text) => {
+                let members = self.chat.current_room_members().await;
+                command::hint(&text, &members)
+            }
+            _ => None,
+        };
+        self.chat.set_command_hint(hint)
+    }
+
     /// This checks once then continues returns to continue the ui loop.
     pub async fn on_tick(&mut self, event_hndl: &UiEventHandle) {
         if self.login_w.logged_in && !self.sync_started {
             self.sync_started = true;
             self.ev_loop.start_sync();
         }
+        match self.themes.reload_if_changed(Instant::now()) {
+            Some(Ok(())) => self
+                .chat
+                .add_notify(&format!("reloaded the {} theme", self.themes.active.name)),
+            Some(Err(e)) => self.set_warning(e),
+            None => {}
+        }
+        if self.login_w.logged_in {
+            self.chat.refresh_members().await;
+            for url in self.chat.take_wanted_previews() {
+                if let Err(e) = self.send_jobs.send(UserRequest::UrlPreview(url)).await {
+                    self.set_error(e.into());
+                }
+            }
+        }
 
         // this will login, send messages, and any other user initiated requests
         match self.ev_msgs.try_recv() {
@@ -518,6 +1129,13 @@ impl AppWidget {
                         self.chat.set_main_screen(true);
                         self.chat.set_current_user(&resp.user_id);
                         self.chat.set_room_state(rooms).await;
+
+                        if let Err(e) = self.send_jobs.send(UserRequest::DirectRooms).await {
+                            self.set_error(e.into())
+                        }
+                        if let Err(e) = self.send_jobs.send(UserRequest::RoomTags).await {
+                            self.set_error(e.into())
+                        }
                     }
                 },
                 RequestResult::Register(res) => match res {
@@ -618,18 +1236,54 @@ impl AppWidget {
                         // TODO need to impl room search...
                     }
                 },
-                // TODO this has the EventId which we need to keep
-                RequestResult::SendMessage(res) => match res {
-                    Err(e) => self.set_error(e),
-                    Ok(_res) => self.chat.set_sending_message(false),
-                },
-                RequestResult::RoomMsgs(res) => match res {
-                    Err(e) => self.set_error(e),
-                    Ok((res, room)) => {
-                        self.process_room_events(res, room).await;
-                        self.scrolling = false
+                RequestResult::SendMessage(room_id, uuid, state) => match state {
+                    SendState::Sent(event_id) => {
+                        self.chat.set_sending_message(false);
+                        self.chat.set_sent(&room_id, &uuid, event_id);
+                    }
+                    // the message is marked pending until a retry gets through
+                    SendState::Pending => {
+                        self.chat.set_sending_message(false);
+                        self.chat
+                            .set_send_state(&room_id, &uuid, Some(SendState::Pending))
+                    }
+                    SendState::Failed(reason) => {
+                        self.chat.set_sending_message(false);
+                        self.chat.add_notify(&format!(
+                            "a message failed to send: {}, /retry or /discard it",
+                            reason
+                        ));
+                        self.chat
+                            .set_send_state(&room_id, &uuid, Some(SendState::Failed(reason)));
                     }
+                    // only read receipts mark a message seen
+                    SendState::Seen => self.chat.set_sending_message(false),
                 },
+                RequestResult::Unsent(messages) => {
+                    for msg in messages {
+                        self.echo_message(&msg.room_id, msg.uuid, msg.content).await;
+                        if msg.failed {
+                            let failed = SendState::Failed("sending gave up".into());
+                            self.chat
+                                .set_send_state(&msg.room_id, &msg.uuid, Some(failed));
+                        }
+                    }
+                }
+                RequestResult::DiscardMessages(discarded) => {
+                    for (room_id, uuid) in &discarded {
+                        self.chat.remove_messages(room_id, &[*uuid]);
+                    }
+                    self.chat
+                        .add_notify(&format!("discarded {} unsent messages", discarded.len()));
+                }
+                RequestResult::RoomMsgs(res) => {
+                    self.scrolling = false;
+                    let room = self.history_request.take().map(|(_, room)| room);
+                    match res {
+                        Err(e) => self.set_error_with_retry(e, room.map(UserRequest::RoomMsgs)),
+                        Ok((res, room)) => self.process_room_events(res, room).await,
+                    }
+                }
                 RequestResult::AcceptInvite(res) => match res {
                     Err(e) => self.set_error(e),
                     Ok(res) => {
@@ -645,13 +1299,15 @@ impl AppWidget {
                 },
                 RequestResult::DeclineInvite(res, room_id) => {
                     if let Err(e) = res {
-                        self.set_error(e);
+                        let retry = UserRequest::DeclineInvite(room_id.clone());
+                        self.set_error_with_retry(e, Some(retry));
                     }
                     self.chat.remove_room(&room_id)
                 }
                 RequestResult::LeaveRoom(res, room_id) => {
                     if let Err(e) = res {
-                        self.set_error(e);
+                        let retry = UserRequest::LeaveRoom(room_id.clone());
+                        self.set_error_with_retry(e, Some(retry));
                     }
                     self.chat.set_leaving_room(false);
                     self.chat.remove_room(&room_id)
@@ -664,22 +1320,133 @@ impl AppWidget {
                     }
                     Err(e) => self.set_error(e),
                 },
-                RequestResult::Typing(res) => {
+                RequestResult::InviteUser(res, user_id) => match res {
+                    Err(e) => self.set_error(e),
+                    Ok(_) => self
+                        .chat
+                        .add_notify(&format!("invited {} to the room", user_id.localpart())),
+                },
+                RequestResult::SetDisplayName(res) => {
                     if let Err(e) = res {
                         self.set_error(e);
                     }
+                }
+                RequestResult::SetTopic(res) => match res {
+                    Err(e) => self.set_error(e),
+                    Ok(_) => self.chat.add_notify("the room topic has been changed"),
+                },
+                RequestResult::Typing(res) => {
+                    if let Err(e) = res {
+                        self.set_warning(e);
+                    }
                     self.typing_notice = false;
                 }
                 RequestResult::ReadReceipt(res) => {
                     if let Err(e) = res {
-                        self.set_error(e);
+                        self.set_warning(e);
+                    }
+                }
+                RequestResult::RoomSearch(res) => {
+                    self.search_request = None;
+                    match res {
+                        Err(e) => self.set_error(e),
+                        Ok(res) => self.chat.room_search_results(res),
                     }
                 }
-                RequestResult::RoomSearch(res) => match res {
+                RequestResult::DirectRooms(res) => match res {
+                    Err(e) => self.set_error_with_retry(e, Some(UserRequest::DirectRooms)),
+                    Ok(direct) => self.chat.set_direct_rooms(direct).await,
+                },
+                RequestResult::RoomTags(res) => match res {
+                    Err(e) => self.set_error_with_retry(e, Some(UserRequest::RoomTags)),
+                    Ok((tags, failed)) => {
+                        self.chat.set_all_tags(tags);
+                        for (room_id, e) in &failed {
+                            tracing::warn!("fetching the tags of {} failed {}", room_id, e);
+                        }
+                        // the rooms that loaded keep their sections, retrying loads them all
+                        if let Some((_, e)) = failed.into_iter().next() {
+                            self.set_error_with_retry(e, Some(UserRequest::RoomTags));
+                        }
+                    }
+                },
+                RequestResult::TagRoom(res, room_id) => match res {
                     Err(e) => self.set_error(e),
-                    Ok(res) => self.chat.room_search_results(res),
+                    Ok(tags) => self.chat.set_room_tags(&room_id, tags),
+                },
+                // a missing preview is not worth an error in the header
+                RequestResult::UrlPreview(res, url) => match res {
+                    Err(e) => {
+                        tracing::warn!("fetching the preview of {} failed {}", url, e);
+                        self.chat.set_preview(url, None)
+                    }
+                    Ok(preview) => self.chat.set_preview(url, preview),
                 },
+                RequestResult::StartDirectMessage(res, user_id) => {
+                    self.chat.set_joining_room(false);
+                    match res {
+                        Err(e) => {
+                            let retry = UserRequest::StartDirectMessage(user_id);
+                            self.set_error_with_retry(e, Some(retry))
+                        }
+                        Ok((room_id, direct)) => {
+                            self.chat.set_direct_rooms(direct).await;
+                            // an existing direct message room is selected now, a new room is
+                            // selected when our join event arrives
+                            if self.chat.rooms().contains_key(&room_id) {
+                                self.chat.set_current_room_id(&room_id);
+                                self.chat.reset_scroll();
+                            } else {
+                                self.chat.add_notify(&format!(
+                                    "started a direct message with {}",
+                                    user_id.localpart()
+                                ));
+                            }
+                        }
+                    }
+                }
                 // sync error
+                RequestResult::Connection(connection) => {
+                    let was_offline =
+                        self.connection.as_ref().map(Connection::is_online) == Some(false);
+                    if was_offline && connection.is_online() {
+                        self.chat.add_notify("reconnected to the server");
+                    }
+                    self.connection = Some(connection);
+                }
+                RequestResult::Cancelled(id) => {
+                    if self.history_request.as_ref().map(|(req, _)| *req) == Some(id) {
+                        self.history_request = None;
+                        self.scrolling = false;
+                        self.chat.add_notify("stopped loading previous messages");
+                    } else if self.search_request == Some(id) {
+                        self.search_request = None;
+                        self.chat.add_notify("room search cancelled");
+                    }
+                }
+                RequestResult::EditMessage(res, room_id, event_id, body) => match res {
+                    Err(e) => {
+                        let retry = UserRequest::EditMessage(room_id, event_id, body);
+                        self.set_error_with_retry(e, Some(retry))
+                    }
+                    Ok(_) => self.chat.edit_message(&room_id, &event_id, body),
+                },
+                RequestResult::RedactMessage(res, room_id, event_id) => match res {
+                    Err(e) => {
+                        let retry = UserRequest::RedactMessage(room_id, event_id);
+                        self.set_error_with_retry(e, Some(retry))
+                    }
+                    Ok(_) => self.chat.redaction_event(&room_id, &event_id),
+                },
+                RequestResult::Restarted(name) => {
+                    // a login in progress when the loop panicked never finishes
+                    self.login_w.logging_in = false;
+                    self.toasts.push(
+                        Severity::Warning,
+                        format!("the {} loop crashed and was restarted", name),
+                        None,
+                    );
+                }
                 RequestResult::Error(err) => self.set_error(err),
             },
             _ => {}
@@ -699,6 +1466,9 @@ impl AppWidget {
                     let invitation = matches!(membership, MembershipChange::Invited);
 
                     let room_id = room.read().await.room_id.clone();
+                    if self.chat.is_current_room(&room_id) {
+                        self.chat.members_changed();
+                    }
 
                     // only display notifications for the current room
                     if self.chat.is_current_room(&room_id)
@@ -724,6 +1494,7 @@ impl AppWidget {
                     }
                 }
                 StateResult::Name(name, room_id) => self.chat.update_room(&name, &room_id),
+                StateResult::CanonicalAlias(alias, _room_id) => self.chat.add_known_alias(&alias),
                 StateResult::Message(msg, room) => {
                     self.chat.add_message(msg, &room);
                     if let Some(event) = self.chat.read_receipt(self.last_interaction, &room) {
@@ -746,12 +1517,13 @@ impl AppWidget {
                         // TODO what should be done for fully read events
                     }
                 }
-                StateResult::Typing(room_id, msg) => {
+                StateResult::Typing(room_id, typing) => {
                     if self.chat.is_current_room(&room_id) {
-                        self.chat.add_notify(&msg)
+                        self.chat.add_notice(Notice::typing(&typing))
                     }
                 }
                 StateResult::ReadReceipt(room_id, events) => {
+                    self.chat.set_seen(&room_id, &events);
                     let mut notices = vec![];
                     if self.chat.is_current_room(&room_id) {
                         for e_id in self.chat.last_3_msg_event_ids(&room_id) {
@@ -767,10 +1539,11 @@ impl AppWidget {
                                             .map(|dur| dur.as_secs() < 600)
                                             == Some(true)
                                         {
-                                            notices.push(format!(
-                                                "{} has seen the latest messages",
-                                                user.localpart()
-                                            ));
+                                            notices.push(
+                                                Notice::default()
+                                                    .user(user, user.localpart())
+                                                    .text(" has seen the latest messages"),
+                                            );
                                         }
                                     }
                                 }
@@ -778,7 +1551,7 @@ impl AppWidget {
                         }
                     }
                     for notice in notices {
-                        self.chat.add_notify(&notice);
+                        self.chat.add_notice(notice);
                     }
                 }
                 StateResult::Reaction(relates_to, event_id, room_id, msg) => self
@@ -793,12 +1566,115 @@ impl AppWidget {
         }
     }
 
+    /// Cancels loading previous messages and searching the room directory, when neither
+    /// is in progress Esc closes the error history, then dismisses a toast and finally quits.
+    ///
+    /// In vim mode Esc leaves insert mode before anything else and never quits.
+    pub async fn on_esc(&mut self) {
+        if self.vim.as_ref().map(|vim| vim.mode) == Some(Mode::Insert)
+            && self.login_w.logged_in
+            && self.chat.is_main_screen()
+            && !self.chat.has_dialog()
+        {
+            self.set_mode(Mode::Normal);
+            return;
+        }
+        let in_flight = self
+            .history_request
+            .iter()
+            .map(|(id, _)| *id)
+            .chain(self.search_request.iter().copied())
+            .collect::<Vec<_>>();
+        if in_flight.is_empty() {
+            if self.toasts.show_history {
+                self.toasts.show_history = false;
+            } else if self.toasts.dismiss().is_none() && !self.leave_dialog() {
+                if let Some(vim) = self.vim.as_mut() {
+                    vim.set_mode(Mode::Normal);
+                } else {
+                    self.should_quit = true;
+                }
+            }
+            return;
+        }
+        for id in in_flight {
+            if let Err(e) = self.send_jobs.send(UserRequest::Cancel(id)).await {
+                self.set_error(e.into());
+            }
+        }
+    }
+
+    /// Closes the open dialog, cancels a reply or edit or gives focus back to the composer.
+    ///
+    /// Returns false if there was nothing to leave.
+    fn leave_dialog(&mut self) -> bool {
+        if !self.login_w.logged_in || !self.chat.is_main_screen() {
+            return false;
+        }
+        if self.chat.is_quick_select() {
+            self.chat.quit_quick_select_room();
+        } else if self.chat.room_input_kind().is_some() {
+            self.chat.quit_room_input();
+        } else if self.chat.is_room_search() {
+            self.chat.set_room_search(false);
+        } else if let Some(draft) = self.chat.draft().cloned() {
+            self.chat.cancel_draft();
+            // the text of a reply is kept, the text of an edit is the old message
+            if let Draft::Edit(_) = draft {
+                self.chat.set_sending_text(String::new());
+            }
+        } else if self.chat.focus() != Focus::Composer {
+            self.chat.set_focus(Focus::Composer);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Retries the request of the current toast.
+    pub async fn on_ctrl_r(&mut self) {
+        match self.toasts.take_retry() {
+            Some(UserRequest::RoomMsgs(room_id)) => {
+                self.scrolling = true;
+                self.load_history(room_id).await;
+            }
+            Some(request) => {
+                if let Err(e) = self.send_jobs.send(request).await {
+                    self.set_error(e.into());
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Shows or hides the error history.
+    pub async fn on_ctrl_l(&mut self) {
+        self.toasts.show_history = !self.toasts.show_history;
+    }
+
+    /// Loads the previous messages of `room_id`, `scrolling` must already be set.
+    async fn load_history(&mut self, room_id: RoomId) {
+        match self
+            .send_jobs
+            .send(UserRequest::RoomMsgs(room_id.clone()))
+            .await
+        {
+            Ok(id) => self.history_request = Some((id, room_id)),
+            Err(e) => {
+                self.scrolling = false;
+                self.set_error(e.into())
+            }
+        }
+    }
+
+    /// Stops the sync and user request loops and waits for both to finish.
     pub async fn on_quit(&mut self) {
         self.ev_loop.quit_sync();
-        if self.send_jobs.send(UserRequest::Quit).await.is_err() {
-            // TODO what should happen when a send fails
-            return;
-        };
+        // the user request loop is already gone if this fails
+        if let Err(e) = self.send_jobs.send(UserRequest::Quit).await {
+            tracing::warn!("the user request loop stopped before quitting {}", e);
+        }
+        self.ev_loop.shutdown().await;
     }
 
     pub async fn on_notifications(&mut self) {
@@ -836,7 +1712,7 @@ impl AppWidget {
             if let Some(room_id) = self.chat.selected_room_search() {
                 if let Err(err) = self
                     .send_jobs
-                    .send(UserRequest::JoinRoom(room_id))
+                    .send(UserRequest::JoinRoom(room_id.into()))
                     .await
                     .map_err(Into::into)
                 {
@@ -846,6 +1722,89 @@ impl AppWidget {
         }
     }
 
+    /// Open or close the input to start a direct message with a user.
+    pub async fn on_ctrl_n(&mut self) {
+        self.toggle_room_input(RoomInputKind::StartDm)
+    }
+
+    /// Open or close the input to tag or untag the selected room.
+    pub async fn on_ctrl_t(&mut self) {
+        self.toggle_room_input(RoomInputKind::Tag)
+    }
+
+    /// Add or remove the selected room from the favourites.
+    pub async fn on_ctrl_f(&mut self) {
+        if self.chat.is_main_screen() && !self.chat.is_room_search() {
+            if let Some(room_id) = self.chat.to_current_room_id() {
+                self.toggle_tag(room_id, "m.favourite".to_string()).await
+            }
+        }
+    }
+
+    /// Collapse or expand the room list section of the selected row.
+    pub async fn on_ctrl_o(&mut self) {
+        if self.chat.is_main_screen() && !self.chat.is_room_search() {
+            self.chat.toggle_room_section()
+        }
+    }
+
+    fn toggle_room_input(&mut self, kind: RoomInputKind) {
+        if self.chat.is_main_screen() && !self.chat.is_room_search() {
+            if self.chat.room_input_kind() == Some(kind) {
+                self.chat.quit_room_input()
+            } else {
+                self.chat.start_room_input(kind)
+            }
+        }
+    }
+
+    async fn submit_room_input(&mut self, kind: RoomInputKind, text: &str) {
+        match kind {
+            RoomInputKind::StartDm => match UserId::try_from(text) {
+                Ok(user_id) => self.start_direct_message(user_id).await,
+                Err(_) => self.chat.add_notify(&format!(
+                    "\"{}\" is not a valid user id, use @user:server",
+                    text
+                )),
+            },
+            RoomInputKind::Tag => {
+                let tag = match text {
+                    "favourite" | "favorite" => "m.favourite".to_string(),
+                    "lowpriority" | "low priority" => "m.lowpriority".to_string(),
+                    tag if tag.starts_with("m.") || tag.starts_with("u.") => tag.to_string(),
+                    tag => format!("u.{}", tag),
+                };
+                if let Some(room_id) = self.chat.to_current_room_id() {
+                    self.toggle_tag(room_id, tag).await
+                }
+            }
+        }
+    }
+
+    async fn start_direct_message(&mut self, user_id: UserId) {
+        if let Err(e) = self
+            .send_jobs
+            .send(UserRequest::StartDirectMessage(user_id))
+            .await
+        {
+            self.set_error(Error::from(e));
+        } else {
+            self.chat.set_joining_room(true);
+        }
+    }
+
+    /// Tags the room with `tag` or removes the tag if the room is already tagged.
+    async fn toggle_tag(&mut self, room_id: RoomId, tag: String) {
+        let request = if self.chat.room_has_tag(&room_id, &tag) {
+            UserRequest::UntagRoom(room_id, tag)
+        } else {
+            UserRequest::TagRoom(room_id, tag)
+        };
+        if let Err(e) = self.send_jobs.send(request).await {
+            self.set_error(e.into())
+        }
+    }
+
     /// Filter current room list for quick-access
     pub async fn on_ctrl_k(&mut self) {
         let do_something = !self.chat.is_room_search();
@@ -885,53 +1844,29 @@ impl AppWidget {
                             ..
                         } = msg;
 
-                        let name = {
-                            let m = room.read().await;
-                            m.joined_members
-                                .get(&sender)
-                                .map(|m| m.name())
-                                .unwrap_or(sender.localpart().to_string())
-                        };
-
-                        match content {
-                            MessageEventContent::Text(TextMessageEventContent {
-                                body,
-                                formatted,
-                                ..
-                            }) => {
-                                let msg = if formatted
-                                    .as_ref()
-                                    .map(|f| f.body.to_string())
-                                    .unwrap_or(body.to_string())
-                                    != *body
-                                {
-                                    // This is extremely expensive
-                                    // TODO cache these results somehow
-                                    crate::widgets::utils::markdown_to_terminal(&body)
-                                        .unwrap_or(body.clone())
-                                } else {
-                                    body.clone()
-                                };
-                                let txn_id = unsigned
-                                    .transaction_id
-                                    .as_ref()
-                                    .cloned()
-                                    .unwrap_or_default();
-
-                                let msg = Message {
-                                    name,
-                                    user: sender.clone(),
-                                    text: msg,
-                                    event_id: event_id.clone(),
-                                    timestamp: *origin_server_ts,
-                                    uuid: Uuid::parse_str(&txn_id).unwrap_or(Uuid::new_v4()),
-                                    read: false,
-                                    reactions: vec![],
-                                    sent_receipt: false,
-                                };
-                                self.chat.add_message(msg, &room.read().await.room_id)
-                            }
-                            _ => {}
+                        let name =
+                            crate::widgets::utils::display_name(&*room.read().await, &sender);
+
+                        if let Some(msg) = crate::widgets::utils::message_text(&content) {
+                            let txn_id = unsigned
+                                .transaction_id
+                                .as_ref()
+                                .cloned()
+                                .unwrap_or_default();
+
+                            let msg = Message {
+                                name,
+                                user: sender.clone(),
+                                text: msg,
+                                event_id: event_id.clone(),
+                                timestamp: *origin_server_ts,
+                                uuid: Uuid::parse_str(&txn_id).unwrap_or(Uuid::new_v4()),
+                                read: false,
+                                reactions: vec![],
+                                sent_receipt: false,
+                                send_state: None,
+                            };
+                            self.chat.add_message(msg, &room.read().await.room_id)
                         }
                     }
                     _ => {}
@@ -965,13 +1900,12 @@ impl AppWidget {
                     self.chat.set_current_room_id(&room_id);
                     self.chat.add_room(room).await;
                 } else {
-                    self.chat.add_notify(&format!(
-                        "{} joined {}",
-                        // TODO when matrix-sdk gets display_name methods use them where ever possible
-                        // instead of `.localpart()`.
-                        sender.localpart(),
-                        room_name,
-                    ));
+                    let name = crate::widgets::utils::display_name(&*room.read().await, &sender);
+                    self.chat.add_notice(
+                        Notice::default()
+                            .user(&sender, name)
+                            .text(format!(" joined {}", room_name)),
+                    );
                 }
             }
             MembershipChange::Invited => {
@@ -982,11 +1916,12 @@ impl AppWidget {
                         self.chat.invited(sender, room).await;
                     }
                 } else {
-                    self.chat.add_notify(&format!(
-                        "{} was invited to {}",
-                        receiver.localpart(),
-                        room_name
-                    ));
+                    let name = crate::widgets::utils::display_name(&*room.read().await, &receiver);
+                    self.chat.add_notice(
+                        Notice::default()
+                            .user(&receiver, name)
+                            .text(format!(" was invited to {}", room_name)),
+                    );
                 }
             }
             MembershipChange::InvitationRejected => {
@@ -1072,9 +2007,35 @@ impl AppWidget {
         }
     }
 
+    /// Fatal errors replace the UI until a key is pressed, any other error is shown as a toast.
     fn set_error(&mut self, e: Error) {
+        self.report(Severity::Error, e, None)
+    }
+
+    /// An error the user can retry by sending `retry` again.
+    fn set_error_with_retry(&mut self, e: Error, retry: Option<UserRequest>) {
+        self.report(Severity::Error, e, retry)
+    }
+
+    /// A failure the user can safely ignore, like a typing notice that was not sent.
+    fn set_warning(&mut self, e: Error) {
+        self.report(Severity::Warning, e, None)
+    }
+
+    fn report(&mut self, severity: Severity, e: Error, retry: Option<UserRequest>) {
         tracing::warn!("an error occurred {:?}", &e);
-        self.error = Some(e);
+        if e.is_fatal() {
+            self.toasts.push(Severity::Fatal, e.to_string(), None);
+            self.error = Some(e);
+        } else if e.needs_relogin() && self.login_w.logged_in {
+            // the session is gone, back to the login screen
+            self.login_w.logged_in = false;
+            self.login_w.logging_in = false;
+            self.chat.set_main_screen(false);
+            self.toasts.push(severity, e.to_string(), None);
+        } else {
+            self.toasts.push(severity, e.to_string(), retry);
+        }
     }
 }
 
@@ -1084,38 +2045,63 @@ impl DrawWidget for AppWidget {
             let chunks = Layout::default()
                 .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
                 .split(f.size());
+            let theme = &self.themes.active;
 
-            let text = if self.scrolling {
+            let mut text = if let Some(toast) = self.toasts.header_text(Instant::now(), theme) {
+                toast
+            } else if self.scrolling {
                 vec![Text::styled(
-                    "Loading previous messages",
-                    Style::new().fg(Color::Green),
+                    "Loading previous messages, Esc to cancel",
+                    Style::new().fg(theme.notice),
                 )]
             } else if !self.login_w.logged_in {
                 vec![Text::styled(
                     "Login or hit the left or right arrow keys to register!",
-                    Style::new().fg(Color::Green),
+                    Style::new().fg(theme.notice),
+                )]
+            } else if self.search_request.is_some() {
+                vec![Text::styled(
+                    "Searching the room directory, Esc to cancel",
+                    Style::new().fg(theme.notice),
                 )]
             } else if self.chat.is_joining_room() {
-                vec![Text::styled("Joining room", Style::new().fg(Color::Green))]
+                vec![Text::styled("Joining room", Style::new().fg(theme.notice))]
             } else if self.chat.is_leaving_room() {
-                vec![Text::styled("Leaving room", Style::new().fg(Color::Green))]
+                vec![Text::styled("Leaving room", Style::new().fg(theme.notice))]
             } else if self.chat.is_sending_message() {
                 vec![Text::styled(
                     "Sending message",
-                    Style::new().fg(Color::Green),
+                    Style::new().fg(theme.notice),
                 )]
             } else if self.chat.is_main_screen() {
-                vec![Text::styled("Chatting", Style::new().fg(Color::Green))]
+                vec![Text::styled("Chatting", Style::new().fg(theme.notice))]
             } else {
-                vec![Text::styled("", Style::new().fg(Color::Green))]
+                vec![Text::styled("", Style::new().fg(theme.notice))]
             };
+            if let Some(vim) = self.vim.as_ref().filter(|_| self.login_w.logged_in) {
+                text.push(Text::styled(
+                    format!(" | -- {} -- {}", vim.mode.label(), vim.pending()),
+                    Style::new().fg(theme.warning).modifier(Modifier::BOLD),
+                ));
+            }
+            if let Some(connection) = self.connection.as_ref().filter(|_| self.login_w.logged_in) {
+                let style = if connection.is_online() {
+                    Style::new().fg(theme.notice)
+                } else {
+                    Style::new().fg(theme.error).modifier(Modifier::BOLD)
+                };
+                text.push(Text::styled(
+                    format!(" | {}", connection.status(Instant::now())),
+                    style,
+                ));
+            }
             let para = Paragraph::new(text.iter())
                 .block(
                     Block::default()
                         .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
+                        .border_style(theme.border_style(false))
                         .title(&self.title)
-                        .title_style(Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)),
+                        .title_style(theme.title_style()),
                 )
                 .alignment(Alignment::Center);
 
@@ -1126,7 +2112,9 @@ impl DrawWidget for AppWidget {
                 .split(chunks[1]);
 
             if let Some(err) = self.error.as_ref() {
-                ErrorWidget::new(err).render(&mut f, chunks2[0])
+                ErrorWidget::new(err).render(&mut f, chunks2[0], theme)
+            } else if self.toasts.show_history {
+                ErrorHistoryWidget(&self.toasts).render(&mut f, chunks2[0], theme)
             } else if !self.login_w.logged_in {
                 if self.login_w.homeserver.is_none() {
                     let domain = url::Url::parse(&self.homeserver)
@@ -1137,11 +2125,13 @@ impl DrawWidget for AppWidget {
                     self.login_w.homeserver = Some(domain);
                 }
                 match self.login_or_register {
-                    LoginOrRegister::Login => self.login_w.render(&mut f, chunks2[0]),
-                    LoginOrRegister::Register => self.register.render(&mut f, chunks2[0]),
+                    LoginOrRegister::Login => self.login_w.render(&mut f, chunks2[0], theme),
+                    LoginOrRegister::Register => self.register.render(&mut f, chunks2[0], theme),
                 }
+            } else if let Some(settings) = self.settings.as_mut() {
+                settings.render(&mut f, chunks2[0], theme)
             } else {
-                self.chat.render(&mut f, chunks2[0])
+                self.chat.render(&mut f, chunks2[0], theme)
             }
         })
     }
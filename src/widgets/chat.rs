@@ -1,6 +1,6 @@
 use std::{
     cell::{Ref, RefCell},
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     ops::Deref,
     rc::Rc,
     sync::Arc,
@@ -9,8 +9,8 @@ use std::{
 
 use matrix_sdk::{
     api::r0::directory::get_public_rooms_filtered::{self, RoomNetwork},
-    events::room::message::MessageEventContent,
-    identifiers::{EventId, RoomId, UserId},
+    events::{receipt::Receipts, room::message::MessageEventContent},
+    identifiers::{EventId, RoomAliasId, RoomId, UserId},
     Room,
 };
 use rumatui_tui::{
@@ -23,12 +23,23 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::{
+    client::{
+        outbox::SendState,
+        preview::{PreviewSettings, UrlPreview},
+        ruma_ext::account_data::Tags,
+    },
     error::Result,
     widgets::{
-        message::{Message, MessageWidget},
+        colors::NickColors,
+        completion::Sources,
+        focus::{Focus, Move},
+        members::MembersWidget,
+        message::{timeline::TimelineSettings, Draft, Message, MessageWidget, Notice},
         room_search::RoomSearchWidget,
-        rooms::{Invitation, Invite, RoomsWidget},
-        RenderWidget,
+        rooms::{Invitation, Invite, RoomInputKind, RoomsWidget},
+        text_input::Edit,
+        theme::Theme,
+        utils, RenderWidget,
     },
 };
 
@@ -39,11 +50,16 @@ pub struct ChatWidget {
     pub rooms_widget: RoomsWidget,
     messages_widget: MessageWidget,
     room_search_widget: RoomSearchWidget,
+    members_widget: MembersWidget,
+    /// The pane that handles the keyboard when no dialog is open.
+    focus: Focus,
     room_search: bool,
     main_screen: bool,
     sending_message: bool,
     joining_room: bool,
     leaving_room: bool,
+    /// Every room alias seen in room state or room search results, used for tab completion.
+    known_aliases: BTreeSet<String>,
 }
 
 impl ChatWidget {
@@ -80,6 +96,12 @@ impl ChatWidget {
     }
 
     pub(crate) fn room_search_results(&mut self, resp: get_public_rooms_filtered::Response) {
+        for room in &resp.chunk {
+            self.known_aliases
+                .extend(room.canonical_alias.iter().map(ToString::to_string));
+            self.known_aliases
+                .extend(room.aliases.iter().map(ToString::to_string));
+        }
         self.room_search_widget.room_search_results(resp)
     }
 
@@ -105,6 +127,70 @@ impl ChatWidget {
 }
 
 impl ChatWidget {
+    pub(crate) fn focus(&self) -> Focus {
+        self.focus
+    }
+
+    pub(crate) fn set_focus(&mut self, focus: Focus) {
+        self.focus = focus;
+    }
+
+    pub(crate) fn move_focus(&mut self, to: Move) {
+        self.focus = self.focus.moved(to);
+    }
+
+    /// Is a dialog open, dialogs take the keyboard from the focused pane.
+    pub(crate) fn has_dialog(&self) -> bool {
+        self.is_room_search() || self.is_quick_select() || self.room_input_kind().is_some()
+    }
+
+    /// Focus the pane at `x`, `y`.
+    pub(crate) fn focus_on_click(&mut self, x: u16, y: u16) {
+        if self.rooms_widget.contains(x, y) {
+            self.focus = Focus::Rooms;
+        } else if self.messages_widget.contains_timeline(x, y) {
+            self.focus = Focus::Timeline;
+        } else if self.messages_widget.contains_composer(x, y) {
+            self.focus = Focus::Composer;
+        } else if self.members_widget.contains(x, y) {
+            self.focus = Focus::Members;
+        }
+    }
+
+    /// Reloads the member list when the current room or its members changed.
+    pub(crate) async fn refresh_members(&mut self) {
+        if let Some(room) = self.to_current_room_id() {
+            if self.members_widget.needs_refresh(&room) {
+                let members = self.current_room_member_names().await;
+                self.members_widget.set_members(room, members);
+            }
+        }
+    }
+
+    pub(crate) fn members_changed(&mut self) {
+        self.members_widget.stale = true;
+    }
+
+    pub(crate) fn member_select_next(&mut self) {
+        self.members_widget.select_next()
+    }
+
+    pub(crate) fn member_select_previous(&mut self) {
+        self.members_widget.select_previous()
+    }
+
+    pub(crate) fn member_select_first(&mut self) {
+        self.members_widget.select_first()
+    }
+
+    pub(crate) fn member_select_last(&mut self) {
+        self.members_widget.select_last()
+    }
+
+    pub(crate) fn selected_member(&self) -> Option<&UserId> {
+        self.members_widget.selected()
+    }
+
     pub(crate) fn is_main_screen(&self) -> bool {
         self.main_screen
     }
@@ -181,6 +267,46 @@ impl ChatWidget {
         self.rooms_widget.is_quick_select()
     }
 
+    pub(crate) fn start_room_input(&mut self, kind: RoomInputKind) {
+        self.rooms_widget.start_room_input(kind);
+    }
+
+    pub(crate) fn quit_room_input(&mut self) {
+        self.rooms_widget.quit_room_input();
+    }
+
+    pub(crate) fn room_input_kind(&self) -> Option<RoomInputKind> {
+        self.rooms_widget.room_input_kind()
+    }
+
+    /// Returns the kind of input and the typed text when the room input is submitted.
+    pub(crate) fn room_input_add_char(&mut self, ch: char) -> Option<(RoomInputKind, String)> {
+        self.rooms_widget.room_input_add_char(ch)
+    }
+
+    pub(crate) async fn set_direct_rooms(&mut self, direct: BTreeMap<UserId, Vec<RoomId>>) {
+        tracing::info!("setting direct message rooms");
+        self.rooms_widget.set_direct_rooms(direct).await
+    }
+
+    pub(crate) fn set_all_tags(&mut self, tags: HashMap<RoomId, Tags>) {
+        tracing::info!("setting room tags");
+        self.rooms_widget.set_all_tags(tags)
+    }
+
+    pub(crate) fn set_room_tags(&mut self, room: &RoomId, tags: Tags) {
+        tracing::info!("updating tags of {}", &room);
+        self.rooms_widget.set_room_tags(room, tags)
+    }
+
+    pub(crate) fn room_has_tag(&self, room: &RoomId, tag: &str) -> bool {
+        self.rooms_widget.has_tag(room, tag)
+    }
+
+    pub(crate) fn toggle_room_section(&mut self) {
+        self.rooms_widget.toggle_section()
+    }
+
     pub(crate) fn as_current_user(&self) -> Option<&UserId> {
         self.me.as_ref()
     }
@@ -211,6 +337,10 @@ impl ChatWidget {
         self.rooms_widget.on_click(btn, x, y)
     }
 
+    pub(crate) fn room_list_on_click(&mut self, btn: MouseButton, x: u16, y: u16) -> bool {
+        self.rooms_widget.on_click_list(btn, x, y)
+    }
+
     pub(crate) fn room_on_scroll_up(&mut self, x: u16, y: u16) -> bool {
         self.rooms_widget.on_scroll_up(x, y)
     }
@@ -227,6 +357,14 @@ impl ChatWidget {
         self.rooms_widget.select_next()
     }
 
+    pub(crate) fn room_select_first(&mut self) {
+        self.rooms_widget.select_first()
+    }
+
+    pub(crate) fn room_select_last(&mut self) {
+        self.rooms_widget.select_last()
+    }
+
     pub(crate) fn remove_invite(&mut self) {
         self.rooms_widget.remove_invite()
     }
@@ -263,6 +401,90 @@ impl ChatWidget {
         self.messages_widget.reset_scroll()
     }
 
+    pub(crate) fn selected_message(&self) -> Option<&Message> {
+        self.messages_widget.selected_message()
+    }
+
+    pub(crate) fn unsent_message(&self) -> Option<Uuid> {
+        self.messages_widget.unsent_message()
+    }
+
+    pub(crate) fn link_at(&self, x: u16, y: u16) -> Option<&str> {
+        self.messages_widget.link_at(x, y)
+    }
+
+    pub(crate) fn selected_link(&self) -> Option<&str> {
+        self.messages_widget.selected_link()
+    }
+
+    pub(crate) fn selected_event_id(&self) -> Option<&EventId> {
+        self.messages_widget.selected_event_id()
+    }
+
+    /// Returns true if the first loaded message was already selected.
+    pub(crate) fn message_select_previous(&mut self) -> bool {
+        self.messages_widget.select_previous_message()
+    }
+
+    pub(crate) fn message_select_next(&mut self) {
+        self.messages_widget.select_next_message()
+    }
+
+    /// Returns true if the first loaded message was already selected.
+    pub(crate) fn message_page_up(&mut self) -> bool {
+        self.messages_widget.select_page_up()
+    }
+
+    pub(crate) fn message_page_down(&mut self) {
+        self.messages_widget.select_page_down()
+    }
+
+    pub(crate) fn message_select_first(&mut self) {
+        self.messages_widget.select_first_message()
+    }
+
+    /// Clears the selection and jumps to the latest message.
+    pub(crate) fn clear_message_selection(&mut self) {
+        self.messages_widget.clear_selection()
+    }
+
+    pub(crate) fn take_wanted_previews(&mut self) -> Vec<String> {
+        self.messages_widget.take_wanted_previews()
+    }
+
+    pub(crate) fn set_preview(&mut self, url: String, preview: Option<UrlPreview>) {
+        self.messages_widget.set_preview(url, preview)
+    }
+
+    pub(crate) fn set_timeline_settings(&mut self, settings: TimelineSettings) {
+        self.messages_widget.set_timeline_settings(settings)
+    }
+
+    pub(crate) fn preview_settings(&self) -> &PreviewSettings {
+        self.messages_widget.preview_settings()
+    }
+
+    pub(crate) fn set_preview_settings(&mut self, settings: PreviewSettings) {
+        self.messages_widget.set_preview_settings(settings)
+    }
+
+    pub(crate) fn set_previews_enabled(&mut self, on: bool, everywhere: bool) {
+        self.messages_widget.set_previews_enabled(on, everywhere)
+    }
+
+    pub(crate) fn draft(&self) -> Option<&Draft> {
+        self.messages_widget.draft()
+    }
+
+    pub(crate) fn set_draft(&mut self, draft: Draft) {
+        self.messages_widget.set_draft(draft)
+    }
+
+    /// Returns false if there was no reply or edit to cancel.
+    pub(crate) fn cancel_draft(&mut self) -> bool {
+        self.messages_widget.cancel_draft()
+    }
+
     pub(crate) fn add_char(&mut self, ch: char) {
         if self.is_quick_select() {
             self.rooms_widget.quick_select_add_char(ch)
@@ -274,6 +496,8 @@ impl ChatWidget {
     pub(crate) fn remove_char(&mut self) {
         if self.is_quick_select() {
             self.rooms_widget.quick_select_remove_char()
+        } else if self.room_input_kind().is_some() {
+            self.rooms_widget.room_input_remove_char()
         } else {
             self.messages_widget.remove_char()
         }
@@ -283,6 +507,17 @@ impl ChatWidget {
         self.messages_widget.add_notify(msg)
     }
 
+    pub(crate) fn add_notice(&mut self, notice: Notice) {
+        self.messages_widget.add_notice(notice)
+    }
+
+    /// Sets the palette sender names are colored from in the timeline, the member list
+    /// and notifications.
+    pub(crate) fn set_nick_colors(&mut self, colors: NickColors) {
+        self.members_widget.nick_colors = colors.clone();
+        self.messages_widget.nick_colors = colors;
+    }
+
     pub(crate) fn set_reaction_event(
         &mut self,
         room: &RoomId,
@@ -311,6 +546,22 @@ impl ChatWidget {
         self.messages_widget.echo_sent_msg(id, name, uuid, content)
     }
 
+    pub(crate) fn set_send_state(&mut self, room: &RoomId, uuid: &Uuid, state: Option<SendState>) {
+        self.messages_widget.set_send_state(room, uuid, state)
+    }
+
+    pub(crate) fn set_sent(&mut self, room: &RoomId, uuid: &Uuid, event_id: EventId) {
+        self.messages_widget.set_sent(room, uuid, event_id)
+    }
+
+    pub(crate) fn set_seen(&mut self, room: &RoomId, receipts: &BTreeMap<EventId, Receipts>) {
+        self.messages_widget.set_seen(room, receipts)
+    }
+
+    pub(crate) fn remove_messages(&mut self, room: &RoomId, uuids: &[Uuid]) {
+        self.messages_widget.remove_messages(room, uuids)
+    }
+
     pub(crate) fn edit_message(&mut self, room: &RoomId, event: &EventId, new_msg: String) {
         tracing::info!("message edit in {}", &room);
         self.messages_widget.edit_message(room, event, new_msg)
@@ -325,8 +576,84 @@ impl ChatWidget {
         self.messages_widget.clear_send_msg()
     }
 
-    pub(crate) fn get_sending_message(&self) -> Result<MessageEventContent> {
-        self.messages_widget.get_sending_message()
+    pub(crate) fn get_sending_message(
+        &self,
+        members: &[(UserId, String)],
+    ) -> Result<MessageEventContent> {
+        self.messages_widget.get_sending_message(members)
+    }
+
+    pub(crate) fn sending_text(&self) -> Option<String> {
+        self.messages_widget.sending_text()
+    }
+
+    pub(crate) fn set_sending_text(&mut self, text: String) {
+        self.messages_widget.set_sending_text(text)
+    }
+
+    pub(crate) fn insert_sending_text(&mut self, text: &str) {
+        self.messages_widget.insert_str(text)
+    }
+
+    pub(crate) fn edit_sending_text(&mut self, edit: Edit) -> bool {
+        self.messages_widget.edit(edit)
+    }
+
+    pub(crate) fn set_command_hint(&mut self, hint: Option<String>) {
+        self.messages_widget.set_command_hint(hint)
+    }
+
+    pub(crate) fn add_known_alias(&mut self, alias: &RoomAliasId) {
+        self.known_aliases.insert(alias.to_string());
+    }
+
+    /// Tab completes the last word of the send box from the current room's members and
+    /// the known room aliases.
+    pub(crate) async fn complete(&mut self, reverse: bool) -> bool {
+        let members = self.current_room_members().await;
+        let aliases = self.known_aliases.iter().cloned().collect::<Vec<_>>();
+        self.messages_widget.complete(
+            Sources {
+                members: &members,
+                aliases: &aliases,
+            },
+            reverse,
+        )
+    }
+
+    /// The `UserId` and display name of the joined members of the current room.
+    pub(crate) async fn current_room_members(&self) -> Vec<(UserId, String)> {
+        let room = self
+            .to_current_room_id()
+            .and_then(|id| self.rooms().get(&id).map(Arc::clone));
+        if let Some(room) = room {
+            room.read()
+                .await
+                .joined_members
+                .iter()
+                .map(|(id, member)| (id.clone(), member.name()))
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
+    /// The joined members of the current room, a name shared with another member is
+    /// followed by the user id.
+    async fn current_room_member_names(&self) -> Vec<(UserId, String)> {
+        let room = self
+            .to_current_room_id()
+            .and_then(|id| self.rooms().get(&id).map(Arc::clone));
+        if let Some(room) = room {
+            let room = room.read().await;
+            let mut names = utils::member_names(&room);
+            room.joined_members
+                .keys()
+                .filter_map(|id| names.remove_entry(id))
+                .collect()
+        } else {
+            vec![]
+        }
     }
 
     /// `check_unread` is used when the user is active in a room, we check for any messages
@@ -355,21 +682,41 @@ impl ChatWidget {
 }
 
 impl RenderWidget for ChatWidget {
-    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect)
+    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect, theme: &Theme)
     where
         B: Backend,
     {
-        let chunks = Layout::default()
-            .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
-            .direction(Direction::Horizontal)
-            .split(area);
-
-        self.rooms_widget.render(f, chunks[0]);
+        // while a dialog is open no pane has focus
+        let focus = Some(self.focus).filter(|_| !self.has_dialog());
+        self.rooms_widget.focused = focus == Some(Focus::Rooms);
+        self.messages_widget.timeline_focused = focus == Some(Focus::Timeline);
+        self.messages_widget.composer_focused = focus == Some(Focus::Composer);
+        self.members_widget.focused = focus == Some(Focus::Members);
 
         if self.is_room_search() {
-            self.room_search_widget.render(f, chunks[1]);
+            let chunks = Layout::default()
+                .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
+                .direction(Direction::Horizontal)
+                .split(area);
+
+            self.rooms_widget.render(f, chunks[0], theme);
+            self.room_search_widget.render(f, chunks[1], theme);
         } else {
-            self.messages_widget.render(f, chunks[1]);
+            let chunks = Layout::default()
+                .constraints(
+                    [
+                        Constraint::Percentage(20),
+                        Constraint::Percentage(60),
+                        Constraint::Percentage(20),
+                    ]
+                    .as_ref(),
+                )
+                .direction(Direction::Horizontal)
+                .split(area);
+
+            self.rooms_widget.render(f, chunks[0], theme);
+            self.messages_widget.render(f, chunks[1], theme);
+            self.members_widget.render(f, chunks[2], theme);
         }
     }
 }
@@ -0,0 +1,163 @@
+//! Nickname colors, each user is drawn in a color picked by a hash of their `UserId`.
+//!
+//! The palette can be replaced in `~/.rumatui/colors.json`
+//!
+//! ```json
+//! { "nicks": ["#d75f5f", "green", "33"] }
+//! ```
+//!
+//! a color is a name, a `#rrggbb` hex color or an index into the 256 color palette.
+
+use std::{fs, path::Path};
+
+use matrix_sdk::identifiers::UserId;
+use rumatui_tui::style::Color;
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+use crate::error::Result;
+
+/// Parses a color name, `#rrggbb` or a 256 color palette index.
+pub fn parse_color(color: &str) -> Option<Color> {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |idx: usize| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(idx) = color.parse::<u8>() {
+        return Some(Color::Indexed(idx));
+    }
+    let name = color.to_lowercase().replace(|c| c == '_' || c == '-', "");
+    let color = match name.as_str() {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// The closest color of the 256 color palette's color cube to a `#rrggbb` color, other
+/// colors are left alone.
+pub fn to_indexed(color: Color) -> Color {
+    // the cube's levels are 0, 95, 135, 175, 215 and 255
+    let level = |c: u8| match c {
+        0..=47 => 0,
+        48..=114 => 1,
+        c => (c - 35) / 40,
+    };
+    match color {
+        Color::Rgb(r, g, b) => Color::Indexed(16 + 36 * level(r) + 6 * level(g) + level(b)),
+        color => color,
+    }
+}
+
+/// Deserializes a list of colors written as `parse_color` reads them.
+pub fn deserialize_colors<'de, D>(deserializer: D) -> std::result::Result<Vec<Color>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|color| {
+            parse_color(color).ok_or_else(|| D::Error::custom(format!("unknown color {}", color)))
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct NickColors {
+    #[serde(rename = "nicks", deserialize_with = "deserialize_colors")]
+    palette: Vec<Color>,
+}
+
+impl Default for NickColors {
+    /// Mid tones from the 256 color palette, readable on dark and light backgrounds.
+    fn default() -> Self {
+        Self {
+            palette: [167, 71, 68, 133, 172, 37, 168, 100, 62, 30]
+                .iter()
+                .map(|idx| Color::Indexed(*idx))
+                .collect(),
+        }
+    }
+}
+
+impl NickColors {
+    /// Loads the palette, a missing file means the default palette.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = fs::read_to_string(path)?;
+        let colors: Self = serde_json::from_str(&json)?;
+        if colors.palette.is_empty() {
+            Ok(Self::default())
+        } else {
+            Ok(colors)
+        }
+    }
+
+    /// Replaces `#rrggbb` colors with the closest of the 256 colors.
+    pub fn without_truecolor(mut self) -> Self {
+        for color in self.palette.iter_mut() {
+            *color = to_indexed(*color);
+        }
+        self
+    }
+
+    /// The color of `user`, the same on every run and every machine.
+    pub fn color(&self, user: &UserId) -> Color {
+        // FNV-1a, `std`'s hashers are not guaranteed to stay the same between releases
+        let hash = user
+            .to_string()
+            .bytes()
+            .fold(0x811c_9dc5_u32, |hash, byte| {
+                (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+            });
+        self.palette
+            .get(hash as usize % self.palette.len().max(1))
+            .copied()
+            .unwrap_or(Color::Magenta)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#d75f5f"), Some(Color::Rgb(0xd7, 0x5f, 0x5f)));
+        assert_eq!(parse_color("33"), Some(Color::Indexed(33)));
+        assert_eq!(parse_color("Light_Blue"), Some(Color::LightBlue));
+        assert_eq!(parse_color("#d75f"), None);
+        assert_eq!(parse_color("mauve"), None);
+
+        let alice = UserId::try_from("@alice:example.org").unwrap();
+        let colors = NickColors::default();
+        assert_eq!(colors.color(&alice), colors.color(&alice.clone()));
+
+        let colors: NickColors = serde_json::from_str(r#"{ "nicks": ["red"] }"#).unwrap();
+        assert_eq!(colors.color(&alice), Color::Red);
+        assert!(serde_json::from_str::<NickColors>(r#"{ "nicks": ["mauve"] }"#).is_err());
+    }
+}
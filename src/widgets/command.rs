@@ -0,0 +1,366 @@
+//! Slash commands typed into the message composer.
+
+use std::convert::TryFrom;
+
+use matrix_sdk::identifiers::UserId;
+
+use crate::client::room_link::RoomLink;
+
+/// A parsed slash command.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// `/join #alias:server`, `/join !room:server?via=server` or a matrix.to/`matrix:` link
+    Join(RoomLink),
+    /// `/leave` or `/part`
+    Leave,
+    /// `/me waves`
+    Me(String),
+    /// `/nick new display name`
+    Nick(String),
+    /// `/topic new room topic`
+    Topic(String),
+    /// `/invite @user:server`
+    Invite(UserId),
+    /// `/query @user:server`
+    Query(UserId),
+    /// `/retry` the selected or last failed message, `/retry all` the whole room
+    Retry { all: bool },
+    /// `/discard` the selected or last failed message, `/discard all` the whole room
+    Discard { all: bool },
+    /// `/previews on|off [all]` turns URL previews on or off in the room or everywhere
+    Previews { on: bool, everywhere: bool },
+    /// `/settings` opens the settings screen
+    Settings,
+    /// `/help`
+    Help,
+    /// `//text` escapes the slash and sends `/text` as a message.
+    Text(String),
+}
+
+impl Command {
+    /// Commands that act on the current room and do nothing without one.
+    pub fn needs_room(&self) -> bool {
+        match self {
+            Command::Leave
+            | Command::Me(_)
+            | Command::Topic(_)
+            | Command::Invite(_)
+            | Command::Retry { .. }
+            | Command::Discard { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// The name, arguments and description of a command.
+#[derive(Clone, Copy, Debug)]
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub args: &'static str,
+    pub help: &'static str,
+}
+
+impl CommandInfo {
+    pub fn usage(&self) -> String {
+        if self.args.is_empty() {
+            format!("/{}", self.name)
+        } else {
+            format!("/{} {}", self.name, self.args)
+        }
+    }
+}
+
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "join",
+        args: "<#alias:server|!room:server|link>",
+        help: "join a room",
+    },
+    CommandInfo {
+        name: "leave",
+        args: "",
+        help: "leave the current room",
+    },
+    CommandInfo {
+        name: "part",
+        args: "",
+        help: "leave the current room",
+    },
+    CommandInfo {
+        name: "me",
+        args: "<message>",
+        help: "send an emote",
+    },
+    CommandInfo {
+        name: "nick",
+        args: "<display name>",
+        help: "set your display name",
+    },
+    CommandInfo {
+        name: "topic",
+        args: "<topic>",
+        help: "set the topic of the current room",
+    },
+    CommandInfo {
+        name: "invite",
+        args: "<@user:server>",
+        help: "invite a user to the current room",
+    },
+    CommandInfo {
+        name: "query",
+        args: "<@user:server>",
+        help: "start a direct message with a user",
+    },
+    CommandInfo {
+        name: "retry",
+        args: "[all]",
+        help: "retry sending the selected or last failed message, or every unsent message with all",
+    },
+    CommandInfo {
+        name: "discard",
+        args: "[all]",
+        help: "discard the selected or last failed message, or every unsent message with all",
+    },
+    CommandInfo {
+        name: "previews",
+        args: "<on|off> [all]",
+        help: "turn URL previews on or off in the current room, or everywhere with all",
+    },
+    CommandInfo {
+        name: "settings",
+        args: "",
+        help: "pick and preview a theme",
+    },
+    CommandInfo {
+        name: "help",
+        args: "",
+        help: "list all commands",
+    },
+];
+
+/// Is the input a command, `//` escapes the slash so it is sent as a message.
+pub fn is_command(input: &str) -> bool {
+    input.starts_with('/')
+}
+
+/// Splits the input into the command name and the rest of the input.
+fn split(input: &str) -> (&str, Option<&str>) {
+    let input = input.trim_start_matches('/');
+    match input.find(char::is_whitespace) {
+        Some(idx) => (&input[..idx], Some(input[idx..].trim())),
+        None => (input, None),
+    }
+}
+
+/// Find the command named `name`.
+pub fn find(name: &str) -> Option<&'static CommandInfo> {
+    COMMANDS.iter().find(|cmd| cmd.name == name)
+}
+
+/// All commands that start with `prefix`.
+pub fn matching(prefix: &str) -> impl Iterator<Item = &'static CommandInfo> + '_ {
+    COMMANDS
+        .iter()
+        .filter(move |cmd| cmd.name.starts_with(prefix))
+}
+
+/// Parses the composer input into a `Command`.
+///
+/// The error is a message meant to be shown to the user.
+pub fn parse(input: &str) -> Result<Command, String> {
+    if input.starts_with("//") {
+        return Ok(Command::Text(input[1..].to_string()));
+    }
+
+    let (name, args) = split(input);
+    let args = args.unwrap_or_default();
+    let info = find(name).ok_or(format!("unknown command /{}, try /help", name))?;
+    let usage = || format!("usage: {}", info.usage());
+
+    match info.name {
+        "join" if !args.is_empty() => RoomLink::parse(args).map(Command::Join),
+        "leave" | "part" => Ok(Command::Leave),
+        "me" if !args.is_empty() => Ok(Command::Me(args.to_string())),
+        "nick" if !args.is_empty() => Ok(Command::Nick(args.to_string())),
+        "topic" if !args.is_empty() => Ok(Command::Topic(args.to_string())),
+        "invite" | "query" if !args.is_empty() => {
+            let user = UserId::try_from(args)
+                .map_err(|_| format!("\"{}\" is not a valid user id, use @user:server", args))?;
+            if info.name == "invite" {
+                Ok(Command::Invite(user))
+            } else {
+                Ok(Command::Query(user))
+            }
+        }
+        "retry" | "discard" => {
+            let all = match args {
+                "" => false,
+                "all" => true,
+                _ => return Err(usage()),
+            };
+            if info.name == "retry" {
+                Ok(Command::Retry { all })
+            } else {
+                Ok(Command::Discard { all })
+            }
+        }
+        "previews" => {
+            let mut words = args.split_whitespace();
+            let on = match words.next() {
+                Some("on") => true,
+                Some("off") => false,
+                _ => return Err(usage()),
+            };
+            match (words.next(), words.next()) {
+                (None, _) => Ok(Command::Previews {
+                    on,
+                    everywhere: false,
+                }),
+                (Some("all"), None) => Ok(Command::Previews {
+                    on,
+                    everywhere: true,
+                }),
+                _ => Err(usage()),
+            }
+        }
+        "settings" => Ok(Command::Settings),
+        "help" => Ok(Command::Help),
+        _ => Err(usage()),
+    }
+}
+
+/// The users that can complete the argument of `/invite` and `/query`.
+fn matching_users<'u>(prefix: &str, users: &'u [(UserId, String)]) -> Vec<&'u UserId> {
+    let prefix = prefix.trim_start_matches('@');
+    users
+        .iter()
+        .map(|(user, _)| user)
+        .filter(|user| user.as_str()[1..].starts_with(prefix))
+        .collect()
+}
+
+/// A hint shown while typing a command, either the matching commands, the
+/// usage and argument candidates of the command or why the command is invalid.
+///
+/// # Arguments
+///
+/// * input - The text of the composer.
+///
+/// * users - The `UserId` and display name of the users that can complete a user id argument.
+pub fn hint(input: &str, users: &[(UserId, String)]) -> Option<String> {
+    if !is_command(input) || input.starts_with("//") {
+        return None;
+    }
+
+    let (name, args) = split(input);
+    match args {
+        // still typing the command name
+        None => {
+            let cmds = matching(name).map(CommandInfo::usage).collect::<Vec<_>>();
+            if cmds.is_empty() {
+                Some(format!("unknown command /{}, try /help", name))
+            } else {
+                Some(cmds.join("  "))
+            }
+        }
+        Some(args) => {
+            let info = match find(name) {
+                Some(info) => info,
+                None => return Some(format!("unknown command /{}, try /help", name)),
+            };
+            if (info.name == "invite" || info.name == "query") && !args.is_empty() {
+                let candidates = matching_users(args, users);
+                if !candidates.is_empty() && parse(input).is_err() {
+                    return Some(
+                        candidates
+                            .iter()
+                            .take(5)
+                            .map(|u| u.as_str())
+                            .collect::<Vec<_>>()
+                            .join("  "),
+                    );
+                }
+            }
+            if args.is_empty() {
+                Some(format!("{} - {}", info.usage(), info.help))
+            } else {
+                match parse(input) {
+                    Ok(_) => Some(format!("{} - {}", info.usage(), info.help)),
+                    Err(err) => Some(err),
+                }
+            }
+        }
+    }
+}
+
+/// The help text listing every command.
+pub fn help() -> String {
+    COMMANDS
+        .iter()
+        .map(|cmd| format!("{} {}", cmd.usage(), cmd.help))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse("/leave"), Ok(Command::Leave));
+        assert_eq!(parse("/part"), Ok(Command::Leave));
+        assert_eq!(parse("/me waves"), Ok(Command::Me("waves".into())));
+        assert_eq!(
+            parse("/topic all about   rust"),
+            Ok(Command::Topic("all about   rust".into()))
+        );
+        assert_eq!(
+            parse("/join #rumatui:matrix.org"),
+            Ok(Command::Join(
+                RoomLink::parse("#rumatui:matrix.org").unwrap()
+            ))
+        );
+        assert_eq!(
+            parse("/query @devin:matrix.org"),
+            Ok(Command::Query(
+                UserId::try_from("@devin:matrix.org").unwrap()
+            ))
+        );
+        assert_eq!(parse("//shrug"), Ok(Command::Text("/shrug".into())));
+        assert_eq!(
+            parse("/previews off all"),
+            Ok(Command::Previews {
+                on: false,
+                everywhere: true
+            })
+        );
+        assert_eq!(parse("/settings"), Ok(Command::Settings));
+        assert_eq!(parse("/retry"), Ok(Command::Retry { all: false }));
+        assert_eq!(parse("/discard all"), Ok(Command::Discard { all: true }));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("/foo bar")
+            .unwrap_err()
+            .contains("unknown command /foo"));
+        assert_eq!(
+            parse("/join"),
+            Err("usage: /join <#alias:server|!room:server|link>".into())
+        );
+        assert!(parse("/invite devin").is_err());
+        assert!(parse("/me").is_err());
+        assert!(parse("/previews maybe").is_err());
+        assert!(parse("/previews on some").is_err());
+        assert!(parse("/retry some").is_err());
+    }
+
+    #[test]
+    fn room_commands() {
+        assert!(Command::Leave.needs_room());
+        assert!(Command::Topic("rust".into()).needs_room());
+        assert!(!Command::Nick("devin".into()).needs_room());
+        assert!(!Command::Help.needs_room());
+    }
+}
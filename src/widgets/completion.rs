@@ -0,0 +1,317 @@
+//! Tab completion for the send box.
+//!
+//! The word before the end of the text is completed from the members of the
+//! room, known room aliases, emoji shortcodes or slash command names.
+
+use matrix_sdk::identifiers::UserId;
+
+use crate::widgets::command;
+
+/// Emoji shortcodes and the emoji they complete to.
+const EMOJI: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("angry", "😠"),
+    ("clap", "👏"),
+    ("confused", "😕"),
+    ("cry", "😢"),
+    ("eyes", "👀"),
+    ("fire", "🔥"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("hugs", "🤗"),
+    ("joy", "😂"),
+    ("kiss", "😘"),
+    ("laughing", "😆"),
+    ("neutral_face", "😐"),
+    ("ok_hand", "👌"),
+    ("party", "🥳"),
+    ("pray", "🙏"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("scream", "😱"),
+    ("shrug", "🤷"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smirk", "😏"),
+    ("sob", "😭"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("sunglasses", "😎"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tired_face", "😫"),
+    ("upside_down_face", "🙃"),
+    ("wave", "👋"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("x", "❌"),
+    ("yum", "😋"),
+    ("zzz", "💤"),
+];
+
+/// Where the candidates of a `Completion` come from.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sources<'a> {
+    /// The `UserId` and display name of the members of the room.
+    pub members: &'a [(UserId, String)],
+    /// The room aliases the client knows about.
+    pub aliases: &'a [String],
+}
+
+/// A possible completion of a word.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    /// What is shown in the candidate list.
+    pub display: String,
+    /// What the word is replaced with.
+    pub replacement: String,
+}
+
+/// The candidates for the word being completed and which is selected.
+#[derive(Clone, Debug)]
+pub struct Completion {
+    /// The text before the word being completed.
+    prefix: String,
+    candidates: Vec<Candidate>,
+    selected: usize,
+}
+
+impl Completion {
+    /// Finds the candidates for the last word in `text`, `None` if there are none.
+    pub fn new(text: &str, sources: Sources<'_>) -> Option<Self> {
+        let start = text
+            .rfind(char::is_whitespace)
+            .map(|idx| idx + text[idx..].chars().next().map_or(1, char::len_utf8))
+            .unwrap_or(0);
+        let word = &text[start..];
+        if word.is_empty() {
+            return None;
+        }
+
+        // the argument of `/invite` and `/query` has to be a user id
+        let user_arg = start > 0
+            && matches!(
+                text.split_whitespace().next(),
+                Some("/invite") | Some("/query")
+            );
+        let mut candidates = candidates(word, start == 0, user_arg, sources);
+        candidates.dedup_by(|a, b| a.replacement == b.replacement);
+        if candidates.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            prefix: text[..start].to_string(),
+            candidates,
+            selected: 0,
+        })
+    }
+
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.candidates.len();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self
+            .selected
+            .checked_sub(1)
+            .unwrap_or(self.candidates.len() - 1);
+    }
+
+    /// Does this completion still apply to `text`.
+    pub fn applies_to(&self, text: &str) -> bool {
+        text.starts_with(&self.prefix)
+    }
+
+    /// The text with the word replaced by the selected candidate.
+    pub fn apply(&self) -> String {
+        format!(
+            "{}{}",
+            self.prefix, self.candidates[self.selected].replacement
+        )
+    }
+}
+
+fn candidates(
+    word: &str,
+    first_word: bool,
+    user_arg: bool,
+    sources: Sources<'_>,
+) -> Vec<Candidate> {
+    let lower = word.to_lowercase();
+    if first_word && word.starts_with('/') && !word.starts_with("//") {
+        command::matching(&word[1..])
+            .map(|cmd| Candidate {
+                display: cmd.usage(),
+                replacement: format!("/{} ", cmd.name),
+            })
+            .collect()
+    } else if word.starts_with('@') {
+        let mut users = sources
+            .members
+            .iter()
+            .filter(|(id, name)| {
+                id.as_str().to_lowercase().starts_with(&lower)
+                    || name.to_lowercase().starts_with(&lower[1..])
+            })
+            .map(|(id, name)| Candidate {
+                display: format!("{} ({})", name, id),
+                replacement: format!("{} ", id),
+            })
+            .collect::<Vec<_>>();
+        users.sort_by(|a, b| a.display.cmp(&b.display));
+        users
+    } else if word.starts_with('#') {
+        let mut aliases = sources
+            .aliases
+            .iter()
+            .filter(|alias| alias.to_lowercase().starts_with(&lower))
+            .map(|alias| Candidate {
+                display: alias.clone(),
+                replacement: format!("{} ", alias),
+            })
+            .collect::<Vec<_>>();
+        aliases.sort_by(|a, b| a.display.cmp(&b.display));
+        aliases
+    } else if word.starts_with(':') && word.len() > 2 {
+        let code = lower[1..].trim_end_matches(':');
+        EMOJI
+            .iter()
+            .filter(|(name, _)| name.starts_with(code))
+            .map(|(name, emoji)| Candidate {
+                display: format!(":{}: {}", name, emoji),
+                replacement: emoji.to_string(),
+            })
+            .collect()
+    } else {
+        // a name at the start of the message addresses that user
+        let suffix = if first_word { ": " } else { " " };
+        let mut users = sources
+            .members
+            .iter()
+            .filter(|(id, name)| {
+                name.to_lowercase().starts_with(&lower) || id.localpart().starts_with(&lower)
+            })
+            .map(|(id, name)| Candidate {
+                display: format!("{} ({})", name, id),
+                replacement: if user_arg {
+                    format!("{} ", id)
+                } else {
+                    format!("{}{}", name, suffix)
+                },
+            })
+            .collect::<Vec<_>>();
+        users.sort_by(|a, b| a.display.cmp(&b.display));
+        users
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    fn members() -> Vec<(UserId, String)> {
+        vec![
+            (
+                UserId::try_from("@devin:matrix.org").unwrap(),
+                "Devin".into(),
+            ),
+            (UserId::try_from("@dave:matrix.org").unwrap(), "Dave".into()),
+            (
+                UserId::try_from("@example:example.org").unwrap(),
+                "ex".into(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn complete_users() {
+        let members = members();
+        let sources = Sources {
+            members: &members,
+            aliases: &[],
+        };
+
+        let mut comp = Completion::new("hi @d", sources).unwrap();
+        assert_eq!(comp.candidates().len(), 2);
+        assert_eq!(comp.apply(), "hi @dave:matrix.org ");
+        comp.select_next();
+        assert_eq!(comp.apply(), "hi @devin:matrix.org ");
+        comp.select_next();
+        assert_eq!(comp.apply(), "hi @dave:matrix.org ");
+        comp.select_previous();
+        assert_eq!(comp.apply(), "hi @devin:matrix.org ");
+
+        let comp = Completion::new("ex", sources).unwrap();
+        assert_eq!(comp.apply(), "ex: ");
+        let comp = Completion::new("thanks dev", sources).unwrap();
+        assert_eq!(comp.apply(), "thanks Devin ");
+
+        assert!(Completion::new("hi @nobody", sources).is_none());
+        assert!(Completion::new("hi ", sources).is_none());
+    }
+
+    #[test]
+    fn complete_aliases_and_emoji() {
+        let aliases = vec!["#rumatui:matrix.org".to_string(), "#rust:matrix.org".into()];
+        let sources = Sources {
+            members: &[],
+            aliases: &aliases,
+        };
+
+        let comp = Completion::new("join #rum", sources).unwrap();
+        assert_eq!(comp.apply(), "join #rumatui:matrix.org ");
+        assert_eq!(
+            Completion::new("#ru", sources).unwrap().candidates().len(),
+            2
+        );
+
+        let comp = Completion::new("nice :tad", sources).unwrap();
+        assert_eq!(comp.apply(), "nice 🎉");
+        assert_eq!(Completion::new(":tada:", sources).unwrap().apply(), "🎉");
+        // a single character after `:` is too short to complete
+        assert!(Completion::new("a :t", sources).is_none());
+    }
+
+    #[test]
+    fn complete_commands() {
+        let members = members();
+        let sources = Sources {
+            members: &members,
+            aliases: &[],
+        };
+
+        assert_eq!(Completion::new("/jo", sources).unwrap().apply(), "/join ");
+        assert_eq!(
+            Completion::new("/invite @dev", sources).unwrap().apply(),
+            "/invite @devin:matrix.org "
+        );
+        assert_eq!(
+            Completion::new("/invite dev", sources).unwrap().apply(),
+            "/invite @devin:matrix.org "
+        );
+        assert_eq!(
+            Completion::new("/query ex", sources).unwrap().apply(),
+            "/query @example:example.org "
+        );
+        // commands are only completed at the start of the message
+        assert!(Completion::new("hi /jo", sources).is_none());
+    }
+}
@@ -1,12 +1,15 @@
 use rumatui_tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::Style,
     widgets::{Block, Borders, Paragraph, Text},
     Frame,
 };
 
-use crate::{error::Error, widgets::RenderWidget};
+use crate::{
+    error::Error,
+    widgets::{theme::Theme, RenderWidget},
+};
 
 #[derive(Debug)]
 pub struct ErrorWidget<'e>(pub &'e Error);
@@ -18,7 +21,7 @@ impl<'e> ErrorWidget<'e> {
 }
 
 impl<'e> RenderWidget for ErrorWidget<'e> {
-    fn render<B>(&mut self, f: &mut Frame<B>, _area: Rect)
+    fn render<B>(&mut self, f: &mut Frame<B>, _area: Rect, theme: &Theme)
     where
         B: Backend,
     {
@@ -48,14 +51,14 @@ impl<'e> RenderWidget for ErrorWidget<'e> {
 
         let txt = [Text::styled(
             self.0.to_string(),
-            Style::default().fg(Color::Red),
+            Style::default().fg(theme.error),
         )];
         let p = Paragraph::new(txt.iter())
             .block(
                 Block::default()
                     .title("Error")
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Red)),
+                    .border_style(Style::default().fg(theme.error)),
             )
             .wrap(true);
         f.render_widget(p, chunks[1])
@@ -0,0 +1,101 @@
+//! Which pane of the main screen handles the keyboard.
+//!
+//! The room list is on the left, the timeline is above the composer in the middle and
+//! the member list is on the right. Dialogs, like quick select or the room search, take
+//! the keyboard while they are open no matter which pane has focus.
+
+use termion::event::Key;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Focus {
+    Rooms,
+    Timeline,
+    Composer,
+    Members,
+}
+
+impl Default for Focus {
+    fn default() -> Self {
+        Self::Composer
+    }
+}
+
+/// Where to move focus to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Move {
+    Left,
+    Right,
+    Up,
+    Down,
+    Next,
+    Previous,
+}
+
+impl Focus {
+    /// The order Tab and Shift-Tab move through the panes.
+    const ORDER: [Focus; 4] = [Self::Rooms, Self::Timeline, Self::Composer, Self::Members];
+
+    /// The pane focus moves to from this one.
+    pub fn moved(self, to: Move) -> Self {
+        let idx = Self::ORDER
+            .iter()
+            .position(|f| *f == self)
+            .unwrap_or_default();
+        match (self, to) {
+            (_, Move::Next) => Self::ORDER[(idx + 1) % Self::ORDER.len()],
+            (_, Move::Previous) => Self::ORDER[(idx + Self::ORDER.len() - 1) % Self::ORDER.len()],
+            (Self::Timeline, Move::Left) | (Self::Composer, Move::Left) => Self::Rooms,
+            (Self::Members, Move::Left) | (Self::Rooms, Move::Right) => Self::Composer,
+            (Self::Timeline, Move::Right) | (Self::Composer, Move::Right) => Self::Members,
+            (Self::Composer, Move::Up) => Self::Timeline,
+            (Self::Timeline, Move::Down) => Self::Composer,
+            (focus, _) => focus,
+        }
+    }
+}
+
+/// Alt-h/j/k/l move focus, Ctrl-arrows move focus as well when the terminal sends them.
+pub fn movement(key: Key) -> Option<Move> {
+    match key {
+        Key::Alt('h') => Some(Move::Left),
+        Key::Alt('j') => Some(Move::Down),
+        Key::Alt('k') => Some(Move::Up),
+        Key::Alt('l') => Some(Move::Right),
+        _ => None,
+    }
+}
+
+/// termion does not parse Ctrl-arrows, they arrive as unsupported `\x1b[1;5A` style
+/// escape sequences.
+pub fn ctrl_arrow(bytes: &[u8]) -> Option<Move> {
+    match bytes {
+        b"\x1b[1;5A" => Some(Move::Up),
+        b"\x1b[1;5B" => Some(Move::Down),
+        b"\x1b[1;5C" => Some(Move::Right),
+        b"\x1b[1;5D" => Some(Move::Left),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn moves() {
+        assert_eq!(Focus::Composer.moved(Move::Up), Focus::Timeline);
+        assert_eq!(Focus::Timeline.moved(Move::Left), Focus::Rooms);
+        assert_eq!(Focus::Rooms.moved(Move::Right), Focus::Composer);
+        assert_eq!(Focus::Composer.moved(Move::Right), Focus::Members);
+        // there is nothing left of the room list
+        assert_eq!(Focus::Rooms.moved(Move::Left), Focus::Rooms);
+
+        assert_eq!(Focus::Members.moved(Move::Next), Focus::Rooms);
+        assert_eq!(Focus::Rooms.moved(Move::Previous), Focus::Members);
+
+        assert_eq!(ctrl_arrow(b"\x1b[1;5D"), Some(Move::Left));
+        assert_eq!(ctrl_arrow(b"\x1b[D"), None);
+        assert_eq!(movement(Key::Alt('k')), Some(Move::Up));
+        assert_eq!(movement(Key::Char('k')), None);
+    }
+}
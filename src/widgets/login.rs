@@ -1,13 +1,13 @@
 use rumatui_tui::{
     backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     widgets::{Block, Borders, Paragraph, Text},
     Frame,
 };
 use termion::event::MouseButton;
 
-use crate::widgets::RenderWidget;
+use crate::widgets::{theme::Theme, RenderWidget};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Loading {
@@ -96,7 +96,7 @@ impl LoginWidget {
 }
 
 impl RenderWidget for LoginWidget {
-    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect)
+    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect, theme: &Theme)
     where
         B: Backend,
     {
@@ -116,7 +116,7 @@ impl RenderWidget for LoginWidget {
         let login = &format!("Log in to {}", server);
         let blk = Block::default()
             .title(login)
-            .title_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
+            .title_style(Style::default().fg(theme.border).modifier(Modifier::BOLD))
             .borders(Borders::ALL);
         f.render_widget(blk, chunks[1]);
 
@@ -149,12 +149,12 @@ impl RenderWidget for LoginWidget {
             self.waiting.tick(width_chunk1[1].width);
             let blk = Block::default()
                 .title("Logging in")
-                .border_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
+                .border_style(Style::default().fg(theme.accent).modifier(Modifier::BOLD))
                 .borders(Borders::ALL);
 
             let t = [Text::styled(
                 "*".repeat(self.waiting.count),
-                Style::default().fg(Color::Magenta),
+                Style::default().fg(theme.accent),
             )];
             let p = Paragraph::new(t.iter())
                 .block(blk)
@@ -166,7 +166,7 @@ impl RenderWidget for LoginWidget {
                 (
                     Block::default()
                         .title("User Name")
-                        .border_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
+                        .border_style(Style::default().fg(theme.accent).modifier(Modifier::BOLD))
                         .borders(Borders::ALL),
                     Block::default().title("Password").borders(Borders::ALL),
                 )
@@ -175,7 +175,7 @@ impl RenderWidget for LoginWidget {
                     Block::default().title("User Name").borders(Borders::ALL),
                     Block::default()
                         .title("Password")
-                        .border_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
+                        .border_style(Style::default().fg(theme.accent).modifier(Modifier::BOLD))
                         .borders(Borders::ALL),
                 )
             };
@@ -199,7 +199,7 @@ impl RenderWidget for LoginWidget {
             // User name
             let t = [Text::styled(
                 &self.login.username,
-                Style::default().fg(Color::Cyan),
+                Style::default().fg(theme.secondary),
             )];
             let p = Paragraph::new(t.iter()).block(high_user);
 
@@ -208,7 +208,7 @@ impl RenderWidget for LoginWidget {
             // Password from here down
             let t2 = [Text::styled(
                 "*".repeat(self.login.password.len()),
-                Style::default().fg(Color::Cyan),
+                Style::default().fg(theme.secondary),
             )];
             let p2 = Paragraph::new(t2.iter()).block(high_pass);
 
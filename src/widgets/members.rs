@@ -0,0 +1,112 @@
+use matrix_sdk::identifiers::{RoomId, UserId};
+use rumatui_tui::{
+    backend::Backend,
+    layout::Rect,
+    style::Style,
+    widgets::{Block, Borders, List, Text},
+    Frame,
+};
+
+use crate::widgets::{colors::NickColors, rooms::ListState, theme::Theme, RenderWidget};
+
+/// The joined members of the current room, shown to the right of the timeline.
+#[derive(Clone, Debug, Default)]
+pub struct MembersWidget {
+    area: Rect,
+    /// The room the members are of.
+    room: Option<RoomId>,
+    /// The `UserId` and display name of each member sorted by name.
+    members: ListState<(UserId, String)>,
+    /// The members changed since they were last loaded.
+    pub(crate) stale: bool,
+    pub(crate) focused: bool,
+    pub(crate) nick_colors: NickColors,
+}
+
+impl MembersWidget {
+    /// Does the list need loading for `room`.
+    pub fn needs_refresh(&self, room: &RoomId) -> bool {
+        self.stale || self.room.as_ref() != Some(room)
+    }
+
+    /// Replaces the members, the selected member stays selected if they are still in the room.
+    pub fn set_members(&mut self, room: RoomId, mut members: Vec<(UserId, String)>) {
+        members.sort_by(|a, b| a.1.to_lowercase().cmp(&b.1.to_lowercase()));
+        let selected = self
+            .members
+            .get_selected()
+            .filter(|_| self.room.as_ref() == Some(&room))
+            .and_then(|(id, _)| members.iter().position(|(user, _)| user == id))
+            .unwrap_or_default();
+
+        self.members = ListState::new(members);
+        self.members.selected = selected;
+        self.room = Some(room);
+        self.stale = false;
+    }
+
+    pub fn selected(&self) -> Option<&UserId> {
+        self.members.get_selected().map(|(id, _)| id)
+    }
+
+    pub fn select_next(&mut self) {
+        self.members.select_next()
+    }
+
+    pub fn select_previous(&mut self) {
+        self.members.select_previous()
+    }
+
+    pub fn select_first(&mut self) {
+        self.members.selected = 0;
+    }
+
+    pub fn select_last(&mut self) {
+        self.members.selected = self.members.len().saturating_sub(1);
+    }
+
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        self.area.intersects(Rect::new(x, y, 1, 1))
+    }
+}
+
+impl RenderWidget for MembersWidget {
+    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect, theme: &Theme)
+    where
+        B: Backend,
+    {
+        self.area = area;
+        // the block borders take up two lines
+        let height = (area.height as usize).saturating_sub(2);
+        let selected = self.members.selected_idx();
+        let offset = (selected + 1).saturating_sub(height);
+
+        let focused = self.focused;
+        let colors = &self.nick_colors;
+        let items = self
+            .members
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .map(|(i, (user, name))| {
+                if focused && i == selected {
+                    Text::styled(format!(">> {}", name), theme.selected_style())
+                } else {
+                    Text::styled(
+                        format!("   {}", name),
+                        Style::default().fg(colors.color(user)),
+                    )
+                }
+            });
+
+        let title = format!("Members ({})", self.members.len());
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(&title)
+                .border_style(theme.border_style(self.focused))
+                .title_style(theme.title_style()),
+        );
+        f.render_widget(list, area);
+    }
+}
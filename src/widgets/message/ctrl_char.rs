@@ -5,6 +5,10 @@ use rumatui_tui::style::{Color, Modifier, Style};
 use rumatui_tui::widgets::Text;
 
 use super::Message;
+use crate::{
+    client::outbox::SendState,
+    widgets::{theme::Theme, utils::find_links},
+};
 
 #[derive(Clone, Debug, Default)]
 pub struct CtrlChunk {
@@ -321,10 +325,11 @@ impl CtrlChars {
 }
 
 /// Parses CSI codes and converts them into `Vec<tui::widgets::Text>` chunks.
-pub fn process_text<'a>(message: &'a Message) -> Vec<Text<'a>> {
+///
+/// The sender is left out, the timeline shows it in the header of the message's group.
+pub fn process_text<'a>(message: &'a Message, theme: &Theme) -> Vec<Text<'a>> {
     use itertools::Itertools;
 
-    let name = format!("{}: ", message.name);
     let mut msg = message.text.to_string();
     if msg.contains("    ") {
         msg = msg.replace("    ", "\u{2800}   ");
@@ -337,8 +342,10 @@ pub fn process_text<'a>(message: &'a Message) -> Vec<Text<'a>> {
 
     let body = CtrlChars::parse(msg).into_text();
 
-    let mut formatted = vec![Text::styled(name, Style::default().fg(Color::Magenta))];
-    formatted.extend(body);
+    let mut formatted = body
+        .into_iter()
+        .flat_map(|text| linkify(text, theme))
+        .collect::<Vec<_>>();
     // add the reactions
     if !message.reactions.is_empty() {
         let reactions = format!(
@@ -347,9 +354,54 @@ pub fn process_text<'a>(message: &'a Message) -> Vec<Text<'a>> {
         );
         formatted.push(Text::raw(reactions));
     }
+    match &message.send_state {
+        Some(SendState::Pending) => formatted.push(Text::styled(
+            "\u{2800}   sending...\n",
+            Style::default().fg(theme.dim),
+        )),
+        Some(SendState::Failed(reason)) => formatted.push(Text::styled(
+            format!(
+                "\u{2800}   failed to send: {}, /retry or /discard\n",
+                reason
+            ),
+            Style::default().fg(theme.error),
+        )),
+        Some(SendState::Sent(_)) | Some(SendState::Seen) | None => {}
+    }
     formatted
 }
 
+/// Splits the URLs out of `text` as hyperlinks.
+fn linkify<'a>(text: Text<'a>, theme: &Theme) -> Vec<Text<'a>> {
+    let (body, style) = match text {
+        Text::Raw(body) => (body, Style::default()),
+        Text::Styled(body, style) => (body, style),
+        link @ Text::Link(..) => return vec![link],
+    };
+    let links = find_links(&body);
+    if links.is_empty() {
+        return vec![Text::Styled(body, style)];
+    }
+
+    let link_style = style
+        .fg(theme.link)
+        .modifier(style.modifier | Modifier::UNDERLINED);
+    let mut texts = vec![];
+    let mut end = 0;
+    for range in links {
+        if range.start > end {
+            texts.push(Text::styled(body[end..range.start].to_string(), style));
+        }
+        let url = body[range.clone()].to_string();
+        texts.push(Text::link(url.clone(), link_style, url));
+        end = range.end;
+    }
+    if end < body.len() {
+        texts.push(Text::styled(body[end..].to_string(), style));
+    }
+    texts
+}
+
 // TODO why do all but `failed_message` work locally and fail in travis CI?
 #[cfg(test)]
 mod test {
@@ -1,4 +1,5 @@
 pub mod ctrl_char;
 pub mod msgs;
+pub mod timeline;
 
-pub use msgs::{Message, MessageWidget};
+pub use msgs::{Draft, Message, MessageWidget, Notice};
@@ -1,6 +1,6 @@
 use std::{
     cell::{Cell, RefCell},
-    collections::{HashMap, VecDeque},
+    collections::{BTreeMap, HashMap, VecDeque},
     convert::TryFrom,
     fmt,
     ops::Deref,
@@ -9,10 +9,13 @@ use std::{
     time::{Duration, SystemTime},
 };
 
+use chrono::Local;
 use matrix_sdk::{
     events::{
+        receipt::Receipts,
         room::message::{
-            FormattedBody, MessageEventContent, MessageFormat, RelatesTo, TextMessageEventContent,
+            FormattedBody, InReplyTo, MessageEventContent, MessageFormat, RelatesTo,
+            TextMessageEventContent,
         },
         AnyPossiblyRedactedSyncMessageEvent, AnySyncMessageEvent, SyncMessageEvent,
     },
@@ -22,18 +25,35 @@ use matrix_sdk::{
 };
 use rumatui_tui::{
     backend::Backend,
+    buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect, ScrollMode},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     widgets::{Block, Borders, Paragraph, Text},
     Frame,
 };
 use termion::event::MouseButton;
 use tokio::sync::RwLock;
+use unicode_segmentation::UnicodeSegmentation;
 use uuid::Uuid;
 
 use crate::{
+    client::{
+        outbox::SendState,
+        preview::{PreviewSettings, UrlPreview},
+    },
     error::{Error, Result},
-    widgets::{message::ctrl_char, utils::markdown_to_html, RenderWidget},
+    widgets::{
+        colors::NickColors,
+        completion::{Completion, Sources},
+        message::{
+            ctrl_char,
+            timeline::{self, TimelineSettings},
+        },
+        text_input::{Edit, TextInput},
+        theme::Theme,
+        utils::{find_links, is_markdown, markdown_to_html, mention_pills},
+        RenderWidget,
+    },
 };
 
 /// A reaction event containing the string (emoji) and the event id for the reaction
@@ -69,6 +89,65 @@ pub struct Message {
     pub sent_receipt: bool,
     pub timestamp: SystemTime,
     pub uuid: Uuid,
+    /// Where a message sent by the user is on its way to the other members, `None`
+    /// for messages from everyone else.
+    pub send_state: Option<SendState>,
+}
+
+/// What the send box is writing besides a new message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Draft {
+    /// A reply to the message, the name is the sender's.
+    Reply(EventId, String),
+    /// A new version of one of the user's messages.
+    Edit(EventId),
+}
+
+/// The preview of a URL, fetched once and kept for every message linking to it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Preview {
+    Loading,
+    Loaded(UrlPreview),
+    /// The page has nothing to preview or fetching it failed.
+    Missing,
+}
+
+/// A notification, the parts naming a user are drawn in the user's color.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Notice(Vec<(String, Option<UserId>)>);
+
+impl Notice {
+    pub fn text<S: Into<String>>(mut self, text: S) -> Self {
+        self.0.push((text.into(), None));
+        self
+    }
+
+    pub fn user<S: Into<String>>(mut self, user: &UserId, name: S) -> Self {
+        self.0.push((name.into(), Some(user.clone())));
+        self
+    }
+
+    /// Who is typing in the room, empty when nobody is.
+    pub fn typing(users: &[(UserId, String)]) -> Self {
+        let mut notice = Self::default();
+        for (idx, (user, name)) in users.iter().enumerate() {
+            if idx > 0 {
+                notice = notice.text(", ");
+            }
+            notice = notice.user(user, name.as_str());
+        }
+        match users.len() {
+            0 => notice,
+            1 => notice.text(" is typing..."),
+            _ => notice.text(" are typing..."),
+        }
+    }
+}
+
+impl From<&str> for Notice {
+    fn from(text: &str) -> Self {
+        Self::default().text(text)
+    }
 }
 
 pub enum MsgType {
@@ -88,17 +167,46 @@ pub enum MsgType {
 pub struct MessageWidget {
     msg_area: Rect,
     send_area: Rect,
+    /// The whole send box, `send_area` is only the button.
+    input_area: Rect,
     // TODO save this to a local "database" somehow
     /// This is the RoomId of the last used room.
     pub(crate) current_room: Rc<RefCell<Option<RoomId>>>,
     messages: HashMap<RoomId, Vec<Message>>,
     pub(crate) me: Option<UserId>,
     pub unread_notifications: UInt,
-    send_msgs: HashMap<RoomId, String>,
-    notifications: VecDeque<(Option<SystemTime>, String)>,
+    /// The send box and its history for each room.
+    send_msgs: HashMap<RoomId, TextInput>,
+    notifications: VecDeque<(Option<SystemTime>, Notice)>,
+    /// Usage, completions or errors of the slash command being typed.
+    command_hint: Option<String>,
+    /// The tab completion candidates of the word being typed.
+    completion: Option<Completion>,
     scroll_pos: usize,
     did_overflow: Option<Rc<Cell<bool>>>,
     at_top: Option<Rc<Cell<bool>>>,
+    pub(crate) timeline_focused: bool,
+    pub(crate) composer_focused: bool,
+    /// The message replies, edits and deletes act on, `None` when no message is selected.
+    selected: Option<EventId>,
+    /// The number of lines below the selected message at the last render.
+    selected_below: Option<usize>,
+    /// Scroll the selected message into view on the next render.
+    follow_selection: bool,
+    /// Where the links in the timeline were drawn and their URLs.
+    links: Vec<(Rect, String)>,
+    /// The reply or edit being written in each room.
+    drafts: HashMap<RoomId, Draft>,
+    /// Timestamps, day separators and sender groups.
+    timeline_settings: TimelineSettings,
+    /// The color of each sender's name.
+    pub(crate) nick_colors: NickColors,
+    /// Where URL previews are shown.
+    preview_settings: PreviewSettings,
+    /// The previews of the first link of each message that has been on screen.
+    previews: HashMap<String, Preview>,
+    /// The URLs of visible messages whose previews need fetching.
+    wanted_previews: Vec<String>,
 }
 
 impl MessageWidget {
@@ -106,7 +214,7 @@ impl MessageWidget {
         for room in rooms.values() {
             let room = room.read().await;
 
-            self.send_msgs.insert(room.room_id.clone(), String::new());
+            self.send_msgs.entry(room.room_id.clone()).or_default();
             self.unread_notifications = room.unread_notifications.unwrap_or_default();
             self.unread_notifications += room.unread_highlight.unwrap_or_default();
 
@@ -124,7 +232,8 @@ impl MessageWidget {
 
     pub async fn add_room(&mut self, room: Arc<RwLock<Room>>) {
         self.send_msgs
-            .insert(room.read().await.room_id.clone(), String::new());
+            .entry(room.read().await.room_id.clone())
+            .or_default();
     }
 
     // TODO factor out with AppWidget::process_room_events and MessageWidget::echo_sent_msg
@@ -137,57 +246,45 @@ impl MessageWidget {
             unsigned,
             ..
         } = event;
-        let name = if let Some(mem) = room.joined_members.get(&sender) {
-            mem.name()
-        } else {
-            sender.localpart().into()
-        };
-        match content {
-            MessageEventContent::Text(TextMessageEventContent {
-                body, formatted, ..
-            }) => {
-                let msg = if formatted
-                    .as_ref()
-                    .map(|f| f.body.to_string())
-                    .unwrap_or(body.to_string())
-                    != *body
-                {
-                    crate::widgets::utils::markdown_to_terminal(body).unwrap_or(body.clone())
-                // None.unwrap_or(body.clone())
-                } else {
-                    body.clone()
-                };
-                let txn_id = unsigned
-                    .transaction_id
-                    .as_ref()
-                    .cloned()
-                    .unwrap_or_default();
-
-                self.add_message(
-                    Message {
-                        name,
-                        user: sender.clone(),
-                        text: msg,
-                        event_id: event_id.clone(),
-                        timestamp: *origin_server_ts,
-                        uuid: Uuid::parse_str(&txn_id).unwrap_or(Uuid::new_v4()),
-                        read: false,
-                        reactions: vec![],
-                        sent_receipt: false,
-                    },
-                    &room.room_id,
-                );
-            }
-            _ => {}
+        let name = crate::widgets::utils::display_name(room, sender);
+        if let Some(msg) = crate::widgets::utils::message_text(content) {
+            let txn_id = unsigned
+                .transaction_id
+                .as_ref()
+                .cloned()
+                .unwrap_or_default();
+
+            self.add_message(
+                Message {
+                    name,
+                    user: sender.clone(),
+                    text: msg,
+                    event_id: event_id.clone(),
+                    timestamp: *origin_server_ts,
+                    uuid: Uuid::parse_str(&txn_id).unwrap_or(Uuid::new_v4()),
+                    read: false,
+                    reactions: vec![],
+                    sent_receipt: false,
+                    send_state: None,
+                },
+                &room.room_id,
+            );
         }
     }
 
-    pub fn add_message(&mut self, msg: Message, room: &RoomId) {
+    pub fn add_message(&mut self, mut msg: Message, room: &RoomId) {
+        if msg.send_state.is_none() && Some(&msg.user) == self.me.as_ref() {
+            msg.send_state = Some(SendState::Sent(msg.event_id.clone()));
+        }
         if let Some(messages) = self.messages.get_mut(room) {
-            // remove the message echo when user sends a message and we display the text before
-            // the server responds
-            if let Some(idx) = messages.iter().position(|m| m.uuid == msg.uuid) {
-                messages[idx] = msg;
+            // replace the message echo when user sends a message and we display the text before
+            // the server responds, the transaction id of the event is the echo's `Uuid`
+            if let Some(echo) = messages.iter_mut().find(|m| m.uuid == msg.uuid) {
+                msg.reactions = std::mem::take(&mut echo.reactions);
+                if echo.send_state == Some(SendState::Seen) {
+                    msg.send_state = Some(SendState::Seen);
+                }
+                *echo = msg;
                 return;
             }
         }
@@ -205,8 +302,74 @@ impl MessageWidget {
         }
     }
 
+    pub fn set_send_state(&mut self, room: &RoomId, uuid: &Uuid, state: Option<SendState>) {
+        if let Some(messages) = self.messages.get_mut(room) {
+            if let Some(msg) = messages.iter_mut().find(|m| &m.uuid == uuid) {
+                msg.send_state = state;
+            }
+        }
+    }
+
+    /// The server accepted the echoed message `uuid`, the echo now has the real `EventId`
+    /// so reactions, edits and receipts find it.
+    pub fn set_sent(&mut self, room: &RoomId, uuid: &Uuid, event_id: EventId) {
+        if let Some(messages) = self.messages.get_mut(room) {
+            if let Some(msg) = messages.iter_mut().find(|m| &m.uuid == uuid) {
+                // the synced event may have beaten the response and been seen already
+                if msg.send_state != Some(SendState::Seen) {
+                    msg.send_state = Some(SendState::Sent(event_id.clone()));
+                }
+                msg.event_id = event_id;
+            }
+        }
+    }
+
+    /// Marks the user's messages up to each event another member has read as seen.
+    pub fn set_seen(&mut self, room: &RoomId, receipts: &BTreeMap<EventId, Receipts>) {
+        let me = self.me.as_ref();
+        let messages = match self.messages.get_mut(room) {
+            Some(messages) => messages,
+            None => return,
+        };
+        messages.sort_by(|msg, msg2| msg.timestamp.cmp(&msg2.timestamp));
+
+        let last_read = receipts
+            .iter()
+            .filter(|(_, rec)| {
+                rec.read
+                    .as_ref()
+                    .map_or(false, |users| users.keys().any(|user| Some(user) != me))
+            })
+            .filter_map(|(event_id, _)| messages.iter().position(|m| &m.event_id == event_id))
+            .max();
+        if let Some(last_read) = last_read {
+            for msg in messages[..=last_read].iter_mut() {
+                if let Some(SendState::Sent(_)) = msg.send_state {
+                    msg.send_state = Some(SendState::Seen);
+                }
+            }
+        }
+    }
+
+    /// Remove the echoes of messages that will never be sent.
+    pub fn remove_messages(&mut self, room: &RoomId, uuids: &[Uuid]) {
+        if let Some(messages) = self.messages.get_mut(room) {
+            messages.retain(|m| {
+                let unsent = matches!(
+                    m.send_state,
+                    Some(SendState::Pending) | Some(SendState::Failed(_))
+                );
+                !(unsent && uuids.contains(&m.uuid))
+            });
+        }
+    }
+
     pub fn add_notify(&mut self, notify: &str) {
-        self.notifications.push_back((None, notify.to_string()));
+        self.add_notice(Notice::from(notify))
+    }
+
+    pub fn add_notice(&mut self, notice: Notice) {
+        self.notifications.push_back((None, notice));
     }
 
     pub fn set_reaction_event(
@@ -240,48 +403,399 @@ impl MessageWidget {
         }
     }
 
-    pub fn clear_send_msg(&mut self) {
-        if let Some(room_id) = self.current_room.borrow().deref() {
-            if let Some(msg) = self.send_msgs.get_mut(room_id) {
-                msg.clear()
+    /// The send box of the current room.
+    fn current_input(&self) -> Option<&TextInput> {
+        let room = self.current_room.borrow().clone()?;
+        self.send_msgs.get(&room)
+    }
+
+    fn current_input_mut(&mut self) -> Option<&mut TextInput> {
+        let room = self.current_room.borrow().clone()?;
+        self.send_msgs.get_mut(&room)
+    }
+
+    /// The messages of the current room in the order they are shown.
+    fn current_messages(&self) -> Option<&Vec<Message>> {
+        let room = self.current_room.borrow().clone()?;
+        self.messages.get(&room)
+    }
+
+    fn selected_idx(&self) -> Option<usize> {
+        let selected = self.selected.as_ref()?;
+        self.current_messages()?
+            .iter()
+            .position(|msg| &msg.event_id == selected)
+    }
+
+    /// The selected message, `None` when nothing in the current room is selected.
+    pub fn selected_message(&self) -> Option<&Message> {
+        self.current_messages()?.get(self.selected_idx()?)
+    }
+
+    /// The message `/retry` and `/discard` act on, the selected message when it is unsent
+    /// otherwise the latest message of the current room that failed to send.
+    pub fn unsent_message(&self) -> Option<Uuid> {
+        let unsent = |msg: &&Message| {
+            matches!(
+                msg.send_state,
+                Some(SendState::Pending) | Some(SendState::Failed(_))
+            )
+        };
+        match self.selected_message().filter(unsent) {
+            Some(msg) => Some(msg.uuid),
+            None => self
+                .current_messages()?
+                .iter()
+                .rev()
+                .find(|msg| matches!(msg.send_state, Some(SendState::Failed(_))))
+                .map(|msg| msg.uuid),
+        }
+    }
+
+    pub fn selected_event_id(&self) -> Option<&EventId> {
+        self.selected_message().map(|msg| &msg.event_id)
+    }
+
+    /// Selects the message at `idx` of the current room and scrolls it into view, with
+    /// no such message the timeline jumps to the latest message.
+    fn select_idx(&mut self, idx: Option<usize>) {
+        let selected = idx.and_then(|idx| {
+            self.current_messages()
+                .and_then(|msgs| msgs.get(idx))
+                .map(|msg| msg.event_id.clone())
+        });
+        if selected.is_none() {
+            self.reset_scroll();
+        }
+        self.selected = selected;
+        self.selected_below = None;
+        self.follow_selection = true;
+    }
+
+    /// Selects the message before the selected one, with nothing selected the latest.
+    ///
+    /// Returns true if the first loaded message was already selected.
+    pub fn select_previous_message(&mut self) -> bool {
+        let (idx, at_top) = match self.selected_idx() {
+            Some(idx) => (idx.saturating_sub(1), idx == 0),
+            None => (
+                self.current_messages()
+                    .map_or(0, Vec::len)
+                    .saturating_sub(1),
+                false,
+            ),
+        };
+        self.select_idx(Some(idx));
+        at_top
+    }
+
+    /// Selects the message after the selected one, past the latest nothing is selected.
+    pub fn select_next_message(&mut self) {
+        let idx = self.selected_idx().map(|idx| idx + 1);
+        self.select_idx(idx);
+    }
+
+    /// Moves the selection up about the height of the timeline.
+    ///
+    /// Returns true if the first loaded message was already selected.
+    pub fn select_page_up(&mut self) -> bool {
+        let page = self.page_height();
+        let room = match self.current_room.borrow().clone() {
+            Some(room) => room,
+            None => return false,
+        };
+        let msgs = match self.messages.get(&room) {
+            Some(msgs) => msgs,
+            None => return false,
+        };
+        let mut idx = self
+            .selected_idx()
+            .unwrap_or_else(|| msgs.len().saturating_sub(1));
+        let at_top = idx == 0;
+        let mut lines = 0;
+        while idx > 0 && lines < page {
+            lines += self.message_height(&room, msgs, idx);
+            idx -= 1;
+        }
+        self.select_idx(Some(idx));
+        at_top
+    }
+
+    /// Moves the selection down about the height of the timeline, it stops at the
+    /// latest message.
+    pub fn select_page_down(&mut self) {
+        let page = self.page_height();
+        let room = match self.current_room.borrow().clone() {
+            Some(room) => room,
+            None => return,
+        };
+        let (msgs, mut idx) = match (self.messages.get(&room), self.selected_idx()) {
+            (Some(msgs), Some(idx)) => (msgs, idx),
+            _ => return,
+        };
+        let mut lines = 0;
+        while idx + 1 < msgs.len() && lines < page {
+            lines += self.message_height(&room, msgs, idx);
+            idx += 1;
+        }
+        self.select_idx(Some(idx));
+    }
+
+    pub fn select_first_message(&mut self) {
+        self.select_idx(Some(0));
+    }
+
+    /// Clears the selection and jumps to the latest message.
+    pub fn clear_selection(&mut self) {
+        self.select_idx(None);
+    }
+
+    /// The number of lines that fit in the timeline.
+    fn page_height(&self) -> usize {
+        // the block borders take up two lines
+        (self.msg_area.height as usize).saturating_sub(2).max(1)
+    }
+
+    /// The texts of `msg` in `room` followed by the preview card of its first link.
+    ///
+    /// A day separator and the sender's header come first unless `msg` continues the
+    /// group of `prev`, the message shown before it.
+    fn message_texts<'a>(
+        &'a self,
+        room: &RoomId,
+        prev: Option<&Message>,
+        msg: &'a Message,
+        theme: &Theme,
+    ) -> Vec<Text<'a>> {
+        let now = Local::now();
+        let settings = &self.timeline_settings;
+        let mut texts = vec![];
+        if let Some(day) = timeline::day_change(prev, msg, now) {
+            texts.push(Text::styled(
+                format!("\u{2500}\u{2500} {} \u{2500}\u{2500}\n", day),
+                Style::default().fg(theme.dim),
+            ));
+        }
+        if !prev.map_or(false, |prev| settings.continues_group(prev, msg)) {
+            texts.push(Text::styled(
+                msg.name.as_str(),
+                Style::default()
+                    .fg(self.nick_colors.color(&msg.user))
+                    .modifier(Modifier::BOLD),
+            ));
+            if let Some(time) = settings.timestamp(msg.timestamp, now) {
+                texts.push(Text::styled(
+                    format!("  {}", time),
+                    Style::default().fg(theme.dim),
+                ));
+            }
+            texts.push(Text::raw("\n"));
+        }
+        texts.extend(ctrl_char::process_text(msg, theme));
+        if self.preview_settings.is_enabled(room) {
+            let preview = first_link(&msg.text).and_then(|url| self.previews.get(url));
+            if let Some(Preview::Loaded(preview)) = preview {
+                texts.extend(preview_card(preview, theme));
             }
         }
+        texts
     }
 
-    // TODO Im sure there is an actual way to do this like Riot
-    fn process_message(&self) -> Result<MsgType> {
-        if let Some(room_id) = self.current_room.borrow().deref() {
-            if let Some(msg) = self.send_msgs.get(room_id) {
-                if msg.contains('`') {
-                    Ok(MsgType::FormattedText)
-                } else {
-                    Ok(MsgType::PlainText)
+    /// The number of lines the message at `idx` of `msgs` takes up in the timeline.
+    fn message_height(&self, room: &RoomId, msgs: &[Message], idx: usize) -> usize {
+        let width = (self.msg_area.width as usize).saturating_sub(2);
+        let prev = idx.checked_sub(1).map(|prev| &msgs[prev]);
+        // colors do not change the height, any theme will do
+        let text = self
+            .message_texts(room, prev, &msgs[idx], &Theme::default())
+            .iter()
+            .map(|text| match text {
+                Text::Raw(body) | Text::Styled(body, _) | Text::Link(body, ..) => body.as_ref(),
+            })
+            .collect::<String>();
+        wrapped_height(&text, width)
+    }
+
+    /// The lines below the selected message and the lines the selected message takes up.
+    fn selection_lines(&self, room_id: &RoomId) -> Option<(usize, usize)> {
+        let selected = self.selected.as_ref()?;
+        let messages = self.messages.get(room_id)?;
+        let idx = messages.iter().position(|msg| &msg.event_id == selected)?;
+        let below = (idx + 1..messages.len())
+            .map(|below| self.message_height(room_id, messages, below))
+            .sum();
+        Some((below, self.message_height(room_id, messages, idx)))
+    }
+
+    /// Queues the previews of the links in the messages on screen that have not been
+    /// fetched yet.
+    fn want_visible_previews(&mut self, room_id: &RoomId) {
+        if !self.preview_settings.is_enabled(room_id) {
+            return;
+        }
+        let messages = match self.messages.get(room_id) {
+            Some(messages) => messages,
+            None => return,
+        };
+        let bottom = self.scroll_pos + self.page_height();
+        let mut below = 0;
+        let mut wanted = vec![];
+        for (idx, msg) in messages.iter().enumerate().rev() {
+            if below >= bottom {
+                break;
+            }
+            let height = self.message_height(room_id, messages, idx);
+            if below + height > self.scroll_pos {
+                if let Some(url) = first_link(&msg.text) {
+                    if !self.previews.contains_key(url) && !wanted.contains(&url) {
+                        wanted.push(url);
+                    }
                 }
-            } else {
-                Err(Error::Rumatui(
-                    "The room was added to the send_msgs HashMap rumatui BUG",
-                ))
             }
+            below += height;
+        }
+
+        let wanted = wanted.into_iter().map(str::to_string).collect::<Vec<_>>();
+        for url in wanted {
+            self.previews.insert(url.clone(), Preview::Loading);
+            self.wanted_previews.push(url);
+        }
+    }
+
+    /// The URLs whose previews should be fetched, each is only returned once.
+    pub fn take_wanted_previews(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.wanted_previews)
+    }
+
+    /// Stores the fetched preview of `url`, `None` when there is nothing to show.
+    pub fn set_preview(&mut self, url: String, preview: Option<UrlPreview>) {
+        let preview = preview.map_or(Preview::Missing, Preview::Loaded);
+        self.previews.insert(url, preview);
+    }
+
+    pub fn set_timeline_settings(&mut self, settings: TimelineSettings) {
+        self.timeline_settings = settings;
+    }
+
+    pub fn preview_settings(&self) -> &PreviewSettings {
+        &self.preview_settings
+    }
+
+    pub fn set_preview_settings(&mut self, settings: PreviewSettings) {
+        self.preview_settings = settings;
+    }
+
+    /// Turns previews on or off in the current room, or everywhere.
+    pub fn set_previews_enabled(&mut self, on: bool, everywhere: bool) {
+        let room = self.current_room.borrow().clone();
+        if everywhere {
+            // a room left on or off would ignore the new global setting
+            self.preview_settings.rooms.clear();
+            self.preview_settings.set(None, on);
         } else {
-            Err(Error::Rumatui("No current room has been set rumatui BUG"))
+            self.preview_settings.set(room.as_ref(), on);
+        }
+        self.selected_below = None;
+    }
+
+    /// Keeps the selected message in place when messages arrive below it and scrolls it
+    /// into view when the selection moved.
+    fn keep_selection_visible(&mut self, room_id: &RoomId) {
+        let (below, height) = match self.selection_lines(room_id) {
+            Some(lines) => lines,
+            None => {
+                self.selected_below = None;
+                return;
+            }
+        };
+        if let Some(before) = self.selected_below.replace(below) {
+            self.scroll_pos += below.saturating_sub(before);
+        }
+        if self.follow_selection {
+            self.follow_selection = false;
+            let page = self.page_height();
+            if below < self.scroll_pos {
+                self.scroll_pos = below;
+            } else if below + height > self.scroll_pos + page {
+                self.scroll_pos = (below + height).saturating_sub(page);
+            }
         }
     }
 
-    pub fn get_sending_message(&self) -> Result<MessageEventContent> {
+    pub fn draft(&self) -> Option<&Draft> {
+        let room = self.current_room.borrow().clone()?;
+        self.drafts.get(&room)
+    }
+
+    pub fn set_draft(&mut self, draft: Draft) {
+        let room = self.current_room.borrow().clone();
+        if let Some(room) = room {
+            self.drafts.insert(room, draft);
+        }
+    }
+
+    /// Returns false if there was no reply or edit to cancel.
+    pub fn cancel_draft(&mut self) -> bool {
+        let room = self.current_room.borrow().clone();
+        room.and_then(|room| self.drafts.remove(&room)).is_some()
+    }
+
+    /// Clears the send box after the text was sent, the text is added to the room's history.
+    pub fn clear_send_msg(&mut self) {
+        self.command_hint = None;
+        self.completion = None;
+        self.cancel_draft();
+        if let Some(input) = self.current_input_mut() {
+            let sent = input.text().to_string();
+            input.push_history(sent);
+            input.clear();
+        }
+    }
+
+    /// Decides if the message needs a HTML `formatted_body`.
+    ///
+    /// Returns the markdown to render with mentions of room members replaced by pills.
+    fn process_message(msg: &str, members: &[(UserId, String)]) -> (MsgType, String) {
+        let (markdown, mentions) = mention_pills(msg, members);
+        if mentions || is_markdown(&markdown) {
+            (MsgType::FormattedText, markdown)
+        } else {
+            (MsgType::PlainText, markdown)
+        }
+    }
+
+    /// The content of the message in the send box of the current room.
+    ///
+    /// # Arguments
+    ///
+    /// * members - The `UserId` and display name of each member of the room, used
+    /// to turn `@user` mentions into pills.
+    pub fn get_sending_message(&self, members: &[(UserId, String)]) -> Result<MessageEventContent> {
         if let Some(room_id) = self.current_room.borrow().deref() {
-            if let Some(to_send) = self.send_msgs.get(room_id) {
-                match self.process_message()? {
-                    MsgType::PlainText => Ok(MessageEventContent::Text(
-                        TextMessageEventContent::new_plain(to_send.as_str()),
-                    )),
-                    MsgType::FormattedText => {
+            if let Some(to_send) = self.send_msgs.get(room_id).map(TextInput::text) {
+                let relates_to = match self.drafts.get(room_id) {
+                    Some(Draft::Reply(event_id, _)) => Some(RelatesTo {
+                        in_reply_to: InReplyTo {
+                            event_id: event_id.clone(),
+                        },
+                    }),
+                    _ => None,
+                };
+                match Self::process_message(to_send, members) {
+                    (MsgType::PlainText, _) => {
+                        Ok(MessageEventContent::Text(TextMessageEventContent {
+                            relates_to,
+                            ..TextMessageEventContent::new_plain(to_send)
+                        }))
+                    }
+                    (MsgType::FormattedText, markdown) => {
                         Ok(MessageEventContent::Text(TextMessageEventContent {
                             body: to_send.to_string(),
                             formatted: Some(FormattedBody {
                                 format: MessageFormat::Html,
-                                body: markdown_to_html(&to_send),
+                                body: markdown_to_html(&markdown),
                             }),
-                            relates_to: None::<RelatesTo>,
+                            relates_to,
                         }))
                     }
                     _ => todo!("implement more sending messages"),
@@ -303,38 +817,24 @@ impl MessageWidget {
         uuid: Uuid,
         content: MessageEventContent,
     ) {
-        match content {
-            MessageEventContent::Text(TextMessageEventContent {
-                body, formatted, ..
-            }) => {
-                let msg = if formatted
-                    .as_ref()
-                    .map(|f| f.body.to_string())
-                    .unwrap_or(body.to_string())
-                    != body
-                {
-                    // This is extremely expensive
-                    // TODO cache these results somehow
-                    crate::widgets::utils::markdown_to_terminal(&body).unwrap_or(body.clone())
-                } else {
-                    body
-                };
-                let timestamp = SystemTime::now();
+        if let Some(msg) = crate::widgets::utils::message_text(&content) {
+            let timestamp = SystemTime::now();
 
-                let msg = Message {
-                    text: msg,
-                    user: self.me.as_ref().unwrap().clone(),
-                    timestamp,
-                    name,
-                    event_id: EventId::try_from("$fakeeventid:rumatui.client").unwrap(),
-                    uuid,
-                    read: true,
-                    reactions: vec![],
-                    sent_receipt: true,
-                };
-                self.add_message(msg, id)
-            }
-            _ => {}
+            let msg = Message {
+                text: msg,
+                user: self.me.as_ref().unwrap().clone(),
+                timestamp,
+                name,
+                // a placeholder until the server responds, unique so echoes are not
+                // mistaken for each other
+                event_id: EventId::try_from(format!("${}:rumatui.client", uuid).as_str()).unwrap(),
+                uuid,
+                read: true,
+                reactions: vec![],
+                sent_receipt: true,
+                send_state: Some(SendState::Pending),
+            };
+            self.add_message(msg, id)
         }
     }
 
@@ -432,30 +932,54 @@ impl MessageWidget {
     }
 
     pub fn on_scroll_up(&mut self, x: u16, y: u16) -> bool {
-        let intersects = self.msg_area.intersects(Rect::new(x, y, 1, 1));
-        if intersects {
-            if let Some(overflow) = self.did_overflow.as_ref() {
-                if overflow.get() {
-                    if let Some(at_top) = self.at_top.as_ref() {
-                        if at_top.get() {
-                            at_top.set(false);
-                            return true;
-                        } else {
-                            self.scroll_pos += 1;
-                            return false;
-                        }
+        self.contains_timeline(x, y) && self.scroll_up()
+    }
+
+    /// Scrolls the timeline up a line, returns true when the top is reached and
+    /// older messages should be loaded.
+    pub fn scroll_up(&mut self) -> bool {
+        if let Some(overflow) = self.did_overflow.as_ref() {
+            if overflow.get() {
+                if let Some(at_top) = self.at_top.as_ref() {
+                    if at_top.get() {
+                        at_top.set(false);
+                        true
                     } else {
                         self.scroll_pos += 1;
-                        return false;
+                        false
                     }
                 } else {
-                    return true;
+                    self.scroll_pos += 1;
+                    false
                 }
             } else {
-                unreachable!("did_overflow was not set")
+                true
             }
+        } else {
+            // nothing has been rendered yet
+            false
         }
-        false
+    }
+
+    /// The URL of the link drawn at `x`, `y`.
+    pub fn link_at(&self, x: u16, y: u16) -> Option<&str> {
+        self.links
+            .iter()
+            .find(|(area, _)| area.intersects(Rect::new(x, y, 1, 1)))
+            .map(|(_, url)| url.as_str())
+    }
+
+    /// The first URL in the selected message.
+    pub fn selected_link(&self) -> Option<&str> {
+        first_link(&self.selected_message()?.text)
+    }
+
+    pub fn contains_timeline(&self, x: u16, y: u16) -> bool {
+        self.msg_area.intersects(Rect::new(x, y, 1, 1))
+    }
+
+    pub fn contains_composer(&self, x: u16, y: u16) -> bool {
+        self.input_area.intersects(Rect::new(x, y, 1, 1))
     }
 
     fn calculate_scroll_down(&mut self) {
@@ -472,25 +996,103 @@ impl MessageWidget {
         }
     }
 
-    pub fn add_char(&mut self, ch: char) {
-        if let Some(room) = self.current_room.borrow().as_ref() {
-            if let Some(m) = self.send_msgs.get_mut(room) {
-                m.push(ch)
+    /// The text in the send box of the current room.
+    pub fn sending_text(&self) -> Option<String> {
+        self.current_input().map(|input| input.text().to_string())
+    }
+
+    /// Replaces the text in the send box of the current room.
+    pub fn set_sending_text(&mut self, text: String) {
+        self.completion = None;
+        if let Some(input) = self.current_input_mut() {
+            input.set_text(text)
+        }
+    }
+
+    pub fn set_command_hint(&mut self, hint: Option<String>) {
+        self.command_hint = hint;
+    }
+
+    /// Completes the word before the cursor, repeated calls cycle through the candidates.
+    ///
+    /// Returns false if there is nothing to complete.
+    ///
+    /// # Arguments
+    ///
+    /// * sources - The members and aliases to complete from.
+    ///
+    /// * reverse - Cycle backwards through the candidates.
+    pub fn complete(&mut self, sources: Sources<'_>, reverse: bool) -> bool {
+        let text = match self.current_input() {
+            Some(input) => input.split_at_cursor().0.to_string(),
+            None => return false,
+        };
+
+        match self.completion.as_mut() {
+            Some(completion) if completion.applies_to(&text) => {
+                if reverse {
+                    completion.select_previous()
+                } else {
+                    completion.select_next()
+                }
+            }
+            _ => {
+                self.completion = Completion::new(&text, sources);
+                if reverse {
+                    if let Some(completion) = self.completion.as_mut() {
+                        completion.select_previous()
+                    }
+                }
             }
         }
+
+        if let Some(completed) = self.completion.as_ref().map(Completion::apply) {
+            if let Some(input) = self.current_input_mut() {
+                input.set_text_before_cursor(completed)
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn add_char(&mut self, ch: char) {
+        self.completion = None;
+        if let Some(input) = self.current_input_mut() {
+            input.insert(ch)
+        }
+    }
+
+    /// Insert `text` at the cursor, used for pasted text.
+    pub fn insert_str(&mut self, text: &str) {
+        self.completion = None;
+        if let Some(input) = self.current_input_mut() {
+            input.insert_str(text)
+        }
     }
 
     pub fn remove_char(&mut self) {
-        if let Some(room) = self.current_room.borrow().as_ref() {
-            if let Some(m) = self.send_msgs.get_mut(room) {
-                m.pop();
-            }
+        self.edit(Edit::DeleteBefore);
+    }
+
+    /// Edit the send box, returns false if nothing changed.
+    ///
+    /// When the cursor can not move up or down a line the room's history is browsed.
+    pub fn edit(&mut self, edit: Edit) -> bool {
+        self.completion = None;
+        match self.current_input_mut() {
+            Some(input) => match edit {
+                Edit::Up => input.edit(edit) || input.history_previous(),
+                Edit::Down => input.edit(edit) || input.history_next(),
+                _ => input.edit(edit),
+            },
+            None => false,
         }
     }
 }
 
 impl RenderWidget for MessageWidget {
-    fn render<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+    fn render<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, theme: &Theme) {
         use itertools::Itertools;
 
         if self.did_overflow.is_none() {
@@ -500,18 +1102,19 @@ impl RenderWidget for MessageWidget {
             self.at_top = Some(Rc::new(Cell::new(false)));
         }
 
-        // TODO handle getting the textbox message better
-        let sending_text = if let Some(room_id) = self.current_room.borrow().as_ref() {
-            self.send_msgs
-                .get(room_id)
-                // TODO
-                .cloned()
-                .unwrap_or_default()
-        } else {
-            String::new()
-        };
+        let (before, after) = self
+            .current_input()
+            .map(|input| {
+                let (before, after) = input.split_at_cursor();
+                (before.to_string(), after.to_string())
+            })
+            .unwrap_or_default();
 
-        let mut lines = sending_text.chars().filter(|c| *c == '\n').count();
+        let mut lines = before
+            .chars()
+            .chain(after.chars())
+            .filter(|c| *c == '\n')
+            .count();
         if lines <= 1 {
             lines = 2;
         }
@@ -533,13 +1136,10 @@ impl RenderWidget for MessageWidget {
             .split(area);
 
         self.msg_area = chunks[0];
-        let b = self.current_room.borrow();
-        let current_room_id = if let Some(id) = b.as_ref() {
-            Some(id.clone())
-        } else {
+        let current_room_id = self.current_room.borrow().clone().or_else(|| {
             // or take the first room in the list, this happens on login
             self.messages.keys().next().cloned()
-        };
+        });
 
         let mut msg_copy = vec![];
         // TODO no alloc split messages up by hashmap of roomid to message vec?
@@ -551,12 +1151,25 @@ impl RenderWidget for MessageWidget {
                     // this message has been read and a read receipt will be sent for it
                     mark_msg.read = true;
                 }
-                for msg in messages
-                    .iter_mut()
+            }
+            self.keep_selection_visible(&room_id);
+            self.want_visible_previews(&room_id);
+            if let Some(messages) = self.messages.get(&room_id) {
+                let selected = self.selected.as_ref();
+                let messages = messages
+                    .iter()
                     .unique_by(|msg| msg.event_id.clone())
-                    .flat_map(|msg| ctrl_char::process_text(msg))
-                {
-                    msg_copy.push(msg);
+                    .collect::<Vec<_>>();
+                for (idx, &msg) in messages.iter().enumerate() {
+                    let prev = idx.checked_sub(1).map(|prev| messages[prev]);
+                    let is_selected = Some(&msg.event_id) == selected;
+                    for text in self.message_texts(&room_id, prev, msg, theme) {
+                        msg_copy.push(if is_selected {
+                            highlight(text, theme)
+                        } else {
+                            text
+                        });
+                    }
                 }
             }
         }
@@ -567,19 +1180,16 @@ impl RenderWidget for MessageWidget {
                     "-----Messages-----unread {}",
                     self.unread_notifications.to_string()
                 ),
-                Style::default().fg(Color::Red).modifier(Modifier::BOLD),
+                Style::default().fg(theme.error).modifier(Modifier::BOLD),
             )
         } else {
-            (
-                "-----Messages-----".to_string(),
-                Style::default().fg(Color::Yellow).modifier(Modifier::BOLD),
-            )
+            ("-----Messages-----".to_string(), theme.title_style())
         };
         let messages = Paragraph::new(msg_copy.iter())
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
+                    .border_style(theme.border_style(self.timeline_focused))
                     .title(&title)
                     .title_style(style),
             )
@@ -590,6 +1200,7 @@ impl RenderWidget for MessageWidget {
             .at_top(Rc::clone(self.at_top.as_ref().unwrap()));
 
         f.render_widget(messages, chunks[0]);
+        self.links = drawn_links(f.buffer_mut(), chunks[0]);
 
         // display each notification for 6 seconds
         if let Some((time, _item)) = self.notifications.get_mut(0) {
@@ -604,44 +1215,99 @@ impl RenderWidget for MessageWidget {
             }
         }
 
-        let t2 = vec![Text::styled(
-            self.notifications
-                .get(0)
-                .map(|(_time, item)| item.as_str())
-                .unwrap_or("Notifications..."),
-            Style::default().fg(Color::Green),
-        )];
+        // while completing the notification box shows the candidates, the selected one is
+        // highlighted, while typing a command it shows the command's hint
+        let t2 = if let Some(completion) = self.completion.as_ref() {
+            completion
+                .candidates()
+                .iter()
+                .enumerate()
+                .map(|(i, cand)| {
+                    let style = if i == completion.selected() {
+                        Style::default()
+                            .fg(theme.selected)
+                            .modifier(Modifier::BOLD | Modifier::REVERSED)
+                    } else {
+                        Style::default().fg(theme.notice)
+                    };
+                    Text::styled(format!(" {} ", cand.display), style)
+                })
+                .collect::<Vec<_>>()
+        } else if let Some(hint) = self.command_hint.as_ref() {
+            vec![Text::styled(
+                hint.as_str(),
+                Style::default().fg(theme.warning),
+            )]
+        } else if let Some((_time, notice)) = self.notifications.get(0) {
+            notice
+                .0
+                .iter()
+                .map(|(text, user)| {
+                    let style = match user {
+                        Some(user) => Style::default()
+                            .fg(self.nick_colors.color(user))
+                            .modifier(Modifier::BOLD),
+                        None => Style::default().fg(theme.notice),
+                    };
+                    Text::styled(text.as_str(), style)
+                })
+                .collect()
+        } else {
+            vec![Text::styled(
+                "Notifications...",
+                Style::default().fg(theme.notice),
+            )]
+        };
         let notification = Paragraph::new(t2.iter())
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
-                    .title_style(Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)),
+                    .border_style(theme.border_style(false))
+                    .title_style(theme.title_style()),
             )
             .wrap(true);
 
         f.render_widget(notification, chunks[1]);
 
-        let t3 = vec![
-            Text::styled(&sending_text, Style::default().fg(Color::Blue)),
-            Text::styled(
+        // the grapheme under the cursor is reversed, at the end of the text the blinking
+        // `<` is the cursor
+        let text_style = Style::default().fg(theme.text);
+        let mut t3 = vec![Text::styled(&before, text_style)];
+        match after.graphemes(true).next() {
+            Some(under) => {
+                let cursor = text_style.modifier(Modifier::REVERSED);
+                if under == "\n" {
+                    t3.push(Text::styled(" ", cursor));
+                    t3.push(Text::styled(&after, text_style));
+                } else {
+                    t3.push(Text::styled(under, cursor));
+                    t3.push(Text::styled(&after[under.len()..], text_style));
+                }
+            }
+            None => t3.push(Text::styled(
                 "<",
                 Style::default()
-                    .fg(Color::LightGreen)
+                    .fg(theme.selected)
                     .modifier(Modifier::RAPID_BLINK),
-            ),
-        ];
+            )),
+        }
+        let send_title = match self.draft() {
+            Some(Draft::Reply(_, name)) => format!("Reply to {}", name),
+            Some(Draft::Edit(_)) => "Edit message".to_string(),
+            None => "Send".to_string(),
+        };
         let text_box = Paragraph::new(t3.iter())
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
-                    .title("Send")
-                    .title_style(Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)),
+                    .border_style(theme.border_style(self.composer_focused))
+                    .title(&send_title)
+                    .title_style(theme.title_style()),
             )
             .wrap(true);
 
         f.render_widget(text_box, chunks[2]);
+        self.input_area = chunks[2];
 
         let btn = Layout::default()
             .constraints([Constraint::Percentage(90), Constraint::Percentage(10)].as_ref())
@@ -650,13 +1316,166 @@ impl RenderWidget for MessageWidget {
 
         self.send_area = btn[1];
 
-        let btn_text = vec![Text::styled("Send", Style::default().fg(Color::Blue))];
+        let btn_text = vec![Text::styled("Send", Style::default().fg(theme.text))];
         let button = Paragraph::new(btn_text.iter()).block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
-                .title_style(Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)),
+                .border_style(theme.border_style(false))
+                .title_style(theme.title_style()),
         );
         f.render_widget(button, btn[1]);
     }
 }
+
+/// The links drawn in `area` of `buf`, a link is split where it is wrapped.
+fn drawn_links(buf: &Buffer, area: Rect) -> Vec<(Rect, String)> {
+    let mut links: Vec<(Rect, String)> = vec![];
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            if let Some(link) = buf.get(x, y).link.as_ref() {
+                match links.last_mut() {
+                    Some((drawn, url)) if url == link && drawn.y == y && drawn.right() == x => {
+                        drawn.width += 1
+                    }
+                    _ => links.push((Rect::new(x, y, 1, 1), link.clone())),
+                }
+            }
+        }
+    }
+    links
+}
+
+/// The first URL in `text`, the one a preview is shown for.
+fn first_link(text: &str) -> Option<&str> {
+    find_links(text)
+        .into_iter()
+        .next()
+        .map(|range| &text[range])
+}
+
+/// The lines of a preview card, indented under the message like reactions.
+fn preview_card(preview: &UrlPreview, theme: &Theme) -> Vec<Text<'static>> {
+    let border = Style::default().fg(theme.dim);
+    let mut card = vec![];
+    let title = match (preview.site.as_ref(), preview.title.as_ref()) {
+        (Some(site), Some(title)) => Some(format!("{} - {}", site, title)),
+        (site, title) => title.or(site).cloned(),
+    };
+    if let Some(title) = title {
+        card.push(Text::styled("\u{2800}   \u{2502} ", border));
+        card.push(Text::styled(
+            format!("{}\n", title),
+            Style::default()
+                .fg(theme.secondary)
+                .modifier(Modifier::BOLD),
+        ));
+    }
+    if let Some(description) = preview.description.as_ref() {
+        card.push(Text::styled("\u{2800}   \u{2502} ", border));
+        card.push(Text::raw(format!("{}\n", description)));
+    }
+    card
+}
+
+/// The number of lines `text` takes up when lines are broken at `width`.
+fn wrapped_height(text: &str, width: usize) -> usize {
+    let width = width.max(1);
+    text.lines()
+        .map(|line| (line.graphemes(true).count().max(1) + width - 1) / width)
+        .sum()
+}
+
+/// Shows `text` as part of the selected message.
+fn highlight<'a>(text: Text<'a>, theme: &Theme) -> Text<'a> {
+    match text {
+        Text::Raw(body) => Text::Styled(body, Style::default().bg(theme.highlight)),
+        Text::Styled(body, style) => Text::Styled(body, style.bg(theme.highlight)),
+        Text::Link(body, style, url) => Text::Link(body, style.bg(theme.highlight), url),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn widget(room: &RoomId) -> MessageWidget {
+        let mut widget = MessageWidget::default();
+        widget.me = Some(UserId::try_from("@me:example.org").unwrap());
+        *widget.current_room.borrow_mut() = Some(room.clone());
+        widget
+    }
+
+    fn text(body: &str) -> MessageEventContent {
+        MessageEventContent::Text(TextMessageEventContent::new_plain(body))
+    }
+
+    #[test]
+    fn echoes_are_reconciled() {
+        let room = RoomId::try_from("!room:example.org").unwrap();
+        let mut widget = widget(&room);
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        widget.echo_sent_msg(&room, "me".into(), first, text("first"));
+        widget.echo_sent_msg(&room, "me".into(), second, text("second"));
+
+        let echoes = &widget.messages[&room];
+        assert_eq!(echoes.len(), 2);
+        assert_ne!(echoes[0].event_id, echoes[1].event_id);
+        assert_eq!(echoes[0].send_state, Some(SendState::Pending));
+
+        // the send response arrives first
+        let event_id = EventId::try_from("$first:example.org").unwrap();
+        widget.set_sent(&room, &first, event_id.clone());
+        let reaction = EventId::try_from("$reaction:example.org").unwrap();
+        widget.set_reaction_event(&room, &event_id, &reaction, "👍");
+
+        // then the synced event replaces the echo and keeps the reaction
+        let mut synced = widget.messages[&room][0].clone();
+        synced.send_state = None;
+        synced.reactions = vec![];
+        widget.add_message(synced, &room);
+
+        let messages = &widget.messages[&room];
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].event_id, event_id);
+        assert_eq!(messages[0].send_state, Some(SendState::Sent(event_id)));
+        assert_eq!(messages[0].reactions.len(), 1);
+        assert_eq!(messages[1].send_state, Some(SendState::Pending));
+    }
+
+    #[test]
+    fn selection_moves_through_messages() {
+        let room = RoomId::try_from("!room:example.org").unwrap();
+        let mut widget = widget(&room);
+        widget.msg_area = Rect::new(0, 0, 40, 6);
+        for body in &["one", "two", "three", "four", "five", "six"] {
+            widget.echo_sent_msg(&room, "me".into(), Uuid::new_v4(), text(body));
+        }
+        // without "sending..." under them every message is a single line
+        for msg in widget.messages.get_mut(&room).unwrap() {
+            msg.send_state = None;
+        }
+        let body = |widget: &MessageWidget| widget.selected_message().map(|msg| msg.text.clone());
+
+        assert!(!widget.select_previous_message());
+        assert_eq!(body(&widget).as_deref(), Some("six"));
+        widget.select_previous_message();
+        assert_eq!(body(&widget).as_deref(), Some("five"));
+
+        // the messages after the first are one line and four lines fit between the borders
+        assert!(!widget.select_page_up());
+        assert_eq!(body(&widget).as_deref(), Some("one"));
+        assert!(widget.select_previous_message());
+        // the first message has the day separator and the group's header above it
+        widget.select_page_down();
+        assert_eq!(body(&widget).as_deref(), Some("three"));
+        widget.select_page_down();
+        assert_eq!(body(&widget).as_deref(), Some("six"));
+
+        widget.select_next_message();
+        assert_eq!(widget.selected_event_id(), None);
+        assert_eq!(widget.scroll_pos, 0);
+
+        assert_eq!(wrapped_height("abcdef\nab\n", 4), 3);
+    }
+}
@@ -0,0 +1,229 @@
+//! Timestamps, day separators and sender headers in the timeline.
+//!
+//! Consecutive messages from one sender share a header with the sender's name and the
+//! time of the first message. The look is configured by `~/.rumatui/timeline.json`
+//!
+//! ```json
+//! { "timestamps": "absolute", "time_format": "%H:%M", "group_minutes": 5 }
+//! ```
+//!
+//! every field can be left out.
+
+use std::{fmt::Write, fs, path::Path, time::SystemTime};
+
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
+use serde::Deserialize;
+
+use crate::{error::Result, widgets::message::Message};
+
+/// How the time a message was sent is shown.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Timestamps {
+    /// "just now", "5m ago" or "3h ago", messages from before today use `time_format`.
+    Relative,
+    /// The local time in `time_format`.
+    Absolute,
+    Off,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TimelineSettings {
+    pub timestamps: Timestamps,
+    /// A `chrono` format string.
+    pub time_format: String,
+    /// Messages from the same sender this many minutes apart or less share a header,
+    /// zero gives every message its own header.
+    pub group_minutes: i64,
+}
+
+impl Default for TimelineSettings {
+    fn default() -> Self {
+        Self {
+            timestamps: Timestamps::Absolute,
+            time_format: "%H:%M".to_string(),
+            group_minutes: 5,
+        }
+    }
+}
+
+impl TimelineSettings {
+    /// Loads the settings, a missing file means the default settings.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = fs::read_to_string(path)?;
+        let mut settings: Self = serde_json::from_str(&json)?;
+        // formatting with an invalid format string panics so it is checked once here
+        if !valid_time_format(&settings.time_format) {
+            tracing::warn!(
+                "invalid time_format {:?}, using the default",
+                settings.time_format
+            );
+            settings.time_format = Self::default().time_format;
+        }
+        Ok(settings)
+    }
+
+    /// The time shown in the header of a message sent at `time`, `None` when
+    /// timestamps are off.
+    pub fn timestamp(&self, time: SystemTime, now: DateTime<Local>) -> Option<String> {
+        let time = DateTime::<Local>::from(time);
+        let ago = now.signed_duration_since(time);
+        match self.timestamps {
+            Timestamps::Off => None,
+            Timestamps::Relative if ago < Duration::minutes(1) => Some("just now".to_string()),
+            Timestamps::Relative if ago < Duration::hours(1) => {
+                Some(format!("{}m ago", ago.num_minutes()))
+            }
+            Timestamps::Relative if time.date() == now.date() => {
+                Some(format!("{}h ago", ago.num_hours()))
+            }
+            Timestamps::Relative | Timestamps::Absolute => {
+                Some(time.format(&self.time_format).to_string())
+            }
+        }
+    }
+
+    /// Is `msg` shown under the header of `prev`, the message before it.
+    pub fn continues_group(&self, prev: &Message, msg: &Message) -> bool {
+        let prev_time = DateTime::<Local>::from(prev.timestamp);
+        let time = DateTime::<Local>::from(msg.timestamp);
+        self.group_minutes > 0
+            && prev.user == msg.user
+            && prev_time.date() == time.date()
+            && time.signed_duration_since(prev_time) <= Duration::minutes(self.group_minutes)
+    }
+}
+
+/// The label of the separator before `msg`, `None` when `prev` was sent the same day.
+pub fn day_change(prev: Option<&Message>, msg: &Message, now: DateTime<Local>) -> Option<String> {
+    let day = DateTime::<Local>::from(msg.timestamp).date();
+    if prev.map(|prev| DateTime::<Local>::from(prev.timestamp).date()) == Some(day) {
+        return None;
+    }
+
+    let today = now.date();
+    Some(if day == today {
+        "Today".to_string()
+    } else if day == today.pred() {
+        "Yesterday".to_string()
+    } else {
+        day.format("%A, %B %-d, %Y").to_string()
+    })
+}
+
+/// Can `format` be used to format a time, chrono only reports an invalid format while
+/// formatting.
+fn valid_time_format(format: &str) -> bool {
+    let mut out = String::new();
+    write!(
+        out,
+        "{}",
+        Utc.ymd(2020, 1, 1).and_hms(0, 0, 0).format(format)
+    )
+    .is_ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use chrono::TimeZone;
+    use matrix_sdk::identifiers::{EventId, UserId};
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn message(user: &str, time: DateTime<Local>) -> Message {
+        Message {
+            name: user.to_string(),
+            text: "hello".to_string(),
+            user: UserId::try_from(format!("@{}:example.org", user).as_str()).unwrap(),
+            event_id: EventId::try_from("$event:example.org").unwrap(),
+            read: true,
+            reactions: vec![],
+            sent_receipt: true,
+            timestamp: SystemTime::from(time),
+            uuid: Uuid::new_v4(),
+            send_state: None,
+        }
+    }
+
+    #[test]
+    fn timestamps() {
+        let now = Local.ymd(2020, 6, 3).and_hms(12, 0, 0);
+        let mut settings = TimelineSettings::default();
+        let time = |h, m| SystemTime::from(Local.ymd(2020, 6, 3).and_hms(h, m, 0));
+
+        assert_eq!(
+            settings.timestamp(time(9, 5), now).as_deref(),
+            Some("09:05")
+        );
+
+        settings.timestamps = Timestamps::Relative;
+        assert_eq!(
+            settings.timestamp(time(12, 0), now).as_deref(),
+            Some("just now")
+        );
+        assert_eq!(
+            settings.timestamp(time(11, 55), now).as_deref(),
+            Some("5m ago")
+        );
+        assert_eq!(
+            settings.timestamp(time(9, 5), now).as_deref(),
+            Some("2h ago")
+        );
+        let yesterday = SystemTime::from(Local.ymd(2020, 6, 2).and_hms(23, 0, 0));
+        assert_eq!(settings.timestamp(yesterday, now).as_deref(), Some("23:00"));
+
+        settings.timestamps = Timestamps::Off;
+        assert_eq!(settings.timestamp(time(9, 5), now), None);
+    }
+
+    #[test]
+    fn invalid_time_format() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("rumatui-timeline-{}.json", Uuid::new_v4()));
+        fs::write(&path, r#"{ "time_format": "%Q" }"#).unwrap();
+
+        let settings = TimelineSettings::load(&path).unwrap();
+        assert_eq!(
+            settings.time_format,
+            TimelineSettings::default().time_format
+        );
+        assert!(valid_time_format("%Y-%m-%d %H:%M"));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn groups_and_days() {
+        let now = Local.ymd(2020, 6, 3).and_hms(12, 0, 0);
+        let settings = TimelineSettings::default();
+        let first = message("alice", Local.ymd(2020, 6, 2).and_hms(23, 58, 0));
+        let same_day = message("alice", Local.ymd(2020, 6, 2).and_hms(23, 59, 0));
+        let next_day = message("alice", Local.ymd(2020, 6, 3).and_hms(0, 1, 0));
+        let later = message("alice", Local.ymd(2020, 6, 3).and_hms(0, 30, 0));
+        let other = message("bob", Local.ymd(2020, 6, 3).and_hms(0, 31, 0));
+
+        assert!(settings.continues_group(&first, &same_day));
+        // a new day always starts a new group
+        assert!(!settings.continues_group(&same_day, &next_day));
+        assert!(!settings.continues_group(&next_day, &later));
+        assert!(!settings.continues_group(&later, &other));
+
+        assert_eq!(day_change(None, &first, now).as_deref(), Some("Yesterday"));
+        assert_eq!(day_change(Some(&first), &same_day, now), None);
+        assert_eq!(
+            day_change(Some(&same_day), &next_day, now).as_deref(),
+            Some("Today")
+        );
+        let old = message("bob", Local.ymd(2020, 5, 1).and_hms(8, 0, 0));
+        assert_eq!(
+            day_change(None, &old, now).as_deref(),
+            Some("Friday, May 1, 2020")
+        );
+    }
+}
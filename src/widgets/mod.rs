@@ -4,16 +4,27 @@ use rumatui_tui::{backend::Backend, layout::Rect, Frame, Terminal};
 
 pub mod app;
 pub mod chat;
+pub mod colors;
+pub mod command;
+pub mod completion;
 mod error;
+pub mod focus;
 pub mod login;
+pub mod members;
 pub mod message;
 pub mod register;
 pub mod room_search;
 pub mod rooms;
+pub mod settings;
+pub mod text_input;
+pub mod theme;
+pub mod toast;
 pub mod utils;
 
+use theme::Theme;
+
 pub trait RenderWidget {
-    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect)
+    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect, theme: &Theme)
     where
         B: Backend;
 }
@@ -1,13 +1,13 @@
 use rumatui_tui::{
     backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     widgets::{Block, Borders, Paragraph, Text},
     Frame,
 };
 use termion::event::MouseButton;
 
-use crate::widgets::{login::Loading, RenderWidget};
+use crate::widgets::{login::Loading, theme::Theme, RenderWidget};
 
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -64,7 +64,7 @@ impl RegisterWidget {
 }
 
 impl RenderWidget for RegisterWidget {
-    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect)
+    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect, theme: &Theme)
     where
         B: Backend,
     {
@@ -84,7 +84,7 @@ impl RenderWidget for RegisterWidget {
         let register = &format!("Register account on {}", server);
         let blk = Block::default()
             .title(register)
-            .title_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
+            .title_style(Style::default().fg(theme.border).modifier(Modifier::BOLD))
             .borders(Borders::ALL);
         f.render_widget(blk, chunks[1]);
 
@@ -117,12 +117,12 @@ impl RenderWidget for RegisterWidget {
             self.waiting.tick(width_chunk1[1].width);
             let blk = Block::default()
                 .title("Registering")
-                .border_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
+                .border_style(Style::default().fg(theme.accent).modifier(Modifier::BOLD))
                 .borders(Borders::ALL);
 
             let t = [Text::styled(
                 "*".repeat(self.waiting.count),
-                Style::default().fg(Color::Magenta),
+                Style::default().fg(theme.accent),
             )];
             let p = Paragraph::new(t.iter())
                 .block(blk)
@@ -134,7 +134,7 @@ impl RenderWidget for RegisterWidget {
                 (
                     Block::default()
                         .title("User Name")
-                        .border_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
+                        .border_style(Style::default().fg(theme.accent).modifier(Modifier::BOLD))
                         .borders(Borders::ALL),
                     Block::default().title("Password").borders(Borders::ALL),
                 )
@@ -143,7 +143,7 @@ impl RenderWidget for RegisterWidget {
                     Block::default().title("User Name").borders(Borders::ALL),
                     Block::default()
                         .title("Password")
-                        .border_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
+                        .border_style(Style::default().fg(theme.accent).modifier(Modifier::BOLD))
                         .borders(Borders::ALL),
                 )
             };
@@ -167,7 +167,7 @@ impl RenderWidget for RegisterWidget {
             // User name
             let t = [Text::styled(
                 &self.register.username,
-                Style::default().fg(Color::Cyan),
+                Style::default().fg(theme.secondary),
             )];
             let p = Paragraph::new(t.iter()).block(high_user);
 
@@ -176,7 +176,7 @@ impl RenderWidget for RegisterWidget {
             // Password from here down
             let t2 = [Text::styled(
                 "*".repeat(self.register.password.len()),
-                Style::default().fg(Color::Cyan),
+                Style::default().fg(theme.secondary),
             )];
             let p2 = Paragraph::new(t2.iter()).block(high_pass);
 
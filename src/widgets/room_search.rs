@@ -10,12 +10,12 @@ use matrix_sdk::{
 use rumatui_tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     widgets::{Block, Borders, List, ListState as ListTrack, Paragraph, Text},
     Frame,
 };
 
-use crate::widgets::{rooms::ListState, RenderWidget};
+use crate::widgets::{rooms::ListState, theme::Theme, RenderWidget};
 
 #[derive(Clone, Debug, Default)]
 pub struct RoomSearchWidget {
@@ -119,7 +119,7 @@ impl RoomSearchWidget {
 }
 
 impl RenderWidget for RoomSearchWidget {
-    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect)
+    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect, theme: &Theme)
     where
         B: Backend,
     {
@@ -144,9 +144,7 @@ impl RenderWidget for RoomSearchWidget {
         let list_height = area.height as usize;
         // Use highlight_style only if something is selected
         let selected = self.names.selected;
-        let highlight_style = Style::default()
-            .fg(Color::LightGreen)
-            .modifier(Modifier::BOLD);
+        let highlight_style = theme.selected_style();
         let highlight_symbol = ">>";
         // Make sure the list show the selected item
         let offset = {
@@ -190,7 +188,7 @@ impl RenderWidget for RoomSearchWidget {
                         .modifier(highlight_style.modifier);
                     Text::styled(format!("{} {}", highlight_symbol, name), style)
                 } else {
-                    let style = Style::default().fg(Color::Blue);
+                    let style = Style::default().fg(theme.text);
                     Text::styled(format!(" {}", name), style)
                 }
             })
@@ -200,36 +198,36 @@ impl RenderWidget for RoomSearchWidget {
                 Block::default()
                     .borders(Borders::ALL)
                     .title("Public Rooms")
-                    .border_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
-                    .title_style(Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)),
+                    .border_style(theme.border_style(true))
+                    .title_style(theme.title_style()),
             )
-            .style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD));
+            .style(Style::default().fg(theme.accent).modifier(Modifier::BOLD));
         f.render_widget(list, chunks[1]);
 
         let mut topic = found_topic.unwrap_or_default();
         topic.push_str("    ");
 
         let t = vec![
-            Text::styled(&topic, Style::default().fg(Color::Blue)),
-            Text::styled(&details, Style::default().fg(Color::LightGreen)),
+            Text::styled(&topic, Style::default().fg(theme.text)),
+            Text::styled(&details, Style::default().fg(theme.selected)),
         ];
         let room_topic = Paragraph::new(t.iter())
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
+                    .border_style(theme.border_style(false))
                     .title("Room Topic")
-                    .title_style(Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)),
+                    .title_style(theme.title_style()),
             )
             .wrap(true);
         f.render_widget(room_topic, chunks[0]);
 
         let t3 = vec![
-            Text::styled(&self.search_term, Style::default().fg(Color::Blue)),
+            Text::styled(&self.search_term, Style::default().fg(theme.text)),
             Text::styled(
                 "<",
                 Style::default()
-                    .fg(Color::LightGreen)
+                    .fg(theme.selected)
                     .modifier(Modifier::RAPID_BLINK),
             ),
         ];
@@ -237,9 +235,9 @@ impl RenderWidget for RoomSearchWidget {
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
+                    .border_style(theme.border_style(true))
                     .title("Send")
-                    .title_style(Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)),
+                    .title_style(theme.title_style()),
             )
             .wrap(true);
 
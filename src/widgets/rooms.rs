@@ -1,6 +1,6 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     ops::{DerefMut, Index, IndexMut},
     rc::Rc,
     sync::Arc,
@@ -14,7 +14,7 @@ use matrix_sdk::{
 use rumatui_tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     widgets::{Block, Borders, List, Paragraph, Text},
     Frame,
 };
@@ -22,7 +22,10 @@ use serde::{Deserialize, Serialize};
 use termion::event::MouseButton;
 use tokio::sync::RwLock;
 
-use crate::widgets::RenderWidget;
+use crate::{
+    client::ruma_ext::account_data::Tags,
+    widgets::{theme::Theme, RenderWidget},
+};
 
 // TODO: Use format_simple to highlight which characters have been matched in the quick-select search
 #[allow(unused_imports)]
@@ -91,15 +94,6 @@ impl<I: std::fmt::Debug> ListState<I> {
     }
 }
 
-impl ListState<(String, RoomId)> {
-    pub(crate) fn add_unique(&mut self, name: String, room_id: RoomId) {
-        // TODO not great to clone for a Eq...
-        if !self.items.contains(&(name.clone(), room_id.clone())) {
-            self.items.push((name, room_id));
-        }
-    }
-}
-
 impl<I> Index<usize> for ListState<I> {
     type Output = I;
     fn index(&self, idx: usize) -> &Self::Output {
@@ -112,6 +106,180 @@ impl<I> IndexMut<usize> for ListState<I> {
     }
 }
 
+/// The section of the room list a room is shown in.
+///
+/// The order of the variants is the order the sections are displayed.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SectionKind {
+    /// Rooms tagged `m.favourite`.
+    Favourite,
+    /// Rooms tagged with a custom `u.*` tag, holds the tag without the `u.` prefix.
+    Tag(String),
+    /// Every room that is not tagged or a direct message.
+    Rooms,
+    /// Rooms from the `m.direct` account data.
+    Direct,
+    /// Rooms tagged `m.lowpriority`.
+    LowPriority,
+}
+
+impl SectionKind {
+    pub fn title(&self) -> &str {
+        match self {
+            Self::Favourite => "Favourites",
+            Self::Tag(tag) => tag,
+            Self::Rooms => "Rooms",
+            Self::Direct => "Direct Messages",
+            Self::LowPriority => "Low priority",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Section<I> {
+    pub kind: SectionKind,
+    pub collapsed: bool,
+    pub items: Vec<I>,
+}
+
+/// A row of a `SectionedList`, either a section header or an item of an expanded section.
+#[derive(Debug)]
+pub enum Row<'a, I> {
+    Header(&'a Section<I>),
+    Item(&'a I),
+}
+
+/// A list of items grouped into collapsible sections.
+///
+/// Selection moves over the visible rows, the section headers and the items of
+/// every expanded section.
+#[derive(Clone, Debug)]
+pub struct SectionedList<I> {
+    pub sections: Vec<Section<I>>,
+    selected: usize,
+}
+
+impl<I> Default for SectionedList<I> {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl<I> SectionedList<I> {
+    pub fn new(sections: Vec<Section<I>>) -> SectionedList<I> {
+        SectionedList {
+            sections,
+            selected: 0,
+        }
+    }
+
+    /// All visible rows in display order.
+    pub fn rows(&self) -> impl Iterator<Item = Row<'_, I>> {
+        self.sections.iter().flat_map(|section| {
+            let items = if section.collapsed {
+                &section.items[..0]
+            } else {
+                &section.items[..]
+            };
+            std::iter::once(Row::Header(section)).chain(items.iter().map(Row::Item))
+        })
+    }
+
+    /// The number of visible rows.
+    pub fn len(&self) -> usize {
+        self.sections
+            .iter()
+            .map(|s| if s.collapsed { 1 } else { s.items.len() + 1 })
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// Every item in every section, including the items of collapsed sections.
+    pub fn items(&self) -> impl Iterator<Item = &I> {
+        self.sections.iter().flat_map(|s| s.items.iter())
+    }
+
+    /// Scrolls back up the list
+    pub fn select_previous(&mut self) {
+        if self.selected != 0 {
+            self.selected -= 1;
+        }
+    }
+
+    /// Scrolls down the list
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.len() {
+            self.selected += 1
+        }
+    }
+
+    /// Gets the index of the selected row.
+    pub fn selected_idx(&self) -> usize {
+        self.selected
+    }
+
+    /// Selects the row at `idx`, returns false if there is no such row.
+    pub fn select_row(&mut self, idx: usize) -> bool {
+        if idx < self.len() {
+            self.selected = idx;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Selects the first visible row matching `pred`.
+    pub fn select_item<P: Fn(&I) -> bool>(&mut self, pred: P) -> bool {
+        let found = self.rows().position(|row| match row {
+            Row::Item(item) => pred(item),
+            Row::Header(_) => false,
+        });
+        if let Some(idx) = found {
+            self.selected = idx;
+        }
+        found.is_some()
+    }
+
+    /// The selected item, `None` when a section header is selected.
+    pub fn get_selected(&self) -> Option<&I> {
+        match self.rows().nth(self.selected) {
+            Some(Row::Item(item)) => Some(item),
+            _ => None,
+        }
+    }
+
+    /// The index of the section the selected row belongs to.
+    pub fn selected_section(&self) -> Option<usize> {
+        let mut row = 0;
+        for (idx, section) in self.sections.iter().enumerate() {
+            row += if section.collapsed {
+                1
+            } else {
+                section.items.len() + 1
+            };
+            if self.selected < row {
+                return Some(idx);
+            }
+        }
+        None
+    }
+
+    /// Collapses or expands the section of the selected row, the selection moves
+    /// to the section header.
+    pub fn toggle_selected_section(&mut self) -> Option<&Section<I>> {
+        let idx = self.selected_section()?;
+        self.sections[idx].collapsed = !self.sections[idx].collapsed;
+        self.selected = self.sections[..idx]
+            .iter()
+            .map(|s| if s.collapsed { 1 } else { s.items.len() + 1 })
+            .sum();
+        Some(&self.sections[idx])
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Invitation {
     pub(crate) room_id: RoomId,
@@ -125,15 +293,43 @@ pub enum Invite {
     NoClick,
 }
 
+/// The text inputs shown at the bottom of the `RoomsWidget`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RoomInputKind {
+    /// The user id to start a direct message with.
+    StartDm,
+    /// The tag to add or remove from the selected room.
+    Tag,
+}
+
+impl RoomInputKind {
+    fn title(&self) -> &'static str {
+        match self {
+            Self::StartDm => "Start DM (@user:server)",
+            Self::Tag => "Tag or untag room (favourite, lowpriority or a name)",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RoomInput {
+    pub kind: RoomInputKind,
+    pub text: String,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct RoomsWidget {
     area: Rect,
     yes_area: Rect,
     no_area: Rect,
+    /// The first row shown in the room list, used to find the clicked row.
+    list_offset: usize,
     /// This is the RoomId of the last used room, the room to show on startup.
     pub(crate) current_room: Rc<RefCell<Option<RoomId>>>,
-    /// List of displayable room name and room id
-    pub names: ListState<(String, RoomId)>,
+    /// List of displayable room name and room id sorted into sections.
+    pub names: SectionedList<(String, RoomId)>,
+    /// Every displayable room name and room id, `names` is built from this.
+    all_names: Vec<(String, RoomId)>,
     /// Map of room id and matrix_sdk::Room
     pub(crate) rooms: HashMap<RoomId, Arc<RwLock<Room>>>,
     /// When a user receives an invitation an alert pops up in the `RoomsWidget` pane
@@ -141,8 +337,17 @@ pub struct RoomsWidget {
     pub(crate) invite: Option<Invitation>,
     /// Are we filtering (if none: No. If some (even empty string): Yes)
     pub filter_string: Option<String>,
-    // For restoring the original names-list after quick-select finished
-    names_backup: ListState<(String, RoomId)>,
+    /// Map of direct message room id to the user the direct messages are with.
+    ///
+    /// This is built from the `m.direct` account data.
+    pub(crate) direct: HashMap<RoomId, UserId>,
+    /// The `m.tag` room account data of each room.
+    pub(crate) tags: HashMap<RoomId, Tags>,
+    /// The sections the user has collapsed.
+    collapsed: HashSet<SectionKind>,
+    /// The text input shown below the room list, `Some` while the input is shown.
+    pub room_input: Option<RoomInput>,
+    pub(crate) focused: bool,
 }
 
 impl RoomsWidget {
@@ -166,41 +371,192 @@ impl RoomsWidget {
             if r.tombstone.is_some() {
                 continue;
             }
-            items.push((r.display_name(), id.clone()));
+            items.push((self.room_name(&r), id.clone()));
         }
 
-        self.names = ListState::new(items);
-        self.names.items.first().map(|r| &r.1)
+        self.all_names = items;
+        self.rebuild_sections();
+        self.names.items().next().map(|r| &r.1)
     }
 
-    pub(crate) async fn add_room(&mut self, room: Arc<RwLock<Room>>) {
+    /// Updates which rooms are direct messages from the `m.direct` account data.
+    ///
+    /// Direct message rooms are relabeled with the other user's display name and
+    /// moved into their own section.
+    pub(crate) async fn set_direct_rooms(&mut self, direct: BTreeMap<UserId, Vec<RoomId>>) {
+        self.direct = direct
+            .into_iter()
+            .flat_map(|(user, rooms)| rooms.into_iter().map(move |id| (id, user.clone())))
+            .collect();
+
+        for (name, id) in self.all_names.iter_mut() {
+            if let Some(room) = self.rooms.get(id) {
+                let r = room.read().await;
+                *name = if let Some(user) = self.direct.get(id) {
+                    direct_name(&r, user)
+                } else {
+                    r.display_name()
+                };
+            }
+        }
+        self.rebuild_sections();
+    }
+
+    /// Is this room a direct message room.
+    pub(crate) fn is_direct(&self, room_id: &RoomId) -> bool {
+        self.direct.contains_key(room_id)
+    }
+
+    /// Replaces the `m.tag` account data of every room.
+    pub(crate) fn set_all_tags(&mut self, tags: HashMap<RoomId, Tags>) {
+        self.tags = tags;
+        self.rebuild_sections();
+    }
+
+    /// Replaces the `m.tag` account data of a single room.
+    pub(crate) fn set_room_tags(&mut self, room_id: &RoomId, tags: Tags) {
+        self.tags.insert(room_id.clone(), tags);
+        self.rebuild_sections();
+    }
+
+    /// Is the room tagged with `tag`.
+    pub(crate) fn has_tag(&self, room_id: &RoomId, tag: &str) -> bool {
+        self.tags
+            .get(room_id)
+            .map(|tags| tags.contains_key(tag))
+            .unwrap_or(false)
+    }
+
+    /// The name shown in the room list, direct messages show the other user's display name.
+    fn room_name(&self, room: &Room) -> String {
+        if let Some(user) = self.direct.get(&room.room_id) {
+            direct_name(room, user)
+        } else {
+            room.display_name()
+        }
+    }
+
+    /// The sections a room belongs to, a room with more than one tag is shown in each section.
+    fn room_sections(&self, room_id: &RoomId) -> Vec<SectionKind> {
+        let mut sections = self
+            .tags
+            .get(room_id)
+            .map(|tags| {
+                tags.keys()
+                    .filter_map(|tag| match tag.as_str() {
+                        "m.favourite" => Some(SectionKind::Favourite),
+                        "m.lowpriority" => Some(SectionKind::LowPriority),
+                        tag if tag.starts_with("u.") => {
+                            Some(SectionKind::Tag(tag.trim_start_matches("u.").to_string()))
+                        }
+                        // tags of other clients are not shown
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        if sections.is_empty() {
+            if self.is_direct(room_id) {
+                sections.push(SectionKind::Direct);
+            } else {
+                sections.push(SectionKind::Rooms);
+            }
+        }
+        sections
+    }
+
+    /// The order of a room within the section of `kind` from the tag's `order`.
+    fn tag_order(&self, room_id: &RoomId, kind: &SectionKind) -> Option<f64> {
+        let tag = match kind {
+            SectionKind::Favourite => "m.favourite".to_string(),
+            SectionKind::LowPriority => "m.lowpriority".to_string(),
+            SectionKind::Tag(tag) => format!("u.{}", tag),
+            SectionKind::Rooms | SectionKind::Direct => return None,
+        };
+        self.tags.get(room_id)?.get(&tag)?.order
+    }
+
+    /// Sorts `items` into sections, keeping the selected room selected.
+    fn build_sections(&mut self, items: &[(String, RoomId)]) {
+        let selected = self.names.get_selected().map(|(_, id)| id.clone());
+
+        let mut sections: BTreeMap<SectionKind, Vec<(String, RoomId)>> = BTreeMap::new();
+        for (name, id) in items.iter().unique_by(|(_, id)| id) {
+            for kind in self.room_sections(id) {
+                sections
+                    .entry(kind)
+                    .or_default()
+                    .push((name.clone(), id.clone()));
+            }
+        }
+
+        self.names = SectionedList::new(
+            sections
+                .into_iter()
+                .map(|(kind, mut items)| {
+                    // rooms without an order come after ordered rooms, see the `m.tag` spec
+                    items.sort_by(|(_, a), (_, b)| {
+                        let a = self.tag_order(a, &kind).unwrap_or(std::f64::INFINITY);
+                        let b = self.tag_order(b, &kind).unwrap_or(std::f64::INFINITY);
+                        a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+                    });
+                    Section {
+                        collapsed: self.filter_string.is_none() && self.collapsed.contains(&kind),
+                        kind,
+                        items,
+                    }
+                })
+                .collect(),
+        );
+
+        if let Some(id) = selected {
+            self.set_room_selected(&id);
+        } else {
+            self.names.select_item(|_| true);
+        }
+    }
+
+    /// Rebuilds the room list sections from every known room.
+    fn rebuild_sections(&mut self) {
         if self.filter_string.is_some() {
-            return;
+            self.apply_quick_select_filter();
+        } else {
+            let items = std::mem::take(&mut self.all_names);
+            self.build_sections(&items);
+            self.all_names = items;
         }
+    }
+
+    pub(crate) async fn add_room(&mut self, room: Arc<RwLock<Room>>) {
         let r = room.read().await;
-        let name = r.display_name();
+        let name = self.room_name(&r);
         let room_id = r.room_id.clone();
 
         self.rooms.insert(room_id.clone(), Arc::clone(&room));
 
-        self.names.add_unique(name, room_id)
+        if !self.all_names.iter().any(|(_, id)| id == &room_id) {
+            self.all_names.push((name, room_id));
+        }
+        self.rebuild_sections();
     }
 
     pub(crate) fn remove_room(&mut self, room_id: &RoomId) {
-        if self.filter_string.is_some() {
-            return;
-        }
         self.rooms.remove(room_id);
-        if let Some(idx) = self.names.items.iter().position(|(_, id)| room_id == id) {
-            self.names.items.remove(idx);
-        }
-        if !self.names.is_empty() {
-            self.names.select_next();
-            if let Some((_name, room_id)) = self.names.get_selected() {
-                if let Some(id) = self.current_room.borrow_mut().deref_mut() {
-                    *id = room_id.clone();
-                    return;
-                }
+        self.tags.remove(room_id);
+        self.all_names.retain(|(_, id)| room_id != id);
+
+        let row = self.names.selected_idx();
+        self.rebuild_sections();
+        // select the room that took the place of the removed room
+        self.names.select_row(row);
+        if self.names.get_selected().is_none() {
+            self.names.select_item(|_| true);
+        }
+        if let Some((_name, room_id)) = self.names.get_selected() {
+            if let Some(id) = self.current_room.borrow_mut().deref_mut() {
+                *id = room_id.clone();
+                return;
             }
         }
         // else no room found so remove the current room
@@ -208,11 +564,13 @@ impl RoomsWidget {
     }
 
     pub(crate) fn update_room(&mut self, name: &str, room_id: &RoomId) {
-        if self.filter_string.is_some() {
+        // direct messages keep the other user's name
+        if self.is_direct(room_id) {
             return;
         }
-        if let Some(idx) = self.names.items.iter().position(|(_, id)| room_id == id) {
-            self.names.items[idx] = (name.to_string(), room_id.clone());
+        if let Some(idx) = self.all_names.iter().position(|(_, id)| room_id == id) {
+            self.all_names[idx] = (name.to_string(), room_id.clone());
+            self.rebuild_sections();
         }
     }
 
@@ -241,6 +599,35 @@ impl RoomsWidget {
         Invite::NoClick
     }
 
+    /// Selects the clicked room, clicking a section header collapses or expands the section.
+    ///
+    /// Returns true if a different room was selected.
+    pub fn on_click_list(&mut self, btn: MouseButton, x: u16, y: u16) -> bool {
+        if !self.area.intersects(Rect::new(x, y, 1, 1)) || btn != MouseButton::Left {
+            return false;
+        }
+        // the first line of the area is the block's border
+        if y <= self.area.y {
+            return false;
+        }
+        let row = (y - self.area.y - 1) as usize + self.list_offset;
+        if !self.names.select_row(row) {
+            return false;
+        }
+        if self.names.get_selected().is_some() {
+            let before = self.current_room.borrow().clone();
+            self.set_current_from_selected();
+            before != *self.current_room.borrow()
+        } else {
+            self.toggle_section();
+            false
+        }
+    }
+
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        self.area.intersects(Rect::new(x, y, 1, 1))
+    }
+
     pub fn on_scroll_up(&mut self, x: u16, y: u16) -> bool {
         if self.area.intersects(Rect::new(x, y, 1, 1)) {
             self.select_previous();
@@ -257,37 +644,58 @@ impl RoomsWidget {
         false
     }
 
-    /// Moves selection down the list
-    pub fn select_next(&mut self) {
-        self.names.select_next();
+    fn set_current_from_selected(&mut self) {
         if let Some((_name, id)) = self.names.get_selected() {
             *self.current_room.borrow_mut() = Some(id.clone());
         }
     }
 
+    /// Moves selection down the list
+    pub fn select_next(&mut self) {
+        self.names.select_next();
+        self.set_current_from_selected();
+    }
+
     /// Moves the selection up the list
     pub fn select_previous(&mut self) {
         self.names.select_previous();
-        if let Some((_name, id)) = self.names.get_selected() {
-            *self.current_room.borrow_mut() = Some(id.clone());
-        }
+        self.set_current_from_selected();
+    }
+
+    pub fn select_first(&mut self) {
+        self.names.select_row(0);
+        self.set_current_from_selected();
+    }
+
+    pub fn select_last(&mut self) {
+        self.names.select_row(self.names.len().saturating_sub(1));
+        self.set_current_from_selected();
     }
 
     pub fn set_room_selected(&mut self, room_id: &RoomId) {
-        if let Some(idx) = self.names.items.iter().position(|(_, id)| room_id == id) {
-            self.names.selected = idx;
+        self.names.select_item(|(_, id)| room_id == id);
+    }
+
+    /// Collapses or expands the section of the selected row.
+    pub fn toggle_section(&mut self) {
+        if let Some(section) = self.names.toggle_selected_section() {
+            let kind = section.kind.clone();
+            if section.collapsed {
+                self.collapsed.insert(kind);
+            } else {
+                self.collapsed.remove(&kind);
+            }
         }
     }
 
     pub fn start_quick_select_room(&mut self) {
         self.filter_string = Some(String::new());
-        self.names_backup = self.names.clone();
+        self.apply_quick_select_filter();
     }
 
     pub fn quit_quick_select_room(&mut self) {
         self.filter_string = None;
-        self.names = Default::default();
-        std::mem::swap(&mut self.names.items, &mut self.names_backup.items);
+        self.rebuild_sections();
 
         let id = self.current_room.borrow().clone();
         if let Some(id) = id {
@@ -296,35 +704,34 @@ impl RoomsWidget {
     }
 
     fn apply_quick_select_filter(&mut self) {
-        if let Some(needle) = &mut self.filter_string {
+        if let Some(needle) = &self.filter_string {
             if !needle.is_empty() {
                 // Matching against user input. Collecting tuples of the text + match-result
                 let mut vals: Vec<_> = self
-                    .names_backup
-                    .items
+                    .all_names
                     .iter()
                     .map(|(name, id)| (name, id, best_match(needle, name)))
                     .filter(|(_, _, r)| r.as_ref().map_or(0, |res| res.score()) > 0)
                     .collect();
-                if !vals.is_empty() {
-                    // Sort the vec by the match-score
-                    vals.sort_by_cached_key(|(_name, _id, r)| {
-                        r.as_ref().map_or(0, |res| res.score())
-                    });
-                    let first_id = vals[0].1.clone();
-                    self.names.items = vals
-                        .iter()
-                        .map(|(name, id, _)| ((*name).clone(), (*id).clone()))
-                        .collect();
+                // Sort the vec by the match-score
+                vals.sort_by_cached_key(|(_name, _id, r)| r.as_ref().map_or(0, |res| res.score()));
+                let first_id = vals.first().map(|(_, id, _)| (*id).clone());
+                let matches = vals
+                    .into_iter()
+                    .map(|(name, id, _)| (name.clone(), id.clone()))
+                    .collect::<Vec<_>>();
+
+                // No match found, display nothing
+                self.build_sections(&matches);
+                if let Some(first_id) = first_id {
                     self.set_room_selected(&first_id);
                     // If we do NOT want to view the rooms as we type, move this line to quick_quick_select_room() only
                     *self.current_room.borrow_mut() = Some(first_id);
-                } else {
-                    // No match found, display nothing
-                    self.names.items.clear();
                 }
             } else {
-                self.names = self.names_backup.clone();
+                let items = std::mem::take(&mut self.all_names);
+                self.build_sections(&items);
+                self.all_names = items;
             }
         }
     }
@@ -350,10 +757,57 @@ impl RoomsWidget {
     pub fn is_quick_select(&self) -> bool {
         self.filter_string.is_some()
     }
+
+    pub fn start_room_input(&mut self, kind: RoomInputKind) {
+        self.room_input = Some(RoomInput {
+            kind,
+            text: String::new(),
+        });
+    }
+
+    pub fn quit_room_input(&mut self) {
+        self.room_input = None;
+    }
+
+    /// Adds a character to the room input.
+    ///
+    /// Returns the kind of input and the typed text when enter is pressed.
+    pub fn room_input_add_char(&mut self, ch: char) -> Option<(RoomInputKind, String)> {
+        if ch == '\n' {
+            self.room_input
+                .take()
+                .filter(|input| !input.text.is_empty())
+                .map(|input| (input.kind, input.text))
+        } else {
+            if let Some(input) = &mut self.room_input {
+                input.text.push(ch);
+            }
+            None
+        }
+    }
+
+    pub fn room_input_remove_char(&mut self) {
+        if let Some(input) = &mut self.room_input {
+            input.text.pop();
+        }
+    }
+
+    pub fn room_input_kind(&self) -> Option<RoomInputKind> {
+        self.room_input.as_ref().map(|input| input.kind)
+    }
+}
+
+/// The display name of the other user in a direct message room.
+fn direct_name(room: &Room, user: &UserId) -> String {
+    room.joined_members
+        .get(user)
+        .or(room.invited_members.get(user))
+        .map(|mem| mem.name())
+        .unwrap_or(user.localpart().to_string())
 }
 
 impl RenderWidget for RoomsWidget {
-    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect)
+    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect, theme: &Theme)
     where
         B: Backend,
     {
@@ -361,7 +815,7 @@ impl RenderWidget for RoomsWidget {
             Layout::default()
                 .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
                 .split(area)
-        } else if self.filter_string.is_some() {
+        } else if self.filter_string.is_some() || self.room_input.is_some() {
             Layout::default()
                 .constraints([Constraint::Percentage(90), Constraint::Percentage(10)].as_ref())
                 .split(area)
@@ -372,13 +826,12 @@ impl RenderWidget for RoomsWidget {
         };
 
         self.area = chunks[0];
-        let list_height = self.area.height as usize;
+        // the block borders take up two lines
+        let list_height = (self.area.height as usize).saturating_sub(2);
 
         // Use highlight_style only if something is selected
-        let selected = self.names.selected;
-        let highlight_style = Style::default()
-            .fg(Color::LightGreen)
-            .modifier(Modifier::BOLD);
+        let selected = self.names.selected_idx();
+        let highlight_style = theme.selected_style();
         let highlight_symbol = ">>";
         // Make sure the list show the selected item
         let offset = {
@@ -388,24 +841,43 @@ impl RenderWidget for RoomsWidget {
                 0
             }
         };
+        self.list_offset = offset;
 
         // Render items
         let items = self
             .names
-            .items
-            .iter()
-            .unique_by(|(_, id)| id)
+            .rows()
             .enumerate()
-            .map(|(i, (name, _id))| {
-                if i == selected {
-                    let style = Style::default()
-                        .bg(highlight_style.bg)
-                        .fg(highlight_style.fg)
-                        .modifier(highlight_style.modifier);
-                    Text::styled(format!("{} {}", highlight_symbol, name), style)
-                } else {
-                    let style = Style::default().fg(Color::Blue);
-                    Text::styled(format!(" {}", name), style)
+            .map(|(i, row)| match row {
+                Row::Header(section) => {
+                    let style = if i == selected {
+                        Style::default()
+                            .fg(highlight_style.fg)
+                            .modifier(highlight_style.modifier)
+                    } else {
+                        theme.title_style()
+                    };
+                    Text::styled(
+                        format!(
+                            "{} {} ({})",
+                            if section.collapsed { "+" } else { "-" },
+                            section.kind.title(),
+                            section.items.len()
+                        ),
+                        style,
+                    )
+                }
+                Row::Item((name, _id)) => {
+                    if i == selected {
+                        let style = Style::default()
+                            .bg(highlight_style.bg)
+                            .fg(highlight_style.fg)
+                            .modifier(highlight_style.modifier);
+                        Text::styled(format!("{} {}", highlight_symbol, name), style)
+                    } else {
+                        let style = Style::default().fg(theme.text);
+                        Text::styled(format!("   {}", name), style)
+                    }
                 }
             })
             .skip(offset as usize);
@@ -414,15 +886,15 @@ impl RenderWidget for RoomsWidget {
                 Block::default()
                     .borders(Borders::ALL)
                     .title("Rooms")
-                    .border_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
-                    .title_style(Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)),
+                    .border_style(theme.border_style(self.focused))
+                    .title_style(theme.title_style()),
             )
-            .style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD));
+            .style(Style::default().fg(theme.accent).modifier(Modifier::BOLD));
 
         f.render_widget(list, chunks[0]);
 
         if let Some(invite) = self.invite.as_ref() {
-            let label_text = format!("Invited to {}", invite.room_name);
+            let label_text = format!("Invited to {} (a accept, d decline)", invite.room_name);
             let label = Block::default().title(&label_text);
             f.render_widget(label, chunks[1]);
 
@@ -472,7 +944,7 @@ impl RenderWidget for RoomsWidget {
 
             let t = [Text::styled(
                 "Accept invite",
-                Style::default().fg(Color::Cyan),
+                Style::default().fg(theme.secondary),
             )];
             let ok = Paragraph::new(t.iter()).block(yes);
             f.render_widget(ok, width_chunk1[1]);
@@ -480,7 +952,7 @@ impl RenderWidget for RoomsWidget {
             // Password from here down
             let t2 = [Text::styled(
                 "Decline invite",
-                Style::default().fg(Color::Cyan),
+                Style::default().fg(theme.secondary),
             )];
             let nope = Paragraph::new(t2.iter()).block(no);
             f.render_widget(nope, width_chunk2[1])
@@ -488,12 +960,12 @@ impl RenderWidget for RoomsWidget {
             let text_field = vec![
                 Text::styled(
                     self.filter_string.as_ref().unwrap(),
-                    Style::default().fg(Color::Blue),
+                    Style::default().fg(theme.text),
                 ),
                 Text::styled(
                     "<",
                     Style::default()
-                        .fg(Color::LightGreen)
+                        .fg(theme.selected)
                         .modifier(Modifier::RAPID_BLINK),
                 ),
             ];
@@ -501,9 +973,30 @@ impl RenderWidget for RoomsWidget {
                 .block(
                     Block::default()
                         .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
+                        .border_style(theme.border_style(true))
                         .title("Quick select")
-                        .title_style(Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)),
+                        .title_style(theme.title_style()),
+                )
+                .wrap(true);
+
+            f.render_widget(text_box, chunks[1]);
+        } else if let Some(input) = self.room_input.as_ref() {
+            let text_field = vec![
+                Text::styled(&input.text, Style::default().fg(theme.text)),
+                Text::styled(
+                    "<",
+                    Style::default()
+                        .fg(theme.selected)
+                        .modifier(Modifier::RAPID_BLINK),
+                ),
+            ];
+            let text_box = Paragraph::new(text_field.iter())
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(theme.border_style(true))
+                        .title(input.kind.title())
+                        .title_style(theme.title_style()),
                 )
                 .wrap(true);
 
@@ -511,3 +1004,58 @@ impl RenderWidget for RoomsWidget {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sections() -> SectionedList<&'static str> {
+        SectionedList::new(vec![
+            Section {
+                kind: SectionKind::Favourite,
+                collapsed: false,
+                items: vec!["a", "b"],
+            },
+            Section {
+                kind: SectionKind::Rooms,
+                collapsed: false,
+                items: vec!["c"],
+            },
+        ])
+    }
+
+    #[test]
+    fn select_across_sections() {
+        let mut list = sections();
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.get_selected(), None);
+
+        list.select_next();
+        assert_eq!(list.get_selected(), Some(&"a"));
+        list.select_next();
+        list.select_next();
+        assert_eq!(list.get_selected(), None);
+        assert_eq!(list.selected_section(), Some(1));
+        list.select_next();
+        assert_eq!(list.get_selected(), Some(&"c"));
+        list.select_next();
+        assert_eq!(list.get_selected(), Some(&"c"));
+    }
+
+    #[test]
+    fn collapse_section() {
+        let mut list = sections();
+        assert!(list.select_item(|item| *item == "b"));
+
+        let collapsed = list.toggle_selected_section().map(|s| s.collapsed);
+        assert_eq!(collapsed, Some(true));
+        assert_eq!(list.selected_idx(), 0);
+        assert_eq!(list.len(), 3);
+        // items of a collapsed section can not be selected
+        assert!(!list.select_item(|item| *item == "a"));
+
+        list.select_next();
+        list.select_next();
+        assert_eq!(list.get_selected(), Some(&"c"));
+    }
+}
@@ -0,0 +1,111 @@
+//! The settings screen, opened with `/settings`.
+//!
+//! Moving through the list of themes previews each one, Enter keeps the selected theme
+//! and Esc goes back to the theme in use when the screen was opened.
+
+use rumatui_tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    widgets::{Block, Borders, List, Paragraph, Text},
+    Frame,
+};
+
+use crate::widgets::{
+    rooms::ListState,
+    theme::{Theme, COLORS},
+    RenderWidget,
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct SettingsWidget {
+    themes: ListState<String>,
+    /// The theme in use when the screen was opened.
+    original: String,
+}
+
+impl SettingsWidget {
+    /// A list of the themes called `names` with the `active` theme selected.
+    pub fn new(names: Vec<String>, active: &str) -> Self {
+        let selected = names
+            .iter()
+            .position(|name| name == active)
+            .unwrap_or_default();
+        let mut themes = ListState::new(names);
+        themes.selected = selected;
+        Self {
+            themes,
+            original: active.to_string(),
+        }
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.themes.get_selected().map(String::as_str)
+    }
+
+    /// The name of the theme to go back to when the screen is closed without a choice.
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    pub fn select_next(&mut self) {
+        self.themes.select_next()
+    }
+
+    pub fn select_previous(&mut self) {
+        self.themes.select_previous()
+    }
+}
+
+impl RenderWidget for SettingsWidget {
+    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect, theme: &Theme)
+    where
+        B: Backend,
+    {
+        let chunks = Layout::default()
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+            .direction(Direction::Horizontal)
+            .split(area);
+
+        let selected = self.themes.selected_idx();
+        let items = self.themes.iter().enumerate().map(|(i, name)| {
+            if i == selected {
+                Text::styled(format!(">> {}", name), theme.selected_style())
+            } else {
+                Text::styled(format!("   {}", name), Style::default().fg(theme.text))
+            }
+        });
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Themes (Enter keep, Esc cancel)")
+                .border_style(theme.border_style(true))
+                .title_style(theme.title_style()),
+        );
+        f.render_widget(list, chunks[0]);
+
+        // a swatch of every color of the theme being previewed
+        let swatch = COLORS
+            .iter()
+            .filter_map(|key| Some((key, theme.color(key)?)))
+            .flat_map(|(key, color)| {
+                vec![
+                    Text::styled(
+                        "\u{2588}\u{2588}\u{2588}\u{2588} ",
+                        Style::default().fg(color),
+                    ),
+                    Text::styled(format!("{}\n", key), Style::default().fg(color)),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let title = format!("Preview of {}", theme.name);
+        let preview = Paragraph::new(swatch.iter()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(&title)
+                .border_style(theme.border_style(false))
+                .title_style(theme.title_style()),
+        );
+        f.render_widget(preview, chunks[1]);
+    }
+}
@@ -0,0 +1,345 @@
+//! A multi-line text input with a cursor and a history of submitted text.
+//!
+//! All movement and deletion is by grapheme cluster so the cursor never ends
+//! up in the middle of an emoji or a character with combining marks.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// An editing action, most are bound to a key.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Edit {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    /// Move to the start of the line.
+    Home,
+    /// Move to the end of the line.
+    End,
+    Up,
+    Down,
+    DeleteBefore,
+    DeleteAfter,
+    DeleteWordBefore,
+    DeleteWordAfter,
+    Newline,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TextInput {
+    text: String,
+    /// The byte offset of the cursor, always on a grapheme boundary.
+    cursor: usize,
+    /// Previously submitted text, oldest first.
+    history: Vec<String>,
+    /// The history entry being shown when browsing the history.
+    history_idx: Option<usize>,
+}
+
+impl TextInput {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The text before and after the cursor.
+    pub fn split_at_cursor(&self) -> (&str, &str) {
+        self.text.split_at(self.cursor)
+    }
+
+    /// Replaces the text and moves the cursor to the end.
+    pub fn set_text(&mut self, text: String) {
+        self.cursor = text.len();
+        self.text = text;
+        self.history_idx = None;
+    }
+
+    /// Replaces the text before the cursor leaving the cursor after the new text.
+    pub fn set_text_before_cursor(&mut self, before: String) {
+        let after = self.text.split_off(self.cursor);
+        self.cursor = before.len();
+        self.text = before + &after;
+        self.history_idx = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text(String::new())
+    }
+
+    pub fn insert(&mut self, ch: char) {
+        self.text.insert(self.cursor, ch);
+        self.cursor += ch.len_utf8();
+        self.history_idx = None;
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+        self.history_idx = None;
+    }
+
+    /// Apply `edit`, returns false if nothing changed.
+    pub fn edit(&mut self, edit: Edit) -> bool {
+        let cursor = self.cursor;
+        match edit {
+            Edit::Left => self.cursor = self.prev_boundary(),
+            Edit::Right => self.cursor = self.next_boundary(),
+            Edit::WordLeft => self.cursor = self.word_start(),
+            Edit::WordRight => self.cursor = self.word_end(),
+            Edit::Home => self.cursor = self.line_start(self.cursor),
+            Edit::End => self.cursor = self.line_end(self.cursor),
+            Edit::Up => return self.move_up(),
+            Edit::Down => return self.move_down(),
+            Edit::DeleteBefore => return self.delete(self.prev_boundary(), self.cursor),
+            Edit::DeleteAfter => return self.delete(self.cursor, self.next_boundary()),
+            Edit::DeleteWordBefore => return self.delete(self.word_start(), self.cursor),
+            Edit::DeleteWordAfter => return self.delete(self.cursor, self.word_end()),
+            Edit::Newline => {
+                self.insert('\n');
+                return true;
+            }
+        }
+        cursor != self.cursor
+    }
+
+    /// Remember the submitted `text` so it can be recalled with `history_previous`.
+    pub fn push_history(&mut self, text: String) {
+        if !text.trim().is_empty() && self.history.last() != Some(&text) {
+            self.history.push(text);
+        }
+        self.history_idx = None;
+    }
+
+    /// Show the previous history entry.
+    ///
+    /// History is only entered when the input is empty so a draft is never lost,
+    /// returns false if the history was not entered.
+    pub fn history_previous(&mut self) -> bool {
+        let idx = match self.history_idx {
+            Some(idx) => idx.saturating_sub(1),
+            None if self.text.is_empty() && !self.history.is_empty() => self.history.len() - 1,
+            None => return false,
+        };
+        self.show_history(idx);
+        true
+    }
+
+    /// Show the next history entry, after the newest entry the input is empty again.
+    ///
+    /// Returns false if the history is not being browsed.
+    pub fn history_next(&mut self) -> bool {
+        match self.history_idx {
+            Some(idx) if idx + 1 < self.history.len() => self.show_history(idx + 1),
+            Some(_) => self.clear(),
+            None => return false,
+        }
+        true
+    }
+
+    fn show_history(&mut self, idx: usize) {
+        self.text = self.history[idx].clone();
+        self.cursor = self.text.len();
+        self.history_idx = Some(idx);
+    }
+
+    fn delete(&mut self, start: usize, end: usize) -> bool {
+        if start == end {
+            return false;
+        }
+        self.text.replace_range(start..end, "");
+        self.cursor = start;
+        self.history_idx = None;
+        true
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(idx, _)| idx)
+            .unwrap_or(0)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map(|g| self.cursor + g.len())
+            .unwrap_or(self.cursor)
+    }
+
+    /// The start of the word before the cursor, whitespace before the cursor is skipped.
+    fn word_start(&self) -> usize {
+        let mut start = self.cursor;
+        let mut in_word = false;
+        for (idx, g) in self.text[..self.cursor].grapheme_indices(true).rev() {
+            let space = g.chars().all(char::is_whitespace);
+            if space && in_word {
+                break;
+            }
+            in_word |= !space;
+            start = idx;
+        }
+        start
+    }
+
+    /// The end of the word after the cursor, whitespace after the cursor is skipped.
+    fn word_end(&self) -> usize {
+        let mut end = self.cursor;
+        let mut in_word = false;
+        for (idx, g) in self.text[self.cursor..].grapheme_indices(true) {
+            let space = g.chars().all(char::is_whitespace);
+            if space && in_word {
+                break;
+            }
+            in_word |= !space;
+            end = self.cursor + idx + g.len();
+        }
+        end
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.text[..pos].rfind('\n').map(|idx| idx + 1).unwrap_or(0)
+    }
+
+    fn line_end(&self, pos: usize) -> usize {
+        self.text[pos..]
+            .find('\n')
+            .map(|idx| pos + idx)
+            .unwrap_or_else(|| self.text.len())
+    }
+
+    /// The byte offset of the `column`th grapheme of the line starting at `line_start`,
+    /// or the end of the line if it is shorter.
+    fn offset_in_line(&self, line_start: usize, column: usize) -> usize {
+        let line_end = self.line_end(line_start);
+        self.text[line_start..line_end]
+            .grapheme_indices(true)
+            .nth(column)
+            .map(|(idx, _)| line_start + idx)
+            .unwrap_or(line_end)
+    }
+
+    fn column(&self) -> usize {
+        self.text[self.line_start(self.cursor)..self.cursor]
+            .graphemes(true)
+            .count()
+    }
+
+    fn move_up(&mut self) -> bool {
+        let start = self.line_start(self.cursor);
+        if start == 0 {
+            return false;
+        }
+        let column = self.column();
+        let prev_start = self.line_start(start - 1);
+        self.cursor = self.offset_in_line(prev_start, column);
+        true
+    }
+
+    fn move_down(&mut self) -> bool {
+        let end = self.line_end(self.cursor);
+        if end == self.text.len() {
+            return false;
+        }
+        let column = self.column();
+        self.cursor = self.offset_in_line(end + 1, column);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn input(text: &str) -> TextInput {
+        let mut input = TextInput::default();
+        input.set_text(text.to_string());
+        input
+    }
+
+    #[test]
+    fn grapheme_editing() {
+        // the family emoji is one grapheme made of several chars
+        let mut inp = input("a👨‍👩‍👧b");
+        assert!(inp.edit(Edit::Left));
+        assert!(inp.edit(Edit::Left));
+        assert_eq!(inp.split_at_cursor(), ("a", "👨‍👩‍👧b"));
+        assert!(inp.edit(Edit::DeleteAfter));
+        assert_eq!(inp.text(), "ab");
+        inp.insert('é');
+        assert_eq!(inp.text(), "aéb");
+        inp.insert_str("e\u{301}");
+        assert_eq!(inp.text(), "aée\u{301}b");
+        // `e` and the combining accent are one grapheme
+        assert!(inp.edit(Edit::DeleteBefore));
+        assert!(inp.edit(Edit::DeleteBefore));
+        assert!(inp.edit(Edit::DeleteBefore));
+        assert!(!inp.edit(Edit::DeleteBefore));
+        assert_eq!(inp.text(), "b");
+    }
+
+    #[test]
+    fn word_editing() {
+        let mut inp = input("hello big  world");
+        assert!(inp.edit(Edit::WordLeft));
+        assert_eq!(inp.split_at_cursor(), ("hello big  ", "world"));
+        assert!(inp.edit(Edit::WordLeft));
+        assert_eq!(inp.split_at_cursor(), ("hello ", "big  world"));
+        assert!(inp.edit(Edit::WordRight));
+        assert_eq!(inp.split_at_cursor(), ("hello big", "  world"));
+        assert!(inp.edit(Edit::DeleteWordAfter));
+        assert_eq!(inp.text(), "hello big");
+        assert!(inp.edit(Edit::DeleteWordBefore));
+        assert_eq!(inp.text(), "hello ");
+        assert!(inp.edit(Edit::Home));
+        assert!(!inp.edit(Edit::WordLeft));
+    }
+
+    #[test]
+    fn multi_line() {
+        let mut inp = input("first line");
+        assert!(inp.edit(Edit::Newline));
+        inp.insert('a');
+        assert_eq!(inp.text(), "first line\na");
+        assert!(inp.edit(Edit::Up));
+        assert_eq!(inp.split_at_cursor(), ("f", "irst line\na"));
+        assert!(!inp.edit(Edit::Up));
+        assert!(inp.edit(Edit::End));
+        assert_eq!(inp.split_at_cursor(), ("first line", "\na"));
+        // the second line is shorter so the cursor goes to its end
+        assert!(inp.edit(Edit::Down));
+        assert_eq!(inp.split_at_cursor(), ("first line\na", ""));
+        assert!(!inp.edit(Edit::Down));
+        assert!(inp.edit(Edit::Home));
+        assert_eq!(inp.split_at_cursor(), ("first line\n", "a"));
+    }
+
+    #[test]
+    fn history() {
+        let mut inp = TextInput::default();
+        inp.push_history("one".into());
+        inp.push_history("two".into());
+
+        assert!(inp.history_previous());
+        assert_eq!(inp.text(), "two");
+        assert!(inp.history_previous());
+        assert_eq!(inp.text(), "one");
+        // stays at the oldest entry
+        assert!(inp.history_previous());
+        assert_eq!(inp.text(), "one");
+        assert!(inp.history_next());
+        assert_eq!(inp.text(), "two");
+        assert!(inp.history_next());
+        assert_eq!(inp.text(), "");
+        assert!(!inp.history_next());
+
+        // a draft is never replaced by history
+        inp.insert('x');
+        assert!(!inp.history_previous());
+        assert_eq!(inp.text(), "x");
+    }
+}
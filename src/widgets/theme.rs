@@ -0,0 +1,392 @@
+//! The colors of the user interface.
+//!
+//! `rumatui` comes with a dark, a light and a high contrast theme. Themes of your own go
+//! in `~/.rumatui/themes/<name>.json`, they start from one of the built-in themes and
+//! change some of its colors
+//!
+//! ```json
+//! { "base": "light", "border": "#268bd2", "selected": "magenta" }
+//! ```
+//!
+//! Colors are written as `colors::parse_color` reads them, `#rrggbb` colors are turned
+//! into the closest of the 256 colors when the terminal has no truecolor support. The
+//! theme in use is saved in `~/.rumatui/settings.json` and reloaded when its file changes.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use rumatui_tui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    widgets::colors::{self, parse_color},
+};
+
+/// The names of the colors a theme sets, in the order the settings screen shows them.
+pub const COLORS: [&str; 13] = [
+    "border",
+    "focus",
+    "title",
+    "text",
+    "secondary",
+    "accent",
+    "selected",
+    "notice",
+    "dim",
+    "link",
+    "error",
+    "warning",
+    "highlight",
+];
+
+/// The built-in themes.
+pub const BUILT_IN: [&str; 3] = ["dark", "light", "high_contrast"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// The borders of panes and dialogs.
+    pub border: Color,
+    /// The border of the focused pane.
+    pub focus: Color,
+    pub title: Color,
+    /// Room names, the send box and other everyday text.
+    pub text: Color,
+    /// Buttons, the fields of forms and preview titles.
+    pub secondary: Color,
+    /// The borders of forms and the room lists.
+    pub accent: Color,
+    /// The selected item of a list and the cursor.
+    pub selected: Color,
+    /// The header and the notification box.
+    pub notice: Color,
+    /// Timestamps, day separators and other text that should stay in the background.
+    pub dim: Color,
+    pub link: Color,
+    pub error: Color,
+    pub warning: Color,
+    /// The background of the selected message.
+    pub highlight: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// The colors `rumatui` has always had.
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".to_string(),
+            border: Color::Green,
+            focus: Color::LightCyan,
+            title: Color::Yellow,
+            text: Color::Blue,
+            secondary: Color::Cyan,
+            accent: Color::Magenta,
+            selected: Color::LightGreen,
+            notice: Color::Green,
+            dim: Color::DarkGray,
+            link: Color::Blue,
+            error: Color::Red,
+            warning: Color::Yellow,
+            highlight: Color::DarkGray,
+        }
+    }
+
+    /// Darker colors for terminals with a light background.
+    pub fn light() -> Self {
+        Self {
+            name: "light".to_string(),
+            border: Color::Indexed(28),
+            focus: Color::Indexed(25),
+            title: Color::Indexed(130),
+            text: Color::Indexed(18),
+            secondary: Color::Indexed(30),
+            accent: Color::Indexed(90),
+            selected: Color::Indexed(22),
+            notice: Color::Indexed(28),
+            dim: Color::Indexed(244),
+            link: Color::Indexed(26),
+            error: Color::Indexed(160),
+            warning: Color::Indexed(130),
+            highlight: Color::Indexed(252),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "high_contrast".to_string(),
+            border: Color::White,
+            focus: Color::LightYellow,
+            title: Color::LightYellow,
+            text: Color::White,
+            secondary: Color::LightCyan,
+            accent: Color::LightMagenta,
+            selected: Color::LightGreen,
+            notice: Color::White,
+            dim: Color::Gray,
+            link: Color::LightCyan,
+            error: Color::LightRed,
+            warning: Color::LightYellow,
+            highlight: Color::Blue,
+        }
+    }
+
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high_contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// Reads a theme file, the colors it leaves out come from its `base` theme.
+    pub fn from_json(name: &str, json: &str) -> Result<Self> {
+        let mut colors: HashMap<String, String> = serde_json::from_str(json)?;
+        let mut theme = match colors.remove("base") {
+            Some(base) => Self::built_in(&base)
+                .ok_or_else(|| Error::Json(format!("unknown base theme {}", base)))?,
+            None => Self::dark(),
+        };
+        theme.name = name.to_string();
+        for (key, value) in colors {
+            let color = parse_color(&value)
+                .ok_or_else(|| Error::Json(format!("unknown color {}", value)))?;
+            *theme
+                .color_mut(&key)
+                .ok_or_else(|| Error::Json(format!("unknown theme color {}", key)))? = color;
+        }
+        Ok(theme)
+    }
+
+    pub fn color(&self, key: &str) -> Option<Color> {
+        let mut theme = self.clone();
+        theme.color_mut(key).map(|color| *color)
+    }
+
+    fn color_mut(&mut self, key: &str) -> Option<&mut Color> {
+        let color = match key {
+            "border" => &mut self.border,
+            "focus" => &mut self.focus,
+            "title" => &mut self.title,
+            "text" => &mut self.text,
+            "secondary" => &mut self.secondary,
+            "accent" => &mut self.accent,
+            "selected" => &mut self.selected,
+            "notice" => &mut self.notice,
+            "dim" => &mut self.dim,
+            "link" => &mut self.link,
+            "error" => &mut self.error,
+            "warning" => &mut self.warning,
+            "highlight" => &mut self.highlight,
+            _ => return None,
+        };
+        Some(color)
+    }
+
+    /// Replaces `#rrggbb` colors with the closest of the 256 colors.
+    pub fn without_truecolor(mut self) -> Self {
+        for key in COLORS.iter() {
+            if let Some(color) = self.color_mut(key) {
+                *color = colors::to_indexed(*color);
+            }
+        }
+        self
+    }
+
+    /// The border of a pane, the focused pane stands out from the rest.
+    pub fn border_style(&self, focused: bool) -> Style {
+        let color = if focused { self.focus } else { self.border };
+        Style::default().fg(color).modifier(Modifier::BOLD)
+    }
+
+    pub fn title_style(&self) -> Style {
+        Style::default().fg(self.title).modifier(Modifier::BOLD)
+    }
+
+    pub fn selected_style(&self) -> Style {
+        Style::default().fg(self.selected).modifier(Modifier::BOLD)
+    }
+}
+
+/// Is the background light according to `COLORFGBG`, "15;0" is light text on black.
+pub fn is_light_background(colorfgbg: &str) -> bool {
+    match colorfgbg
+        .rsplit(';')
+        .next()
+        .and_then(|bg| bg.parse::<u8>().ok())
+    {
+        Some(bg) => bg == 7 || bg >= 9,
+        None => false,
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Settings {
+    theme: String,
+}
+
+/// The theme in use and where themes are found.
+#[derive(Clone, Debug, Default)]
+pub struct Themes {
+    /// `~/.rumatui`
+    dir: PathBuf,
+    truecolor: bool,
+    pub active: Theme,
+    /// When the file of the active theme last changed, `None` for built-in themes.
+    modified: Option<SystemTime>,
+    checked: Option<Instant>,
+}
+
+impl Themes {
+    /// Themes found in `dir` starting with the default theme for the terminal.
+    pub fn new(dir: &Path, truecolor: bool, light: bool) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            truecolor,
+            active: if light { Theme::light() } else { Theme::dark() },
+            modified: None,
+            checked: None,
+        }
+    }
+
+    /// Switches to the theme saved in `settings.json`, without one nothing changes.
+    pub fn load_saved(&mut self) -> Result<()> {
+        let path = self.dir.join("settings.json");
+        if !path.exists() {
+            return Ok(());
+        }
+        let settings: Settings = serde_json::from_str(&fs::read_to_string(path)?)?;
+        self.use_theme(&settings.theme)
+    }
+
+    /// Remembers the active theme for the next start.
+    pub async fn save(&self) -> Result<()> {
+        let settings = Settings {
+            theme: self.active.name.clone(),
+        };
+        let json = serde_json::to_string_pretty(&settings)?;
+        tokio::fs::write(self.dir.join("settings.json"), json)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// The built-in themes followed by the themes in `~/.rumatui/themes`.
+    pub fn names(&self) -> Vec<String> {
+        let mut files = fs::read_dir(self.dir.join("themes"))
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+                    .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+                    .filter(|name| !BUILT_IN.contains(&name.as_str()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        files.sort();
+        BUILT_IN
+            .iter()
+            .map(|name| name.to_string())
+            .chain(files)
+            .collect()
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join("themes").join(format!("{}.json", name))
+    }
+
+    /// The built-in theme or the theme file called `name`.
+    pub fn theme(&self, name: &str) -> Result<Theme> {
+        let theme = match Theme::built_in(name) {
+            Some(theme) => theme,
+            None => Theme::from_json(name, &fs::read_to_string(self.path(name))?)?,
+        };
+        if self.truecolor {
+            Ok(theme)
+        } else {
+            Ok(theme.without_truecolor())
+        }
+    }
+
+    pub fn use_theme(&mut self, name: &str) -> Result<()> {
+        self.active = self.theme(name)?;
+        self.modified = if Theme::built_in(name).is_some() {
+            None
+        } else {
+            fs::metadata(self.path(name))
+                .and_then(|meta| meta.modified())
+                .ok()
+        };
+        Ok(())
+    }
+
+    /// Reloads the active theme when its file changed, the file is looked at once a
+    /// second at most.
+    ///
+    /// Returns `None` when nothing was reloaded.
+    pub fn reload_if_changed(&mut self, now: Instant) -> Option<Result<()>> {
+        let modified = self.modified?;
+        if let Some(checked) = self.checked {
+            if now.duration_since(checked) < Duration::from_secs(1) {
+                return None;
+            }
+        }
+        self.checked = Some(now);
+
+        let changed = fs::metadata(self.path(&self.active.name))
+            .and_then(|meta| meta.modified())
+            .ok()?;
+        if changed == modified {
+            return None;
+        }
+        let name = self.active.name.clone();
+        match self.use_theme(&name) {
+            Ok(()) => Some(Ok(())),
+            Err(e) => {
+                // report a broken file once, not every second
+                self.modified = Some(changed);
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn theme_files() {
+        let theme =
+            Theme::from_json("mine", r##"{ "base": "light", "border": "#268bd2" }"##).unwrap();
+        assert_eq!(theme.name, "mine");
+        assert_eq!(theme.border, Color::Rgb(0x26, 0x8b, 0xd2));
+        assert_eq!(theme.title, Theme::light().title);
+        assert_eq!(theme.color("border"), Some(theme.border));
+
+        // 0x26, 0x8b and 0xd2 are closest to the levels 0, 2 and 4 of the color cube
+        let indexed = theme.without_truecolor();
+        assert_eq!(indexed.border, Color::Indexed(16 + 2 * 6 + 4));
+        assert_eq!(indexed.title, Theme::light().title);
+
+        assert!(Theme::from_json("bad", r#"{ "base": "sepia" }"#).is_err());
+        assert!(Theme::from_json("bad", r#"{ "boarder": "red" }"#).is_err());
+        assert!(Theme::from_json("bad", r#"{ "border": "mauve" }"#).is_err());
+    }
+
+    #[test]
+    fn light_backgrounds() {
+        assert!(!is_light_background("15;0"));
+        assert!(is_light_background("0;15"));
+        assert!(is_light_background("0;default;7"));
+        assert!(!is_light_background("default"));
+    }
+}
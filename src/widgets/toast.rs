@@ -0,0 +1,264 @@
+//! Errors and warnings shown in the header without interrupting the user.
+//!
+//! Only fatal errors take over the screen, everything else is queued as a toast and
+//! every error is kept in a history that can be viewed with Ctrl-l.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use rumatui_tui::{
+    backend::Backend,
+    layout::Rect,
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Paragraph, Text},
+    Frame,
+};
+
+use crate::{
+    client::client_loop::UserRequest,
+    widgets::{theme::Theme, RenderWidget},
+};
+
+/// Warnings are dismissed on their own after being shown this long.
+const WARNING_TIMEOUT: Duration = Duration::from_secs(8);
+/// The number of errors kept in the history.
+const HISTORY_LEN: usize = 100;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    /// Something failed that the user can safely ignore.
+    Warning,
+    /// Something the user asked for failed.
+    Error,
+    /// `rumatui` can not continue, shown in place of the whole UI.
+    Fatal,
+}
+
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::Fatal => "fatal",
+        }
+    }
+
+    fn style(self, theme: &Theme) -> Style {
+        match self {
+            Self::Warning => Style::default().fg(theme.warning),
+            Self::Error => Style::default().fg(theme.error).modifier(Modifier::BOLD),
+            Self::Fatal => Style::default()
+                .fg(theme.error)
+                .modifier(Modifier::BOLD | Modifier::REVERSED),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Toast {
+    pub severity: Severity,
+    pub message: String,
+    /// The request that failed, sent again if the user retries.
+    pub retry: Option<UserRequest>,
+    /// When the toast was first shown.
+    shown: Option<Instant>,
+}
+
+/// An error in the error history.
+#[derive(Clone, Debug)]
+pub struct Logged {
+    pub time: SystemTime,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Toasts {
+    /// Toasts waiting to be shown, the front toast is the one shown.
+    queue: VecDeque<Toast>,
+    /// Every error, oldest first.
+    history: VecDeque<Logged>,
+    /// Show the error history in place of the main screen.
+    pub show_history: bool,
+}
+
+impl Toasts {
+    /// Adds `message` to the history and queues a toast unless it is fatal.
+    ///
+    /// # Arguments
+    ///
+    /// * message - The full error, the toast shows only the last line.
+    ///
+    /// * retry - The request to send again if the user retries.
+    pub fn push(&mut self, severity: Severity, message: String, retry: Option<UserRequest>) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(Logged {
+            time: SystemTime::now(),
+            severity,
+            message: message.clone(),
+        });
+
+        if severity != Severity::Fatal {
+            let message = message.lines().last().unwrap_or_default().to_string();
+            self.queue.push_back(Toast {
+                severity,
+                message,
+                retry,
+                shown: None,
+            });
+        }
+    }
+
+    /// The toast to show at `now`, warnings that have been shown long enough are dismissed.
+    pub fn current(&mut self, now: Instant) -> Option<&Toast> {
+        while let Some(toast) = self.queue.front_mut() {
+            match toast.shown {
+                Some(shown)
+                    if toast.severity == Severity::Warning
+                        && now.saturating_duration_since(shown) > WARNING_TIMEOUT =>
+                {
+                    self.queue.pop_front();
+                }
+                Some(_) => break,
+                None => {
+                    toast.shown = Some(now);
+                    break;
+                }
+            }
+        }
+        self.queue.front()
+    }
+
+    /// The number of toasts waiting behind the current one.
+    pub fn waiting(&self) -> usize {
+        self.queue.len().saturating_sub(1)
+    }
+
+    /// Dismiss the current toast.
+    pub fn dismiss(&mut self) -> Option<Toast> {
+        self.queue.pop_front()
+    }
+
+    /// Dismiss the current toast and return the request to retry if it has one.
+    pub fn take_retry(&mut self) -> Option<UserRequest> {
+        if self.queue.front()?.retry.is_some() {
+            self.queue.pop_front().and_then(|toast| toast.retry)
+        } else {
+            None
+        }
+    }
+
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &Logged> {
+        self.history.iter()
+    }
+
+    /// The current toast as it is shown in the header.
+    pub fn header_text(&mut self, now: Instant, theme: &Theme) -> Option<Vec<Text<'static>>> {
+        let waiting = self.waiting();
+        let toast = self.current(now)?;
+        let mut text = vec![Text::styled(
+            format!("{}: {}", toast.severity.label(), toast.message),
+            toast.severity.style(theme),
+        )];
+        let mut hint = String::from(" (");
+        if toast.retry.is_some() {
+            hint.push_str("Ctrl-r retry, ");
+        }
+        hint.push_str("Esc dismiss");
+        if waiting > 0 {
+            hint.push_str(&format!(", {} more", waiting));
+        }
+        hint.push(')');
+        text.push(Text::styled(hint, Style::default().fg(theme.dim)));
+        Some(text)
+    }
+}
+
+/// Shows the error history newest first.
+#[derive(Debug)]
+pub struct ErrorHistoryWidget<'t>(pub &'t Toasts);
+
+impl<'t> RenderWidget for ErrorHistoryWidget<'t> {
+    fn render<B>(&mut self, f: &mut Frame<B>, area: Rect, theme: &Theme)
+    where
+        B: Backend,
+    {
+        let mut text = vec![];
+        for logged in self.0.history().rev() {
+            let time = DateTime::<Local>::from(logged.time).format("%H:%M:%S");
+            text.push(Text::styled(
+                format!("{} {}\n", time, logged.severity.label()),
+                logged.severity.style(theme),
+            ));
+            text.push(Text::raw(format!("{}\n\n", logged.message)));
+        }
+        if text.is_empty() {
+            text.push(Text::styled(
+                "No errors so far",
+                Style::default().fg(theme.notice),
+            ));
+        }
+
+        let p = Paragraph::new(text.iter())
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.border_style(false))
+                    .title("Errors (Ctrl-l or Esc to close)")
+                    .title_style(theme.title_style()),
+            )
+            .wrap(true);
+        f.render_widget(p, area)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn queue_and_history() {
+        let mut toasts = Toasts::default();
+        let now = Instant::now();
+        assert!(toasts.current(now).is_none());
+
+        toasts.push(
+            Severity::Warning,
+            "An error occurred.\ntyping notice failed".into(),
+            None,
+        );
+        toasts.push(
+            Severity::Error,
+            "leaving failed".into(),
+            Some(UserRequest::DirectRooms),
+        );
+        toasts.push(Severity::Fatal, "channel closed".into(), None);
+
+        // only the last line is shown and fatal errors are never toasts
+        assert_eq!(toasts.current(now).unwrap().message, "typing notice failed");
+        assert_eq!(toasts.waiting(), 1);
+        assert_eq!(toasts.history().count(), 3);
+
+        // warnings go away on their own, errors wait to be dismissed
+        let later = now + WARNING_TIMEOUT + Duration::from_secs(1);
+        assert_eq!(toasts.current(later).unwrap().severity, Severity::Error);
+        let much_later = later + WARNING_TIMEOUT * 10;
+        assert_eq!(
+            toasts.current(much_later).unwrap().severity,
+            Severity::Error
+        );
+
+        assert!(matches!(
+            toasts.take_retry(),
+            Some(UserRequest::DirectRooms)
+        ));
+        assert!(toasts.current(much_later).is_none());
+        assert!(toasts.take_retry().is_none());
+        assert!(toasts.dismiss().is_none());
+    }
+}
@@ -1,11 +1,22 @@
 use std::{
+    collections::HashMap,
+    convert::TryFrom,
     fmt::{self, Display},
     io::{self, ErrorKind, Write},
+    ops::Range,
 };
 
 use comrak;
+use matrix_sdk::{
+    events::room::message::{
+        EmoteMessageEventContent, MessageEventContent, TextMessageEventContent,
+    },
+    identifiers::UserId,
+    Room,
+};
 use mdcat::{self, ResourceAccess, Settings, TerminalCapabilities, TerminalSize};
-use pulldown_cmark::{Options, Parser};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use regex::Regex;
 use syntect::parsing::SyntaxSet;
 
 use crate::error::{Error, Result};
@@ -57,6 +68,307 @@ pub(crate) fn markdown_to_terminal(input: &str) -> Result<String> {
     Ok(w.to_string())
 }
 
+lazy_static::lazy_static! {
+    /// Matches `@localpart` or a full `@localpart:server` user id, trailing punctuation is not included.
+    static ref MENTION: Regex =
+        Regex::new(r"@[\w.=\-/]*[\w=\-/](:[\w.\-]*[\w\-](:\d+)?)?").unwrap();
+    /// Matches a `http` or `https` URL, trailing punctuation is removed by `find_links`.
+    static ref URL: Regex = Regex::new(r#"https?://[^\s<>"\x{2800}]+"#).unwrap();
+}
+
+/// The byte ranges of the URLs in `text`.
+///
+/// Punctuation ending a sentence is not part of the URL, neither is a closing parenthesis
+/// without an opening one in the URL.
+pub(crate) fn find_links(text: &str) -> Vec<Range<usize>> {
+    URL.find_iter(text)
+        .map(|found| {
+            let mut url = found.as_str();
+            loop {
+                let unbalanced =
+                    url.ends_with(')') && url.matches('(').count() < url.matches(')').count();
+                if unbalanced || url.ends_with(&['.', ',', ';', ':', '!', '?', '\'', ']'][..]) {
+                    url = &url[..url.len() - 1];
+                } else {
+                    break;
+                }
+            }
+            found.start()..found.start() + url.len()
+        })
+        .collect()
+}
+
 pub(crate) fn markdown_to_html(input: &str) -> String {
-    comrak::markdown_to_html(input, &comrak::ComrakOptions::default())
+    let options = comrak::ComrakOptions {
+        // a new line in a chat message is always a line break
+        hardbreaks: true,
+        ext_strikethrough: true,
+        ..comrak::ComrakOptions::default()
+    };
+    comrak::markdown_to_html(input, &options)
+}
+
+/// Is there any markdown formatting in `input` that would render differently as HTML.
+///
+/// Text split across lines with no other formatting is plain text.
+pub(crate) fn is_markdown(input: &str) -> bool {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    Parser::new_ext(input, options).any(|ev| match ev {
+        Event::Start(Tag::Paragraph) | Event::End(Tag::Paragraph) => false,
+        Event::Text(_) | Event::SoftBreak => false,
+        _ => true,
+    })
+}
+
+/// Replaces `@user` mentions of room members with matrix.to links, the markdown for a pill.
+///
+/// A mention is either a full user id or the localpart or display name of a member.
+/// Mentions in code and links are left alone. Returns the new markdown and whether any
+/// pills were added.
+///
+/// # Arguments
+///
+/// * input - The markdown text of the message.
+///
+/// * members - The `UserId` and display name of each member of the room.
+pub(crate) fn mention_pills(input: &str, members: &[(UserId, String)]) -> (String, bool) {
+    let mut ranges = vec![];
+    let mut in_code_or_link = 0;
+    for (ev, range) in Parser::new(input).into_offset_iter() {
+        match ev {
+            Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::Link(..)) => in_code_or_link += 1,
+            Event::End(Tag::CodeBlock(_)) | Event::End(Tag::Link(..)) => in_code_or_link -= 1,
+            Event::Text(_) if in_code_or_link == 0 => ranges.push(range),
+            _ => {}
+        }
+    }
+
+    let mut output = String::with_capacity(input.len());
+    let mut last = 0;
+    let mut found = false;
+    for range in ranges {
+        for mention in MENTION.find_iter(&input[range.clone()]) {
+            let start = range.start + mention.start();
+            // skip email addresses and user ids that are part of a url
+            if input[..start]
+                .chars()
+                .last()
+                .map_or(false, |c| c.is_alphanumeric() || "/:.@".contains(c))
+            {
+                continue;
+            }
+            if let Some((user, name)) = find_mentioned(mention.as_str(), members) {
+                output.push_str(&input[last..start]);
+                output.push_str(&format!(
+                    "[{}](https://matrix.to/#/{})",
+                    name.replace('[', "\\[").replace(']', "\\]"),
+                    user
+                ));
+                last = range.start + mention.end();
+                found = true;
+            }
+        }
+    }
+    output.push_str(&input[last..]);
+    (output, found)
+}
+
+/// Finds the user a mention refers to, a valid user id is always a mention.
+fn find_mentioned(mention: &str, members: &[(UserId, String)]) -> Option<(UserId, String)> {
+    if let Ok(user) = UserId::try_from(mention) {
+        let name = members
+            .iter()
+            .find(|(id, _)| id == &user)
+            .map(|(_, name)| name.clone())
+            .unwrap_or_else(|| user.localpart().to_string());
+        return Some((user, name));
+    }
+
+    let name = mention.trim_start_matches('@').to_lowercase();
+    let mut matches = members.iter().filter(|(id, display)| {
+        id.localpart().to_lowercase() == name || display.to_lowercase() == name
+    });
+    match (matches.next(), matches.next()) {
+        (Some(member), None) => Some(member.clone()),
+        // no member or the mention is ambiguous
+        _ => None,
+    }
+}
+
+/// The text displayed for a message or `None` if the message type is not displayed.
+///
+/// Formatted messages are rendered for the terminal and emotes are prefixed with `*`.
+pub(crate) fn message_text(content: &MessageEventContent) -> Option<String> {
+    match content {
+        MessageEventContent::Text(TextMessageEventContent {
+            body, formatted, ..
+        }) => {
+            let msg = if formatted
+                .as_ref()
+                .map(|f| f.body.to_string())
+                .unwrap_or(body.to_string())
+                != *body
+            {
+                // This is extremely expensive
+                // TODO cache these results somehow
+                markdown_to_terminal(body).unwrap_or(body.clone())
+            } else {
+                body.clone()
+            };
+            Some(msg)
+        }
+        MessageEventContent::Emote(EmoteMessageEventContent { body, .. }) => {
+            Some(format!("* {}", body))
+        }
+        _ => None,
+    }
+}
+
+/// The names of the joined and invited members of `room`.
+///
+/// A name shared by more than one member is followed by the member's user id, as the
+/// spec's display name disambiguation asks.
+pub(crate) fn member_names(room: &Room) -> HashMap<UserId, String> {
+    let members = room
+        .joined_members
+        .iter()
+        .chain(room.invited_members.iter())
+        .map(|(id, member)| (id.clone(), member.name()))
+        .collect();
+    disambiguate(members).into_iter().collect()
+}
+
+/// The name of `user` in `room` as `member_names` has it, the localpart for users that
+/// are not members.
+pub(crate) fn display_name(room: &Room, user: &UserId) -> String {
+    let name = match room
+        .joined_members
+        .get(user)
+        .or_else(|| room.invited_members.get(user))
+    {
+        Some(member) => member.name(),
+        None => return user.localpart().to_string(),
+    };
+    // only the members sharing the name matter, this runs for every message
+    let shared = room
+        .joined_members
+        .values()
+        .chain(room.invited_members.values())
+        .filter(|member| member.name() == name)
+        .count();
+    if shared > 1 {
+        format!("{} ({})", name, user)
+    } else {
+        name
+    }
+}
+
+fn disambiguate(members: Vec<(UserId, String)>) -> Vec<(UserId, String)> {
+    let mut counts = HashMap::new();
+    for (_, name) in &members {
+        *counts.entry(name.clone()).or_insert(0) += 1;
+    }
+    members
+        .into_iter()
+        .map(|(id, name)| {
+            if counts[&name] > 1 {
+                let name = format!("{} ({})", name, id);
+                (id, name)
+            } else {
+                (id, name)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn members() -> Vec<(UserId, String)> {
+        vec![
+            (
+                UserId::try_from("@devin:matrix.org").unwrap(),
+                "Devin R".into(),
+            ),
+            (
+                UserId::try_from("@example:example.org").unwrap(),
+                "example".into(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn detect_markdown() {
+        assert!(!is_markdown("hello world"));
+        assert!(!is_markdown("hello\nworld"));
+        assert!(is_markdown("**hello**"));
+        assert!(is_markdown("* one\n* two"));
+        assert!(is_markdown(
+            "[rumatui](https://github.com/DevinR528/rumatui)"
+        ));
+        assert!(is_markdown("`code`"));
+    }
+
+    #[test]
+    fn mentions_become_pills() {
+        let (md, found) = mention_pills("hi @devin, and @example:example.org.", &members());
+        assert!(found);
+        assert_eq!(
+            md,
+            "hi [Devin R](https://matrix.to/#/@devin:matrix.org), and \
+             [example](https://matrix.to/#/@example:example.org)."
+        );
+
+        let (md, found) = mention_pills("`@devin` and @nobody", &members());
+        assert!(!found);
+        assert_eq!(md, "`@devin` and @nobody");
+
+        let (_, found) = mention_pills(
+            "mail devin@devin.dev or see https://matrix.to/#/@devin:matrix.org",
+            &members(),
+        );
+        assert!(!found);
+    }
+
+    #[test]
+    fn links_are_found() {
+        let links = |text: &'static str| {
+            find_links(text)
+                .into_iter()
+                .map(|range| &text[range])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            links("see https://matrix.org/docs, or http://example.org."),
+            vec!["https://matrix.org/docs", "http://example.org"]
+        );
+        assert_eq!(
+            links("(https://en.wikipedia.org/wiki/Rust_(programming_language))"),
+            vec!["https://en.wikipedia.org/wiki/Rust_(programming_language)"]
+        );
+        assert!(links("no links in ftp://here or www.example.org").is_empty());
+    }
+
+    #[test]
+    fn shared_names_are_disambiguated() {
+        let mut members = members();
+        members.push((
+            UserId::try_from("@imposter:example.org").unwrap(),
+            "Devin R".into(),
+        ));
+        let names = disambiguate(members)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "Devin R (@devin:matrix.org)",
+                "example",
+                "Devin R (@imposter:example.org)"
+            ]
+        );
+    }
 }